@@ -0,0 +1,298 @@
+//! Password-encrypted wallet backup archives.
+//!
+//! An archive is a single JSON document holding the serialized [`Wallet`]
+//! encrypted with AES-256-CTR, and since version 2 optionally the wallet's
+//! [`TxJournal`] encrypted the same way. The key is derived from the password
+//! with scrypt, and an HMAC-SHA256 over the header and ciphertexts protects
+//! the archive against corruption and tampering.
+//!
+//! [`Wallet`]: crate::wallet::Wallet
+//! [`TxJournal`]: crate::journal::TxJournal
+
+use crate::error::{Result, WalletError};
+use aes::cipher::{KeyIvInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Identifies the file as an mcp-wallet backup.
+pub const BACKUP_FORMAT: &str = "mcp-wallet-backup";
+/// Current archive version.
+pub const BACKUP_VERSION: u32 = 2;
+/// Oldest archive version that can still be restored; version 1 has no
+/// journal.
+pub const MIN_BACKUP_VERSION: u32 = 1;
+
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+type HmacSha256 = Hmac<Sha256>;
+
+/// Scrypt parameters used to derive the archive key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Hex-encoded random salt.
+    pub salt: String,
+    /// Base-2 logarithm of the scrypt cost parameter `N`.
+    pub log_n: u8,
+    /// Scrypt block size.
+    pub r: u32,
+    /// Scrypt parallelization.
+    pub p: u32,
+}
+
+/// A part of the archive encrypted with its own IV.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncryptedSection {
+    /// Hex-encoded AES-CTR initialization vector.
+    pub iv: String,
+    /// Hex-encoded ciphertext.
+    pub ciphertext: String,
+}
+
+/// The decrypted contents of a [`BackupArchive`].
+#[derive(Debug)]
+pub struct BackupContents {
+    /// The wallet JSON.
+    pub wallet: Zeroizing<Vec<u8>>,
+    /// The transaction journal JSON, if it was exported.
+    pub journal: Option<Vec<u8>>,
+}
+
+/// An encrypted, versioned wallet backup.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupArchive {
+    /// Always [`BACKUP_FORMAT`].
+    pub format: String,
+    /// Archive version, see [`BACKUP_VERSION`].
+    pub version: u32,
+    /// Creation time in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Key derivation parameters.
+    pub kdf: KdfParams,
+    /// Hex-encoded AES-CTR initialization vector.
+    pub iv: String,
+    /// Hex-encoded encrypted wallet JSON.
+    pub ciphertext: String,
+    /// The encrypted transaction journal JSON, if it was exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal: Option<EncryptedSection>,
+    /// Hex-encoded HMAC-SHA256 over the header fields and the ciphertexts.
+    pub mac: String,
+}
+
+impl BackupArchive {
+    /// Encrypts the `wallet` and, if given, the `journal` JSON with a key
+    /// derived from `password`.
+    pub fn seal(wallet: &[u8], journal: Option<&[u8]>, password: &str) -> Result<Self> {
+        let mut salt = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let kdf = KdfParams {
+            salt: hex::encode(salt),
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+        };
+        let (enc_key, mac_key) = derive_keys(password, &kdf)?;
+        let wallet = encrypt(&enc_key, wallet);
+        let journal = journal.map(|journal| encrypt(&enc_key, journal));
+
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut archive = Self {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created_at,
+            kdf,
+            iv: wallet.iv,
+            ciphertext: wallet.ciphertext,
+            journal,
+            mac: String::new(),
+        };
+        archive.mac = hex::encode(archive.compute_mac(&mac_key)?);
+        Ok(archive)
+    }
+
+    /// Verifies the archive and decrypts it with `password`.
+    ///
+    /// A wrong password and a corrupted archive both fail the integrity check.
+    pub fn open(&self, password: &str) -> Result<BackupContents> {
+        if self.format != BACKUP_FORMAT {
+            return Err(WalletError::BackupError(format!(
+                "not an mcp-wallet backup (format '{}')",
+                self.format
+            )));
+        }
+        if !(MIN_BACKUP_VERSION..=BACKUP_VERSION).contains(&self.version) {
+            return Err(WalletError::BackupError(format!(
+                "unsupported backup version {}",
+                self.version
+            )));
+        }
+
+        let (enc_key, mac_key) = derive_keys(password, &self.kdf)?;
+        let expected = hex::decode(&self.mac)?;
+        self.mac_state(&mac_key)?
+            .verify_slice(&expected)
+            .map_err(|_| {
+                WalletError::BackupError(
+                    "integrity check failed (wrong password or corrupted backup)".to_string(),
+                )
+            })?;
+
+        let wallet = decrypt(&enc_key, &self.iv, &self.ciphertext)?;
+        let journal = self
+            .journal
+            .as_ref()
+            .map(|journal| decrypt(&enc_key, &journal.iv, &journal.ciphertext))
+            .transpose()?
+            .map(|journal| journal.to_vec());
+        Ok(BackupContents { wallet, journal })
+    }
+
+    fn compute_mac(&self, mac_key: &[u8; 32]) -> Result<Vec<u8>> {
+        Ok(self.mac_state(mac_key)?.finalize().into_bytes().to_vec())
+    }
+
+    /// Feeds the header fields and the ciphertexts to the MAC, so neither
+    /// can be altered.
+    fn mac_state(&self, mac_key: &[u8; 32]) -> Result<HmacSha256> {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(mac_key)
+            .map_err(|e| WalletError::BackupError(e.to_string()))?;
+        let header = format!(
+            "{}:{}:{}:{}:{}:{}:{}:{}",
+            self.format,
+            self.version,
+            self.created_at,
+            self.kdf.salt,
+            self.kdf.log_n,
+            self.kdf.r,
+            self.kdf.p,
+            self.iv
+        );
+        mac.update(header.as_bytes());
+        mac.update(&hex::decode(&self.ciphertext)?);
+        if let Some(journal) = &self.journal {
+            mac.update(journal.iv.as_bytes());
+            mac.update(&hex::decode(&journal.ciphertext)?);
+        }
+        Ok(mac)
+    }
+}
+
+/// Encrypts `plaintext` under a fresh random IV.
+fn encrypt(enc_key: &[u8; 32], plaintext: &[u8]) -> EncryptedSection {
+    let mut iv = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut iv);
+    let mut ciphertext = plaintext.to_vec();
+    Aes256Ctr::new(enc_key.into(), &iv.into()).apply_keystream(&mut ciphertext);
+    EncryptedSection {
+        iv: hex::encode(iv),
+        ciphertext: hex::encode(ciphertext),
+    }
+}
+
+fn decrypt(enc_key: &[u8; 32], iv: &str, ciphertext: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let iv: [u8; 16] = hex::decode(iv)?
+        .try_into()
+        .map_err(|_| WalletError::BackupError("invalid IV length".to_string()))?;
+    let mut plaintext = Zeroizing::new(hex::decode(ciphertext)?);
+    Aes256Ctr::new(enc_key.into(), &iv.into()).apply_keystream(&mut plaintext);
+    Ok(plaintext)
+}
+
+/// A 256-bit key that is wiped when dropped.
+type Key = Zeroizing<[u8; 32]>;
+
+/// Derives the encryption and MAC keys from the password.
+///
+/// The keys and the intermediate buffer are wiped when dropped.
+fn derive_keys(password: &str, kdf: &KdfParams) -> Result<(Key, Key)> {
+    // An archive asking for more work or memory than an export uses would
+    // stall the import, whatever its password
+    if kdf.log_n > SCRYPT_LOG_N || kdf.r > SCRYPT_R || kdf.p > SCRYPT_P {
+        return Err(WalletError::BackupError(format!(
+            "scrypt parameters log_n={}, r={}, p={} exceed the supported {}, {}, {}",
+            kdf.log_n, kdf.r, kdf.p, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P
+        )));
+    }
+    let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p)
+        .map_err(|e| WalletError::BackupError(e.to_string()))?;
+    let salt = hex::decode(&kdf.salt)?;
+    let mut derived = Zeroizing::new([0u8; 64]);
+    scrypt::scrypt(password.as_bytes(), &salt, &params, derived.as_mut())
+        .map_err(|e| WalletError::BackupError(e.to_string()))?;
+
+    let mut enc_key = Key::default();
+    let mut mac_key = Key::default();
+    enc_key.copy_from_slice(&derived[..32]);
+    mac_key.copy_from_slice(&derived[32..]);
+    Ok((enc_key, mac_key))
+}
+
+/// How a backup is applied to an existing wallet.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    /// Add missing accounts and aliases, keep everything already present.
+    #[default]
+    Merge,
+    /// Replace the wallet contents with the backup.
+    Replace,
+}
+
+/// Describes what a restore changed, or would change in a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RestoreReport {
+    /// The mode the backup was applied with.
+    pub mode: RestoreMode,
+    /// Whether the wallet was left untouched.
+    pub dry_run: bool,
+    /// Accounts that are added.
+    pub accounts_added: Vec<String>,
+    /// Accounts that are removed (replace mode only).
+    pub accounts_removed: Vec<String>,
+    /// Watch-only accounts that gain a private key.
+    pub keys_added: Vec<String>,
+    /// Accounts whose nonce changes.
+    pub nonces_changed: Vec<String>,
+    /// Aliases that are added or moved to another address.
+    pub aliases_set: Vec<String>,
+    /// Aliases that are removed (replace mode only).
+    pub aliases_removed: Vec<String>,
+    /// Aliases left unchanged because they point elsewhere locally (merge mode only).
+    pub alias_conflicts: Vec<String>,
+    /// Journaled transactions that are added, by hash.
+    pub transactions_added: Vec<String>,
+    /// Journaled transactions that are removed, by hash (replace mode only).
+    pub transactions_removed: Vec<String>,
+}
+
+impl RestoreReport {
+    /// Sorts every list so reports are stable across runs.
+    pub(crate) fn sort(&mut self) {
+        for list in [
+            &mut self.accounts_added,
+            &mut self.accounts_removed,
+            &mut self.keys_added,
+            &mut self.nonces_changed,
+            &mut self.aliases_set,
+            &mut self.aliases_removed,
+            &mut self.alias_conflicts,
+            &mut self.transactions_added,
+            &mut self.transactions_removed,
+        ] {
+            list.sort();
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Block queries.
+//!
+//! Blocks are looked up by number, hash or tag and summarized with the fields
+//! agents usually need. [`closest_block`] finds the block nearest to a unix
+//! timestamp and [`block_at`] the one whose state was current at that time,
+//! by binary search over block numbers, which takes about `log2(head)`
+//! requests.
+
+use crate::{
+    error::{Result, WalletError},
+    eth_client::EthClient,
+};
+use ethers::{
+    types::{Block, BlockId, BlockNumber, Transaction, H256},
+    utils::to_checksum,
+};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Transactions of a block, as hashes or in full.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum BlockTransactions {
+    /// Only the transaction hashes.
+    Hashes(Vec<H256>),
+    /// The full transactions.
+    Full(Vec<Transaction>),
+}
+
+/// The commonly needed fields of a block.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockSummary {
+    /// The block number; `None` for a pending block.
+    pub number: Option<u64>,
+    /// The block hash; `None` for a pending block.
+    pub hash: Option<H256>,
+    /// The parent block hash.
+    pub parent_hash: H256,
+    /// The block time in unix seconds.
+    pub timestamp: u64,
+    /// The fee recipient, checksummed.
+    pub miner: Option<String>,
+    /// Gas used by all transactions, in decimal.
+    pub gas_used: String,
+    /// The block gas limit, in decimal.
+    pub gas_limit: String,
+    /// The base fee in wei, in decimal; `None` before London.
+    pub base_fee_per_gas: Option<String>,
+    /// Number of transactions in the block.
+    pub transaction_count: usize,
+    /// The transactions, as hashes or in full.
+    pub transactions: BlockTransactions,
+}
+
+impl BlockSummary {
+    fn new<TX>(block: &Block<TX>, transactions: BlockTransactions) -> Self {
+        Self {
+            number: block.number.map(|n| n.as_u64()),
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            timestamp: block.timestamp.low_u64(),
+            miner: block.author.map(|a| to_checksum(&a, None)),
+            gas_used: block.gas_used.to_string(),
+            gas_limit: block.gas_limit.to_string(),
+            base_fee_per_gas: block.base_fee_per_gas.map(|f| f.to_string()),
+            transaction_count: block.transactions.len(),
+            transactions,
+        }
+    }
+}
+
+/// Parses a block number (decimal or `0x` hex), a 32-byte block hash, or one
+/// of the tags `latest`, `earliest`, `safe`, `finalized` and `pending`.
+pub fn parse_block_id(input: &str) -> Result<BlockId> {
+    let input = input.trim();
+    let tag = match input.to_ascii_lowercase().as_str() {
+        "latest" => Some(BlockNumber::Latest),
+        "earliest" => Some(BlockNumber::Earliest),
+        "safe" => Some(BlockNumber::Safe),
+        "finalized" => Some(BlockNumber::Finalized),
+        "pending" => Some(BlockNumber::Pending),
+        _ => None,
+    };
+    if let Some(tag) = tag {
+        return Ok(tag.into());
+    }
+    let invalid = || WalletError::WalletError(format!("Invalid block: {}", input));
+    match input.strip_prefix("0x") {
+        Some(hex) if hex.len() == 64 => H256::from_str(hex)
+            .map(BlockId::Hash)
+            .map_err(|_| invalid()),
+        Some(hex) => u64::from_str_radix(hex, 16)
+            .map(|n| BlockNumber::Number(n.into()).into())
+            .map_err(|_| invalid()),
+        None => input
+            .parse::<u64>()
+            .map(|n| BlockNumber::Number(n.into()).into())
+            .map_err(|_| invalid()),
+    }
+}
+
+/// Resolves a block id to a block number, looking up tags and hashes.
+pub async fn resolve_number(client: &EthClient, id: BlockId) -> Result<u64> {
+    if let BlockId::Number(BlockNumber::Number(number)) = id {
+        return Ok(number.as_u64());
+    }
+    client
+        .get_block(id)
+        .await?
+        .and_then(|b| b.number)
+        .map(|n| n.as_u64())
+        .ok_or_else(|| WalletError::WalletError(format!("Block not found: {:?}", id)))
+}
+
+/// Gets a block summary, with full transactions if `full`.
+pub async fn get_block(
+    client: &EthClient,
+    id: BlockId,
+    full: bool,
+) -> Result<Option<BlockSummary>> {
+    if full {
+        let block = client.get_block_with_txs(id).await?;
+        Ok(block.map(|b| BlockSummary::new(&b, BlockTransactions::Full(b.transactions.clone()))))
+    } else {
+        Ok(client.get_block(id).await?.as_ref().map(summary))
+    }
+}
+
+/// Finds the block whose timestamp is closest to `timestamp` (unix seconds).
+///
+/// Times before genesis give the genesis block and times after the head give
+/// the head. Of two blocks equally close, the earlier one is returned.
+pub async fn closest_block(client: &EthClient, timestamp: u64) -> Result<BlockSummary> {
+    let block = match bracket(client, timestamp).await? {
+        (Some(before), Some(after)) => {
+            let distance_before = timestamp - before.timestamp.low_u64();
+            let distance_after = after.timestamp.low_u64() - timestamp;
+            if distance_before <= distance_after {
+                before
+            } else {
+                after
+            }
+        }
+        (Some(block), None) | (None, Some(block)) => block,
+        (None, None) => unreachable!("the head is before or after any time"),
+    };
+    Ok(summary(&block))
+}
+
+/// Finds the last block mined at or before `timestamp` (unix seconds), whose
+/// state was the current one at that time.
+///
+/// Times after the head give the head; times before genesis are an error.
+pub async fn block_at(client: &EthClient, timestamp: u64) -> Result<BlockSummary> {
+    match bracket(client, timestamp).await? {
+        (Some(block), _) => Ok(summary(&block)),
+        (None, _) => Err(WalletError::WalletError(format!(
+            "No block was mined at or before {}",
+            timestamp
+        ))),
+    }
+}
+
+/// Finds the last block at or before `timestamp` and the first one after it,
+/// if any.
+async fn bracket(
+    client: &EthClient,
+    timestamp: u64,
+) -> Result<(Option<Block<H256>>, Option<Block<H256>>)> {
+    let header = |id: BlockId| async move {
+        client.get_block(id).await?.ok_or_else(|| {
+            WalletError::WalletError(format!("The node did not return block {:?}", id))
+        })
+    };
+    let mut high = header(BlockNumber::Latest.into()).await?;
+    if high.timestamp.low_u64() <= timestamp {
+        return Ok((Some(high), None));
+    }
+    let mut low = header(BlockNumber::Number(0u64.into()).into()).await?;
+    if low.timestamp.low_u64() > timestamp {
+        return Ok((None, Some(low)));
+    }
+
+    // Invariant: low is at or before the target, high after it
+    let number = |b: &Block<H256>| b.number.unwrap_or_default().as_u64();
+    while number(&high) - number(&low) > 1 {
+        let middle = number(&low) + (number(&high) - number(&low)) / 2;
+        let block = header(BlockNumber::Number(middle.into()).into()).await?;
+        if block.timestamp.low_u64() <= timestamp {
+            low = block;
+        } else {
+            high = block;
+        }
+    }
+    Ok((Some(low), Some(high)))
+}
+
+pub(crate) fn summary(block: &Block<H256>) -> BlockSummary {
+    BlockSummary::new(block, BlockTransactions::Hashes(block.transactions.clone()))
+}
@@ -0,0 +1,152 @@
+//! Caching of RPC results that cannot change.
+//!
+//! An [`crate::eth_client::EthClient`] keeps the chain id and, once their block
+//! is finalized, receipts, blocks and code read at a block number, so repeated
+//! lookups do not go to the node again. Anything younger than the finalized block can
+//! still be reorged away and is always fetched. Each kind of entry is bounded;
+//! the oldest entries are evicted first.
+
+use ethers::types::{Address, Block, Bytes, TransactionReceipt, H256};
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+
+/// Entries kept per kind of cached data.
+pub const CACHE_CAPACITY: usize = 1024;
+
+/// How long the finalized block number is trusted before it is asked for
+/// again, about one slot.
+pub const FINALIZED_REFRESH: Duration = Duration::from_secs(12);
+
+/// A map that evicts its oldest entries beyond a capacity.
+#[derive(Debug)]
+struct BoundedMap<K, V> {
+    entries: HashMap<K, V>,
+    order: VecDeque<K>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> BoundedMap<K, V> {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.entries.insert(key.clone(), value).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Immutable RPC results of one network.
+#[derive(Debug)]
+pub struct RpcCache {
+    chain_id: OnceCell<u64>,
+    receipts: Mutex<BoundedMap<H256, TransactionReceipt>>,
+    blocks: Mutex<BoundedMap<H256, Block<H256>>>,
+    block_hashes: Mutex<BoundedMap<u64, H256>>,
+    code: Mutex<BoundedMap<(Address, u64), Bytes>>,
+    /// The highest finalized block number seen and when it was asked for.
+    finalized: Mutex<(u64, Option<Instant>)>,
+}
+
+impl Default for RpcCache {
+    fn default() -> Self {
+        Self {
+            chain_id: OnceCell::new(),
+            receipts: Mutex::new(BoundedMap::new()),
+            blocks: Mutex::new(BoundedMap::new()),
+            block_hashes: Mutex::new(BoundedMap::new()),
+            code: Mutex::new(BoundedMap::new()),
+            finalized: Mutex::new((0, None)),
+        }
+    }
+}
+
+impl RpcCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cell holding the chain id once it is known.
+    pub(crate) fn chain_id(&self) -> &OnceCell<u64> {
+        &self.chain_id
+    }
+
+    pub(crate) fn receipt(&self, hash: H256) -> Option<TransactionReceipt> {
+        self.receipts.lock().unwrap().get(&hash)
+    }
+
+    pub(crate) fn insert_receipt(&self, receipt: TransactionReceipt) {
+        self.receipts
+            .lock()
+            .unwrap()
+            .insert(receipt.transaction_hash, receipt);
+    }
+
+    pub(crate) fn block(&self, hash: H256) -> Option<Block<H256>> {
+        self.blocks.lock().unwrap().get(&hash)
+    }
+
+    pub(crate) fn block_by_number(&self, number: u64) -> Option<Block<H256>> {
+        let hash = self.block_hashes.lock().unwrap().get(&number)?;
+        self.block(hash)
+    }
+
+    /// Keeps a block, which must be finalized, by hash and number.
+    pub(crate) fn insert_block(&self, block: &Block<H256>) {
+        let (Some(hash), Some(number)) = (block.hash, block.number) else {
+            return;
+        };
+        self.blocks.lock().unwrap().insert(hash, block.clone());
+        self.block_hashes
+            .lock()
+            .unwrap()
+            .insert(number.as_u64(), hash);
+    }
+
+    pub(crate) fn code(&self, address: Address, number: u64) -> Option<Bytes> {
+        self.code.lock().unwrap().get(&(address, number))
+    }
+
+    /// Keeps the code of `address` as of finalized block `number`. The latest
+    /// code is not kept: contracts can self-destruct and EIP-7702 accounts
+    /// change their delegation.
+    pub(crate) fn insert_code(&self, address: Address, number: u64, code: &Bytes) {
+        self.code
+            .lock()
+            .unwrap()
+            .insert((address, number), code.clone());
+    }
+
+    /// The highest finalized block number seen, and whether it is due to be
+    /// asked for again.
+    pub(crate) fn finalized(&self) -> (u64, bool) {
+        let (number, asked) = *self.finalized.lock().unwrap();
+        let stale = asked.is_none_or(|asked| asked.elapsed() >= FINALIZED_REFRESH);
+        (number, stale)
+    }
+
+    /// Records the finalized block number reported by the node, if any.
+    pub(crate) fn set_finalized(&self, number: Option<u64>) {
+        let mut finalized = self.finalized.lock().unwrap();
+        finalized.0 = finalized.0.max(number.unwrap_or_default());
+        finalized.1 = Some(Instant::now());
+    }
+}
@@ -0,0 +1,274 @@
+//! Known chains and how to present them.
+//!
+//! The [`ChainRegistry`] maps chain ids to a name, the native currency, block
+//! explorer links, whether the chain takes EIP-1559 transactions and,
+//! optionally, RPC endpoints to switch to at runtime. It starts with the
+//! built-in chains below and can be extended, or its entries replaced, from
+//! `~/.mcp-wallet-chains.json`:
+//!
+//! ```json
+//! {
+//!   "chains": [
+//!     {
+//!       "chain_id": 7777777,
+//!       "name": "Zora",
+//!       "currency": "ETH",
+//!       "explorer_tx_url": "https://explorer.zora.energy/tx/{hash}",
+//!       "explorer_address_url": "https://explorer.zora.energy/address/{address}",
+//!       "rpc_urls": ["https://rpc.zora.energy"]
+//!     }
+//!   ]
+//! }
+//! ```
+
+use crate::error::{Result, WalletError};
+use ethers::types::{Address, H256, U256};
+use ethers::utils::{format_units, to_checksum};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Placeholder for the transaction hash in [`ChainInfo::explorer_tx_url`].
+pub const HASH_PLACEHOLDER: &str = "{hash}";
+
+/// Placeholder for the address in [`ChainInfo::explorer_address_url`].
+pub const ADDRESS_PLACEHOLDER: &str = "{address}";
+
+/// What the wallet knows about one chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainInfo {
+    /// The EIP-155 chain id.
+    pub chain_id: u64,
+    /// Human readable name, e.g. `Ethereum` or `Sepolia`.
+    pub name: String,
+    /// Symbol of the native currency, e.g. `ETH`.
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// Decimals of the native currency.
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    /// Explorer page of a transaction, with `{hash}` for its hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_tx_url: Option<String>,
+    /// Explorer page of an address, with `{address}` for the address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_address_url: Option<String>,
+    /// Whether the chain accepts EIP-1559 (type 2) transactions.
+    #[serde(default = "default_eip1559")]
+    pub eip1559: bool,
+    /// RPC endpoints of the chain, the first preferred and the others used
+    /// for failover. A chain without endpoints cannot be switched to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpc_urls: Vec<String>,
+}
+
+fn default_currency() -> String {
+    "ETH".to_string()
+}
+
+fn default_decimals() -> u8 {
+    18
+}
+
+fn default_eip1559() -> bool {
+    true
+}
+
+impl ChainInfo {
+    /// Describes a chain that is not in the registry.
+    pub fn unknown(chain_id: u64) -> Self {
+        Self {
+            chain_id,
+            name: format!("Chain {}", chain_id),
+            currency: default_currency(),
+            decimals: default_decimals(),
+            explorer_tx_url: None,
+            explorer_address_url: None,
+            eip1559: default_eip1559(),
+            rpc_urls: Vec::new(),
+        }
+    }
+
+    /// Formats an amount of the native currency's smallest unit in whole
+    /// units, e.g. wei as ETH.
+    pub fn format_amount(&self, amount: U256) -> Result<String> {
+        format_units(amount, u32::from(self.decimals)).map_err(|e| {
+            WalletError::WalletError(format!(
+                "Cannot format an amount of {} with {} decimals: {}",
+                self.currency, self.decimals, e
+            ))
+        })
+    }
+
+    /// The explorer page of transaction `hash`, if the chain has an explorer.
+    pub fn tx_url(&self, hash: H256) -> Option<String> {
+        let template = self.explorer_tx_url.as_ref()?;
+        Some(template.replace(HASH_PLACEHOLDER, &format!("{:?}", hash)))
+    }
+
+    /// The explorer page of `address`, if the chain has an explorer.
+    pub fn address_url(&self, address: Address) -> Option<String> {
+        let template = self.explorer_address_url.as_ref()?;
+        Some(template.replace(ADDRESS_PLACEHOLDER, &to_checksum(&address, None)))
+    }
+}
+
+/// An Etherscan-style explorer at `base`.
+fn explorer(base: &str) -> (Option<String>, Option<String>) {
+    (
+        Some(format!("{}/tx/{}", base, HASH_PLACEHOLDER)),
+        Some(format!("{}/address/{}", base, ADDRESS_PLACEHOLDER)),
+    )
+}
+
+fn builtin(chain_id: u64, name: &str, currency: &str, explorer_base: Option<&str>) -> ChainInfo {
+    let (explorer_tx_url, explorer_address_url) = explorer_base.map(explorer).unwrap_or_default();
+    ChainInfo {
+        chain_id,
+        name: name.to_string(),
+        currency: currency.to_string(),
+        decimals: 18,
+        explorer_tx_url,
+        explorer_address_url,
+        eip1559: true,
+        rpc_urls: Vec::new(),
+    }
+}
+
+/// The chains the wallet knows about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainRegistry {
+    chains: BTreeMap<u64, ChainInfo>,
+}
+
+/// The layout of a chains file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChainsFile {
+    #[serde(default)]
+    chains: Vec<ChainInfo>,
+}
+
+impl Default for ChainRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ChainRegistry {
+    /// The built-in chains: the main Ethereum networks and testnets, popular
+    /// rollups and sidechains, and local development nodes.
+    pub fn builtin() -> Self {
+        let chains = [
+            builtin(1, "Ethereum", "ETH", Some("https://etherscan.io")),
+            builtin(
+                11155111,
+                "Sepolia",
+                "ETH",
+                Some("https://sepolia.etherscan.io"),
+            ),
+            builtin(
+                17000,
+                "Holesky",
+                "ETH",
+                Some("https://holesky.etherscan.io"),
+            ),
+            builtin(560048, "Hoodi", "ETH", Some("https://hoodi.etherscan.io")),
+            builtin(
+                10,
+                "OP Mainnet",
+                "ETH",
+                Some("https://optimistic.etherscan.io"),
+            ),
+            builtin(8453, "Base", "ETH", Some("https://basescan.org")),
+            builtin(42161, "Arbitrum One", "ETH", Some("https://arbiscan.io")),
+            builtin(137, "Polygon", "POL", Some("https://polygonscan.com")),
+            builtin(100, "Gnosis", "xDAI", Some("https://gnosisscan.io")),
+            ChainInfo {
+                rpc_urls: vec!["http://127.0.0.1:8545".to_string()],
+                ..builtin(31337, "Anvil", "ETH", None)
+            },
+            builtin(1337, "Geth dev", "ETH", None),
+        ];
+        Self {
+            chains: chains.into_iter().map(|c| (c.chain_id, c)).collect(),
+        }
+    }
+
+    /// Loads the built-in chains extended with those in a JSON file. Entries
+    /// in the file replace built-in ones with the same chain id. A missing
+    /// file yields just the built-in chains.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file: ChainsFile = match std::fs::read_to_string(path.as_ref()) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ChainsFile::default(),
+            Err(e) => return Err(WalletError::FileError(e)),
+        };
+        let mut registry = Self::builtin();
+        registry.extend(file.chains);
+        Ok(registry)
+    }
+
+    /// Returns the default path of the chains file (`~/.mcp-wallet-chains.json`).
+    pub fn default_path() -> Result<PathBuf> {
+        crate::profile::home_file(".mcp-wallet-chains.json")
+    }
+
+    /// Adds chains, replacing known ones with the same chain id.
+    pub fn extend(&mut self, chains: impl IntoIterator<Item = ChainInfo>) {
+        for chain in chains {
+            self.chains.insert(chain.chain_id, chain);
+        }
+    }
+
+    /// Gets a known chain by id.
+    pub fn get(&self, chain_id: u64) -> Option<&ChainInfo> {
+        self.chains.get(&chain_id)
+    }
+
+    /// Gets a chain by id, describing unknown ones generically.
+    pub fn describe(&self, chain_id: u64) -> ChainInfo {
+        self.get(chain_id)
+            .cloned()
+            .unwrap_or_else(|| ChainInfo::unknown(chain_id))
+    }
+
+    /// Finds a chain by its id or its name, ignoring case.
+    pub fn find(&self, id_or_name: &str) -> Option<&ChainInfo> {
+        match id_or_name.trim().parse::<u64>() {
+            Ok(chain_id) => self.get(chain_id),
+            Err(_) => self
+                .chains
+                .values()
+                .find(|c| c.name.eq_ignore_ascii_case(id_or_name.trim())),
+        }
+    }
+
+    /// Finds a chain that can be switched to, by id or name.
+    pub fn network(&self, id_or_name: &str) -> Result<&ChainInfo> {
+        self.find(id_or_name)
+            .filter(|chain| !chain.rpc_urls.is_empty())
+            .ok_or_else(|| WalletError::NetworkNotFound(id_or_name.to_string()))
+    }
+
+    /// Iterates over the chains that have RPC endpoints.
+    pub fn networks(&self) -> impl Iterator<Item = &ChainInfo> {
+        self.iter().filter(|chain| !chain.rpc_urls.is_empty())
+    }
+
+    /// Iterates over the chains in chain id order.
+    pub fn iter(&self) -> impl Iterator<Item = &ChainInfo> {
+        self.chains.values()
+    }
+
+    /// Checks that an EIP-1559 transaction can be built for `chain_id`.
+    /// Chains that are not in the registry are assumed to support it.
+    pub fn check_eip1559(&self, chain_id: u64) -> Result<()> {
+        match self.get(chain_id) {
+            Some(chain) if !chain.eip1559 => Err(WalletError::Eip1559Unsupported(format!(
+                "{} ({})",
+                chain.name, chain.chain_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
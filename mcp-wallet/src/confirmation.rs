@@ -0,0 +1,257 @@
+//! Waiting for transactions to be confirmed.
+//!
+//! [`TxWatcher`] is polled until a transaction is buried under the requested
+//! number of blocks. It remembers the block a receipt was first seen in and
+//! checks on every poll that this block is still canonical, so a transaction
+//! that is reorged out goes back to waiting instead of being reported as
+//! confirmed. Without a receipt it watches the sender's nonce to tell a
+//! replaced transaction from one the node dropped.
+
+use crate::{error::Result, eth_client::EthClient};
+use ethers::types::{Address, BlockNumber, TransactionReceipt, H256, U64};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long to wait for confirmations unless told otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Longer timeouts are cut to this.
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Time between polls unless told otherwise.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polling faster than this only loads the node.
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Polling slower than this would miss reorgs.
+pub const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Polls in a row in which the node did not know the transaction before it
+/// is reported as dropped. Load-balanced RPCs may not see a fresh
+/// transaction on every backend yet.
+pub const DROP_AFTER_MISSES: u32 = 3;
+
+/// Where a watched transaction stands while waiting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WaitProgress {
+    /// Blocks on top of (and including) the transaction's block; 0 if not mined.
+    pub confirmations: u64,
+    /// The confirmations waited for.
+    pub required: u64,
+    /// The block the transaction is currently included in.
+    pub block_number: Option<u64>,
+    /// How many times the transaction's block was reorged out so far.
+    pub reorgs: u32,
+}
+
+impl WaitProgress {
+    /// A short human-readable summary, used for progress notifications.
+    pub fn message(&self) -> String {
+        match self.block_number {
+            Some(block) => format!(
+                "{}/{} confirmations (block {})",
+                self.confirmations, self.required, block
+            ),
+            None if self.reorgs > 0 => "reorged out, waiting to be mined again".to_string(),
+            None => "waiting to be mined".to_string(),
+        }
+    }
+}
+
+/// How waiting for a transaction ended.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WaitOutcome {
+    /// The transaction has the requested confirmations.
+    Confirmed {
+        /// Whether the transaction executed successfully.
+        success: bool,
+        /// Confirmations at the time of the last poll.
+        confirmations: u64,
+        /// Reorgs seen while waiting.
+        reorgs: u32,
+        /// The final receipt.
+        receipt: Box<TransactionReceipt>,
+    },
+    /// Another transaction used the same nonce.
+    Replaced {
+        /// The nonce that was used by another transaction.
+        nonce: u64,
+        /// Reorgs seen while waiting.
+        reorgs: u32,
+    },
+    /// The node no longer knows the transaction and it was not mined.
+    Dropped {
+        /// Reorgs seen while waiting.
+        reorgs: u32,
+    },
+    /// The timeout passed first.
+    TimedOut {
+        /// Where the transaction stood at the last poll.
+        #[serde(flatten)]
+        progress: WaitProgress,
+    },
+}
+
+/// The result of one [`TxWatcher::poll`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchState {
+    /// Not done yet.
+    Waiting(WaitProgress),
+    /// Done, one way or another.
+    Done(WaitOutcome),
+}
+
+/// Follows one transaction until it has enough confirmations.
+#[derive(Debug, Clone)]
+pub struct TxWatcher {
+    hash: H256,
+    required: u64,
+    sender: Option<(Address, u64)>,
+    included_in: Option<(u64, H256)>,
+    reorgs: u32,
+    misses: u32,
+    last: WaitProgress,
+}
+
+impl TxWatcher {
+    /// Watches `hash` until it has `confirmations` (at least 1).
+    pub fn new(hash: H256, confirmations: u64) -> Self {
+        let required = confirmations.max(1);
+        Self {
+            hash,
+            required,
+            sender: None,
+            included_in: None,
+            reorgs: 0,
+            misses: 0,
+            last: WaitProgress {
+                confirmations: 0,
+                required,
+                block_number: None,
+                reorgs: 0,
+            },
+        }
+    }
+
+    /// Sets the sender and nonce when they are already known, e.g. from the journal.
+    pub fn with_sender(mut self, from: Address, nonce: u64) -> Self {
+        self.sender = Some((from, nonce));
+        self
+    }
+
+    /// Checks the transaction once.
+    pub async fn poll(&mut self, client: &EthClient) -> Result<WatchState> {
+        // Read the nonce before the receipt, so a transaction mined in between
+        // is not mistaken for a replaced one
+        let nonce_used = match self.sender {
+            Some((from, nonce)) => {
+                client
+                    .get_transaction_count(from, BlockNumber::Latest)
+                    .await?
+                    > nonce
+            }
+            None => false,
+        };
+
+        let receipt = client
+            .get_transaction_receipt(self.hash)
+            .await?
+            .filter(|r| r.block_number.is_some());
+        let Some(receipt) = receipt else {
+            if self.included_in.take().is_some() {
+                self.reorgs += 1;
+                log::warn!("Transaction 0x{:x} was reorged out", self.hash);
+            }
+            return self.poll_unmined(client, nonce_used).await;
+        };
+        self.misses = 0;
+
+        let block_number = receipt.block_number.unwrap_or_default().as_u64();
+        let block_hash = receipt.block_hash.unwrap_or_default();
+        let previous = self.included_in.take();
+
+        // The node may still serve a receipt from a block that is no longer canonical
+        if client.get_block_hash(block_number).await? != Some(block_hash) {
+            if previous.is_some() {
+                self.reorgs += 1;
+                log::warn!(
+                    "Block {} of transaction 0x{:x} is no longer canonical",
+                    block_number,
+                    self.hash
+                );
+            }
+            return Ok(WatchState::Waiting(self.progress(0, None)));
+        }
+        if previous.is_some_and(|(_, seen_hash)| seen_hash != block_hash) {
+            self.reorgs += 1;
+            log::warn!(
+                "Transaction 0x{:x} moved to block {} after a reorg",
+                self.hash,
+                block_number
+            );
+        }
+        self.included_in = Some((block_number, block_hash));
+
+        let head = client.get_current_block().await?;
+        let confirmations = (head + 1).saturating_sub(block_number);
+        if confirmations >= self.required {
+            return Ok(WatchState::Done(WaitOutcome::Confirmed {
+                success: receipt.status != Some(U64::zero()),
+                confirmations,
+                reorgs: self.reorgs,
+                receipt: Box::new(receipt),
+            }));
+        }
+        Ok(WatchState::Waiting(
+            self.progress(confirmations, Some(block_number)),
+        ))
+    }
+
+    /// The outcome to report when the caller gives up waiting.
+    pub fn timed_out(&self) -> WaitOutcome {
+        WaitOutcome::TimedOut {
+            progress: self.last.clone(),
+        }
+    }
+
+    async fn poll_unmined(&mut self, client: &EthClient, nonce_used: bool) -> Result<WatchState> {
+        if nonce_used {
+            if let Some((_, nonce)) = self.sender {
+                return Ok(WatchState::Done(WaitOutcome::Replaced {
+                    nonce,
+                    reorgs: self.reorgs,
+                }));
+            }
+        }
+        match client.get_transaction_info(self.hash).await? {
+            Some(tx) => {
+                self.misses = 0;
+                if self.sender.is_none() {
+                    // Check the nonce right away on the next poll
+                    self.sender = Some((tx.from, tx.nonce.as_u64()));
+                }
+            }
+            None => {
+                self.misses += 1;
+                if self.misses >= DROP_AFTER_MISSES {
+                    return Ok(WatchState::Done(WaitOutcome::Dropped {
+                        reorgs: self.reorgs,
+                    }));
+                }
+            }
+        }
+        Ok(WatchState::Waiting(self.progress(0, None)))
+    }
+
+    fn progress(&mut self, confirmations: u64, block_number: Option<u64>) -> WaitProgress {
+        self.last = WaitProgress {
+            confirmations,
+            required: self.required,
+            block_number,
+            reorgs: self.reorgs,
+        };
+        self.last.clone()
+    }
+}
@@ -0,0 +1,358 @@
+//! Decoding of revert data and event logs.
+//!
+//! Revert data is decoded as a Solidity `Error(string)`, a `Panic(uint256)` or
+//! a custom error; custom errors, logs and call data are matched against a
+//! small table of well-known signatures (ERC-20, ERC-721, WETH, Ownable).
+//! Anything unknown is returned raw.
+
+use crate::error::WalletError;
+use ethers::{
+    abi::{self, Abi, RawLog, Token},
+    providers::{ProviderError, RpcError},
+    types::{Bytes, Log, H256, U256},
+    utils::to_checksum,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+
+/// Selector of `Error(string)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Functions, events and errors decoded without a contract ABI.
+const KNOWN_SIGNATURES: &[&str] = &[
+    "function transfer(address to, uint256 value) returns (bool)",
+    "function transferFrom(address from, address to, uint256 value) returns (bool)",
+    "function approve(address spender, uint256 value) returns (bool)",
+    "function balanceOf(address owner) view returns (uint256)",
+    "function allowance(address owner, address spender) view returns (uint256)",
+    "function totalSupply() view returns (uint256)",
+    "function decimals() view returns (uint8)",
+    "function ownerOf(uint256 tokenId) view returns (address)",
+    "function safeTransferFrom(address from, address to, uint256 tokenId)",
+    "function setApprovalForAll(address operator, bool approved)",
+    "function deposit() payable",
+    "function withdraw(uint256 wad)",
+    "function transferOwnership(address newOwner)",
+    "event Transfer(address indexed from, address indexed to, uint256 value)",
+    "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+    "event Approval(address indexed owner, address indexed spender, uint256 value)",
+    "event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId)",
+    "event ApprovalForAll(address indexed owner, address indexed operator, bool approved)",
+    "event Deposit(address indexed dst, uint256 wad)",
+    "event Withdrawal(address indexed src, uint256 wad)",
+    "event OwnershipTransferred(address indexed previousOwner, address indexed newOwner)",
+    "error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed)",
+    "error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed)",
+    "error ERC20InvalidReceiver(address receiver)",
+    "error ERC721NonexistentToken(uint256 tokenId)",
+    "error OwnableUnauthorizedAccount(address account)",
+];
+
+fn known_abi() -> &'static Abi {
+    static ABI: OnceLock<Abi> = OnceLock::new();
+    ABI.get_or_init(|| abi::parse_abi(KNOWN_SIGNATURES).expect("known signatures parse"))
+}
+
+/// Why a call reverted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RevertReason {
+    /// The call reverted without data, e.g. `revert()` or `require(cond)`.
+    Empty,
+    /// `revert("message")` or `require(cond, "message")`.
+    Error {
+        /// The revert message.
+        message: String,
+    },
+    /// A failed `assert`, arithmetic overflow, bad array index and the like.
+    Panic {
+        /// The panic code.
+        code: u64,
+        /// What the code means.
+        description: String,
+    },
+    /// A custom Solidity error.
+    Custom {
+        /// The 4-byte error selector.
+        selector: String,
+        /// The error signature, if it is a known one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+        /// Decoded arguments of a known error.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        args: Option<Value>,
+        /// The raw revert data.
+        data: Bytes,
+    },
+}
+
+impl std::fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "reverted without a reason"),
+            Self::Error { message } => write!(f, "{}", message),
+            Self::Panic { code, description } => write!(f, "panic 0x{:02x}: {}", code, description),
+            Self::Custom {
+                signature: Some(signature),
+                args,
+                ..
+            } => match args {
+                Some(args) => write!(f, "{} {}", signature, args),
+                None => write!(f, "{}", signature),
+            },
+            Self::Custom { selector, data, .. } => {
+                write!(f, "custom error {} (data {})", selector, data)
+            }
+        }
+    }
+}
+
+/// Decodes the data returned by a reverted call.
+pub fn decode_revert(data: &[u8]) -> RevertReason {
+    if data.len() < 4 {
+        return RevertReason::Empty;
+    }
+    let (selector, args) = data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[abi::ParamType::String], args) {
+            if let Some(Token::String(message)) = tokens.into_iter().next() {
+                return RevertReason::Error { message };
+            }
+        }
+    }
+    if selector == PANIC_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[abi::ParamType::Uint(256)], args) {
+            if let Some(Token::Uint(code)) = tokens.into_iter().next() {
+                let code = code.low_u64();
+                return RevertReason::Panic {
+                    code,
+                    description: panic_description(code).to_string(),
+                };
+            }
+        }
+    }
+
+    let known = known_abi()
+        .errors()
+        .find(|e| e.signature()[..4] == *selector)
+        .and_then(|e| {
+            let tokens = e.decode(args).ok()?;
+            let names = e.inputs.iter().map(|p| p.name.as_str());
+            let signature = signature_of(&e.name, e.inputs.iter().map(|p| &p.kind));
+            Some((signature, named_tokens(names, tokens)))
+        });
+    let (signature, args) = known.unzip();
+    RevertReason::Custom {
+        selector: format!("0x{}", hex::encode(selector)),
+        signature,
+        args,
+        data: Bytes::from(data.to_vec()),
+    }
+}
+
+/// What a Solidity panic code means.
+pub fn panic_description(code: u64) -> &'static str {
+    match code {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to a zero-initialized function",
+        _ => "unknown panic code",
+    }
+}
+
+/// Extracts revert data from a failed `eth_call` or `eth_estimateGas`.
+///
+/// Returns `None` if the error is not a revert, e.g. a network failure.
+pub fn revert_data(error: &WalletError) -> Option<Bytes> {
+    match error {
+        WalletError::EthersProviderError(e) => provider_revert_data(e),
+        _ => None,
+    }
+}
+
+fn provider_revert_data(error: &ProviderError) -> Option<Bytes> {
+    RpcError::as_error_response(error)?.as_revert_data()
+}
+
+/// Call data decoded with a well-known function signature.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedCall {
+    /// The function signature, e.g. `transfer(address,uint256)`.
+    pub signature: String,
+    /// Decoded arguments by name.
+    pub args: Value,
+    /// Decoded return values by name, if output was given and decodes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub returns: Option<Value>,
+}
+
+/// Decodes call data, and optionally its output, if the function is known.
+pub fn decode_call(input: &[u8], output: Option<&[u8]>) -> Option<DecodedCall> {
+    if input.len() < 4 {
+        return None;
+    }
+    let (selector, args) = input.split_at(4);
+    let function = known_abi()
+        .functions()
+        .find(|f| f.short_signature() == selector)?;
+    let tokens = function.decode_input(args).ok()?;
+    let returns = output.and_then(|output| {
+        let tokens = function.decode_output(output).ok()?;
+        Some(named_tokens(
+            function.outputs.iter().map(|p| p.name.as_str()),
+            tokens,
+        ))
+    });
+    Some(DecodedCall {
+        signature: signature_of(&function.name, function.inputs.iter().map(|p| &p.kind)),
+        args: named_tokens(function.inputs.iter().map(|p| p.name.as_str()), tokens),
+        returns,
+    })
+}
+
+/// A log, decoded if its event is known.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedLog {
+    /// The emitting contract, checksummed.
+    pub address: String,
+    /// Position of the log in its block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_index: Option<u64>,
+    /// The event name, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    /// The full event signature, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Decoded arguments by name, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<Value>,
+    /// Raw topics, for unknown events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<H256>>,
+    /// Raw data, for unknown events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+}
+
+/// Decodes a log with the well-known event signatures.
+pub fn decode_log(log: &Log) -> DecodedLog {
+    decode_log_with(log, known_abi())
+}
+
+/// Decodes a log with the events of `abi`.
+pub fn decode_log_with(log: &Log, abi: &Abi) -> DecodedLog {
+    let mut decoded = DecodedLog {
+        address: to_checksum(&log.address, None),
+        log_index: log.log_index.map(|i| i.as_u64()),
+        event: None,
+        signature: None,
+        args: None,
+        topics: None,
+        data: None,
+    };
+    let raw = RawLog {
+        topics: log.topics.clone(),
+        data: log.data.to_vec(),
+    };
+    let parsed = log.topics.first().and_then(|topic0| {
+        // ERC-20 and ERC-721 share event signatures and differ in indexed params
+        abi.events()
+            .filter(|e| e.signature() == *topic0)
+            .find_map(|e| Some((e, e.parse_log_whole(raw.clone()).ok()?)))
+    });
+    match parsed {
+        Some((event, parsed)) => {
+            let names = parsed.params.iter().map(|p| p.name.as_str());
+            let tokens = parsed.params.iter().map(|p| p.value.clone()).collect();
+            decoded.args = Some(named_tokens(names, tokens));
+            decoded.signature = Some(signature_of(
+                &event.name,
+                event.inputs.iter().map(|p| &p.kind),
+            ));
+            decoded.event = Some(event.name.clone());
+        }
+        None => {
+            decoded.topics = Some(log.topics.clone());
+            decoded.data = Some(log.data.clone());
+        }
+    }
+    decoded
+}
+
+/// Parses human-readable signatures into an ABI. Lines without a keyword are
+/// taken as events, e.g. `Transfer(address indexed from, address indexed to, uint256 value)`.
+pub fn parse_signatures(signatures: &[&str]) -> Result<Abi, WalletError> {
+    const KEYWORDS: &[&str] = &["event ", "function ", "error ", "constructor"];
+    let lines: Vec<String> = signatures
+        .iter()
+        .map(|s| s.trim())
+        .map(|s| {
+            if KEYWORDS.iter().any(|k| s.starts_with(k)) {
+                s.to_string()
+            } else {
+                format!("event {}", s)
+            }
+        })
+        .collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    abi::parse_abi(&lines)
+        .map_err(|e| WalletError::WalletError(format!("Invalid signature: {}", e)))
+}
+
+/// Converts an ABI token to JSON; numbers become decimal strings.
+pub fn token_to_json(token: &Token) -> Value {
+    match token {
+        Token::Address(address) => json!(to_checksum(address, None)),
+        Token::Uint(value) => json!(value.to_string()),
+        Token::Int(value) => json!(int_to_string(*value)),
+        Token::Bool(value) => json!(value),
+        Token::String(value) => json!(value),
+        Token::Bytes(value) | Token::FixedBytes(value) => {
+            json!(format!("0x{}", hex::encode(value)))
+        }
+        Token::Array(items) | Token::FixedArray(items) | Token::Tuple(items) => {
+            Value::Array(items.iter().map(token_to_json).collect())
+        }
+    }
+}
+
+/// Formats a two's complement `int256`.
+fn int_to_string(value: U256) -> String {
+    if value.bit(255) {
+        format!("-{}", (!value).overflowing_add(U256::one()).0)
+    } else {
+        value.to_string()
+    }
+}
+
+fn named_tokens<'a>(names: impl Iterator<Item = &'a str>, tokens: Vec<Token>) -> Value {
+    let args: Map<String, Value> = names
+        .zip(tokens.iter())
+        .enumerate()
+        .map(|(i, (name, token))| {
+            let name = if name.is_empty() {
+                i.to_string()
+            } else {
+                name.to_string()
+            };
+            (name, token_to_json(token))
+        })
+        .collect();
+    Value::Object(args)
+}
+
+fn signature_of<'a>(name: &str, kinds: impl Iterator<Item = &'a abi::ParamType>) -> String {
+    let types: Vec<String> = kinds.map(ToString::to_string).collect();
+    format!("{}({})", name, types.join(","))
+}
@@ -1,6 +1,7 @@
+use crate::decode::RevertReason;
 use ethers::core::types::SignatureError;
 use ethers::providers::ProviderError;
-use ethers::types::{Address, H256};
+use ethers::types::{Address, H256, U256};
 use ethers::utils::{self, rlp::DecoderError};
 use thiserror::Error;
 
@@ -43,6 +44,43 @@ pub enum WalletError {
     #[error("Alias '{0}' already exists.")]
     AliasAlreadyExists(String),
 
+    /// Error when a wallet profile is not configured.
+    #[error("Wallet profile '{0}' not found.")]
+    ProfileNotFound(String),
+
+    /// Error for an invalid wallet profile name.
+    #[error("Profile name '{0}' is invalid. It must be 1-32 alphanumeric, '-' or '_' characters.")]
+    InvalidProfileName(String),
+
+    /// Error when an EIP-1559 transaction is built for a chain without it.
+    #[error("Chain {0} does not support EIP-1559 transactions.")]
+    Eip1559Unsupported(String),
+
+    /// Error when no network with RPC endpoints is configured under a name or chain id.
+    #[error("Network '{0}' not found or has no RPC endpoints.")]
+    NetworkNotFound(String),
+
+    /// Error when a node serves a different chain than expected.
+    #[error("Chain id mismatch: expected {expected}, but the node reports {actual}")]
+    ChainIdMismatch {
+        /// The chain id that was expected.
+        expected: u64,
+        /// The chain id reported by the node.
+        actual: u64,
+    },
+
+    /// Error when a backup archive cannot be created, verified or decrypted.
+    #[error("Backup error: {0}")]
+    BackupError(String),
+
+    /// Error when a V3 keystore file cannot be read, decrypted or written.
+    #[error("Keystore error: {0}")]
+    KeystoreError(String),
+
+    /// Error when Shamir key shares are malformed, inconsistent or insufficient.
+    #[error("Invalid key shares: {0}")]
+    InvalidKeyShares(String),
+
     /// Error when the transaction nonce does not match the account's nonce.
     #[error("Nonce mismatch: expected {expected}, but got {actual}")]
     NonceMismatch {
@@ -52,6 +90,19 @@ pub enum WalletError {
         actual: u64,
     },
 
+    /// Error when simulating a transaction shows that it would revert.
+    #[error("Transaction would revert: {0}")]
+    WouldRevert(RevertReason),
+
+    /// Error when a transaction's gas limit is below what simulation says it needs.
+    #[error("Gas limit {limit} is too low, the transaction needs about {needed}")]
+    GasLimitTooLow {
+        /// The gas the simulation used.
+        needed: U256,
+        /// The gas limit of the transaction.
+        limit: U256,
+    },
+
     /// Error from the ethers-rs signer module.
     #[error("Ethers signer error: {0}")]
     EthersSignerError(#[from] ethers::signers::WalletError),
@@ -2,13 +2,21 @@
 //!
 //! This module provides a client for interacting with an Ethereum node via RPC.
 
+use crate::cache::RpcCache;
+use crate::failover::{EndpointHealth, FailoverClient, FailoverOptions};
+use crate::nonce::{self, OnchainNonces};
 use crate::prelude::*;
+use crate::profile::WalletProfile;
 use ethers::{
-    providers::{Http, Middleware, Provider},
-    types::{Address, Bytes, Transaction, TransactionReceipt, H256},
-    utils::format_ether,
+    providers::{Middleware, Provider, SubscriptionStream},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
+        Filter, Log, Transaction, TransactionReceipt, H256, U256,
+    },
+    utils::{format_ether, keccak256},
 };
-use std::str::FromStr;
+use serde_json::{json, Value};
+use std::{str::FromStr, time::Duration};
 
 /// A client for interacting with an Ethereum RPC endpoint.
 ///
@@ -17,7 +25,13 @@ use std::str::FromStr;
 #[derive(Debug)]
 pub struct EthClient {
     /// The Ethers provider for making RPC calls.
-    provider: Provider<Http>,
+    provider: Provider<FailoverClient>,
+    /// The primary endpoint URL the provider was created for.
+    rpc_url: String,
+    /// Results that cannot change anymore.
+    cache: RpcCache,
+    /// The chain id the client is configured for, if any.
+    expected_chain_id: Option<u64>,
 }
 
 impl EthClient {
@@ -25,7 +39,9 @@ impl EthClient {
     ///
     /// # Arguments
     ///
-    /// * `rpc_url` - The URL of the Ethereum RPC endpoint.
+    /// * `rpc_url` - The URL of the Ethereum RPC endpoint, `http(s)://` or
+    ///   `ws(s)://`, or the path of the node's IPC socket. Sockets are
+    ///   connected on the first request.
     ///
     /// # Returns
     ///
@@ -36,12 +52,76 @@ impl EthClient {
     ///
     /// ```
     /// let client = EthClient::new("https://mainnet.infura.io/v3/YOUR_PROJECT_ID")?;
+    /// let local = EthClient::new("/tmp/anvil.ipc")?;
     /// ```
     pub fn new(rpc_url: &str) -> Result<Self> {
-        let http_provider = Http::from_str(rpc_url)
-            .map_err(|e| WalletError::RpcClientInitialization(e.to_string()))?;
-        let provider = Provider::new(http_provider);
-        Ok(Self { provider })
+        Self::with_endpoints(&[rpc_url], FailoverOptions::default())
+    }
+
+    /// Creates a client over several endpoints of the same network.
+    ///
+    /// Requests go to the healthiest endpoint and fail over to the others on
+    /// errors or timeouts; see [`crate::failover`]. The first URL is the
+    /// primary one reported by [`EthClient::url`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let options = FailoverOptions { quorum: Some(2), ..Default::default() };
+    /// let client = EthClient::with_endpoints(&[primary, backup, third], options)?;
+    /// ```
+    pub fn with_endpoints(rpc_urls: &[&str], options: FailoverOptions) -> Result<Self> {
+        let client =
+            FailoverClient::new(rpc_urls, options).map_err(WalletError::RpcClientInitialization)?;
+        Ok(Self {
+            provider: Provider::new(client),
+            rpc_url: rpc_urls[0].to_string(),
+            cache: RpcCache::new(),
+            expected_chain_id: None,
+        })
+    }
+
+    /// Sets the chain id the endpoints are expected to serve. Transactions
+    /// for other chains are refused, see [`EthClient::expected_chain_id`],
+    /// and fallback endpoints on another chain are not used.
+    pub fn with_expected_chain_id(mut self, chain_id: Option<u64>) -> Self {
+        if let Some(chain_id) = chain_id {
+            self.provider.as_ref().expect_chain_id(chain_id);
+        }
+        self.expected_chain_id = chain_id;
+        self
+    }
+
+    /// Creates a client for the endpoints, quorum, timeout, retries and rate
+    /// limit configured in `profile`.
+    pub fn from_profile(profile: &WalletProfile) -> Result<Self> {
+        let defaults = FailoverOptions::default();
+        let options = FailoverOptions {
+            quorum: profile.quorum,
+            timeout: profile
+                .timeout_secs
+                .map_or(defaults.timeout, Duration::from_secs),
+            max_retries: profile.max_retries.unwrap_or(defaults.max_retries),
+            rate_limit: profile.rate_limit,
+            ..defaults
+        };
+        Ok(Self::with_endpoints(&profile.rpc_urls(), options)?
+            .with_expected_chain_id(profile.chain_id))
+    }
+
+    /// Returns the primary RPC endpoint URL this client talks to.
+    pub fn url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    /// Returns the health of every configured endpoint, primary first.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.provider.as_ref().health()
+    }
+
+    /// Whether the endpoint can push subscription notifications.
+    pub fn supports_subscriptions(&self) -> bool {
+        self.provider.as_ref().supports_subscriptions()
     }
 
     /// Gets the current block number from the Ethereum network.
@@ -60,10 +140,200 @@ impl EthClient {
         Ok(block_number.as_u64())
     }
 
-    /// Gets the current chain id from the Ethereum network.
+    /// Gets the hash of the canonical block at `number`, if the node has it.
+    pub async fn get_block_hash(&self, number: u64) -> Result<Option<H256>> {
+        let block = self.get_block(number.into()).await?;
+        Ok(block.and_then(|b| b.hash))
+    }
+
+    /// Gets a block with its transaction hashes.
+    ///
+    /// Finalized blocks are cached, by hash and by number.
+    pub async fn get_block(&self, id: BlockId) -> Result<Option<Block<H256>>> {
+        let cached = match id {
+            BlockId::Hash(hash) => self.cache.block(hash),
+            BlockId::Number(BlockNumber::Number(number)) => {
+                self.cache.block_by_number(number.as_u64())
+            }
+            BlockId::Number(_) => None,
+        };
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let block = self.provider.get_block(id).await?;
+        if let Some(block) = &block {
+            let number = block.number.map(|n| n.as_u64());
+            if block.hash.is_some() && self.is_finalized(number).await {
+                self.cache.insert_block(block);
+            }
+        }
+        Ok(block)
+    }
+
+    /// Gets a block with its full transactions.
+    pub async fn get_block_with_txs(&self, id: BlockId) -> Result<Option<Block<Transaction>>> {
+        let block = self.provider.get_block_with_txs(id).await?;
+        Ok(block)
+    }
+
+    /// Gets the logs matching `filter` with `eth_getLogs`.
+    pub async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
+        let logs = self.provider.get_logs(filter).await?;
+        Ok(logs)
+    }
+
+    /// Subscribes to new block headers with `eth_subscribe`.
+    pub async fn subscribe_blocks(
+        &self,
+    ) -> Result<SubscriptionStream<'_, FailoverClient, Block<H256>>> {
+        self.check_subscriptions()?;
+        Ok(self.provider.subscribe_blocks().await?)
+    }
+
+    /// Subscribes to new logs matching `filter`; block ranges are ignored.
+    pub async fn subscribe_logs(
+        &self,
+        filter: &Filter,
+    ) -> Result<SubscriptionStream<'_, FailoverClient, Log>> {
+        self.check_subscriptions()?;
+        Ok(self.provider.subscribe_logs(filter).await?)
+    }
+
+    /// Subscribes to the hashes of transactions entering the node's mempool.
+    pub async fn subscribe_pending_txs(
+        &self,
+    ) -> Result<SubscriptionStream<'_, FailoverClient, H256>> {
+        self.check_subscriptions()?;
+        Ok(self.provider.subscribe_pending_txs().await?)
+    }
+
+    fn check_subscriptions(&self) -> Result<()> {
+        if self.supports_subscriptions() {
+            return Ok(());
+        }
+        Err(WalletError::WalletError(format!(
+            "Subscriptions need a ws:// or IPC endpoint, {} does not push notifications",
+            self.rpc_url
+        )))
+    }
+
+    /// Gets the chain id of the network, asking the node only once. The other
+    /// endpoints are checked to serve the same chain when they are first used.
     pub async fn get_chain_id(&self) -> Result<u64> {
-        let id = self.provider.get_chainid().await?;
-        Ok(id.as_u64())
+        let id = self
+            .cache
+            .chain_id()
+            .get_or_try_init(|| async {
+                let id = self.provider.get_chainid().await?;
+                Ok::<_, WalletError>(id.as_u64())
+            })
+            .await?;
+        Ok(*id)
+    }
+
+    /// Returns the chain id transactions are signed for: the configured one,
+    /// or else the one the node reported first.
+    ///
+    /// A configured chain id is returned without asking the node, so
+    /// transactions can be signed offline.
+    pub async fn expected_chain_id(&self) -> Result<u64> {
+        match self.expected_chain_id {
+            Some(chain_id) => Ok(chain_id),
+            None => self.get_chain_id().await,
+        }
+    }
+
+    /// Gets the chain id of the node, failing with
+    /// [`WalletError::ChainIdMismatch`] if it is not the configured one.
+    pub async fn verify_chain_id(&self) -> Result<u64> {
+        let actual = self.get_chain_id().await?;
+        match self.expected_chain_id {
+            Some(expected) if expected != actual => {
+                Err(WalletError::ChainIdMismatch { expected, actual })
+            }
+            _ => Ok(actual),
+        }
+    }
+
+    /// Gets the code deployed at `address`, empty for accounts without code.
+    pub async fn get_code(&self, address: Address) -> Result<Bytes> {
+        self.get_code_at(address, None).await
+    }
+
+    /// Gets the code deployed at `address` as of `block`, or the latest block
+    /// if `None`. Code read at a finalized block number is cached.
+    pub async fn get_code_at(&self, address: Address, block: Option<BlockId>) -> Result<Bytes> {
+        let Some(BlockId::Number(BlockNumber::Number(number))) = block else {
+            return Ok(self.provider.get_code(address, block).await?);
+        };
+        let number = number.as_u64();
+        if let Some(code) = self.cache.code(address, number) {
+            return Ok(code);
+        }
+        let code = self.provider.get_code(address, block).await?;
+        if self.is_finalized(Some(number)).await {
+            self.cache.insert_code(address, number, &code);
+        }
+        Ok(code)
+    }
+
+    /// Gets the storage slot `slot` of `address` as of `block`, or the latest
+    /// block if `None`.
+    pub async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: H256,
+        block: Option<BlockId>,
+    ) -> Result<H256> {
+        let value = self.provider.get_storage_at(address, slot, block).await?;
+        Ok(value)
+    }
+
+    /// Gets the balance of `address` in wei as of `block`, or the latest block
+    /// if `None`.
+    pub async fn get_balance_at(&self, address: Address, block: Option<BlockId>) -> Result<U256> {
+        let balance = self.provider.get_balance(address, block).await?;
+        Ok(balance)
+    }
+
+    /// Gets the balances of `addresses` in wei as of `block`, or the latest
+    /// block if `None`, in one batch request.
+    pub async fn get_balances(
+        &self,
+        addresses: &[Address],
+        block: Option<BlockId>,
+    ) -> Result<Vec<U256>> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        let block = block.unwrap_or(BlockNumber::Latest.into());
+        let calls: Vec<(&str, Value)> = addresses
+            .iter()
+            .map(|address| ("eth_getBalance", json!([address, block])))
+            .collect();
+        let answers = self.provider.as_ref().batch(&calls).await?;
+        answers
+            .into_iter()
+            .map(|answer| Ok(serde_json::from_value(answer?)?))
+            .collect()
+    }
+
+    /// Whether block `number` is finalized, asking the node for its finalized
+    /// block at most every [`crate::cache::FINALIZED_REFRESH`].
+    async fn is_finalized(&self, number: Option<u64>) -> bool {
+        let Some(number) = number else {
+            return false;
+        };
+        let (finalized, stale) = self.cache.finalized();
+        if number <= finalized || !stale {
+            return number <= finalized;
+        }
+        // Nodes without the `finalized` tag get nothing cached
+        let block = self.provider.get_block(BlockNumber::Finalized).await;
+        let finalized = block.ok().flatten().and_then(|b| b.number);
+        self.cache.set_finalized(finalized.map(|n| n.as_u64()));
+        finalized.is_some_and(|n| number <= n.as_u64())
     }
 
     /// Gets the balance of a given Ethereum address.
@@ -88,6 +358,91 @@ impl EthClient {
         Ok(format_ether(balance_wei))
     }
 
+    /// Estimates the current EIP-1559 `(max_fee_per_gas, max_priority_fee_per_gas)`.
+    pub async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+        let fees = self.provider.estimate_eip1559_fees(None).await?;
+        Ok(fees)
+    }
+
+    /// Gets the number of transactions sent from `address` as of `block`.
+    pub async fn get_transaction_count(
+        &self,
+        address: Address,
+        block: impl Into<BlockId>,
+    ) -> Result<u64> {
+        let count = self
+            .provider
+            .get_transaction_count(address, Some(block.into()))
+            .await?;
+        Ok(count.as_u64())
+    }
+
+    /// Gets the latest and pending transaction counts of `address`.
+    pub async fn get_nonces(&self, address: Address) -> Result<OnchainNonces> {
+        let latest = self
+            .get_transaction_count(address, BlockNumber::Latest)
+            .await?;
+        let pending = self
+            .get_transaction_count(address, BlockNumber::Pending)
+            .await?;
+        Ok(OnchainNonces { latest, pending })
+    }
+
+    /// Executes `tx` with `eth_call` without creating a transaction.
+    ///
+    /// Runs against `block`, or the latest block if `None`. A revert comes back
+    /// as a provider error; see [`crate::decode::revert_data`].
+    pub async fn call(&self, tx: &TypedTransaction, block: Option<BlockId>) -> Result<Bytes> {
+        let output = self.provider.call(tx, block).await?;
+        Ok(output)
+    }
+
+    /// Estimates the gas `tx` needs with `eth_estimateGas`.
+    pub async fn estimate_gas(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<U256> {
+        let gas = self.provider.estimate_gas(tx, block).await?;
+        Ok(gas)
+    }
+
+    /// Runs `tx` through one of the node's built-in tracers with `debug_traceCall`.
+    ///
+    /// `tracer` is a geth tracer name such as `callTracer` or `prestateTracer`
+    /// and `config` its `tracerConfig`. Nodes without the `debug` namespace
+    /// return an error.
+    pub async fn trace_call(
+        &self,
+        tx: &TypedTransaction,
+        block: BlockId,
+        tracer: &str,
+        config: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let options = serde_json::json!({ "tracer": tracer, "tracerConfig": config });
+        let trace = self
+            .provider
+            .request("debug_traceCall", (tx, block, options))
+            .await?;
+        Ok(trace)
+    }
+
+    /// Re-executes a mined transaction with one of the node's built-in tracers
+    /// using `debug_traceTransaction`; see [`EthClient::trace_call`].
+    pub async fn trace_transaction(
+        &self,
+        hash: H256,
+        tracer: &str,
+        config: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let options = serde_json::json!({ "tracer": tracer, "tracerConfig": config });
+        let trace = self
+            .provider
+            .request("debug_traceTransaction", (hash, options))
+            .await?;
+        Ok(trace)
+    }
+
     /// Sends a signed transaction to the Ethereum network.
     ///
     /// # Arguments
@@ -107,8 +462,12 @@ impl EthClient {
         let tx_bytes = hex::decode(signed_tx_hex.strip_prefix("0x").unwrap_or(signed_tx_hex))?;
         let tx_bytes = Bytes::from(tx_bytes);
 
-        let pending_tx = self.provider.send_raw_transaction(tx_bytes).await?;
-        Ok(*pending_tx)
+        match self.provider.send_raw_transaction(tx_bytes.clone()).await {
+            Ok(pending_tx) => Ok(*pending_tx),
+            // Sent before, e.g. by a call that timed out after the node took it
+            Err(e) if nonce::is_already_known(&e) => Ok(H256::from(keccak256(&tx_bytes))),
+            Err(e) => Err(e.into()),
+        }
     }
 
     /// Gets information about a transaction by its hash.
@@ -142,11 +501,21 @@ impl EthClient {
     ///
     /// A `Result` containing an `Option<TransactionReceipt>` or a `WalletError`.
     /// The option will be `None` if the receipt is not yet available.
+    /// Receipts from finalized blocks are cached.
     pub async fn get_transaction_receipt(
         &self,
         tx_hash: H256,
     ) -> Result<Option<TransactionReceipt>> {
+        if let Some(receipt) = self.cache.receipt(tx_hash) {
+            return Ok(Some(receipt));
+        }
         let receipt = self.provider.get_transaction_receipt(tx_hash).await?;
+        if let Some(receipt) = &receipt {
+            let number = receipt.block_number.map(|n| n.as_u64());
+            if self.is_finalized(number).await {
+                self.cache.insert_receipt(receipt.clone());
+            }
+        }
         Ok(receipt)
     }
 }
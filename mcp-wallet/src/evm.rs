@@ -0,0 +1,341 @@
+//! Running transactions in an embedded EVM on top of a node's state.
+//!
+//! [`RpcDb`] forks the chain at a block: balances, nonces, code and storage
+//! are fetched through [`EthClient`] when the EVM first touches them and kept
+//! for the rest of the run. Only the standard `eth_getBalance`,
+//! `eth_getTransactionCount`, `eth_getCode` and `eth_getStorageAt` methods are
+//! used, so [`execute`] works against public endpoints without the debug
+//! namespace.
+
+use crate::{
+    error::{Result, WalletError},
+    eth_client::EthClient,
+};
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, AccountState, Block, BlockId, Bytes, DiffMode, Log,
+    NameOrAddress, H160, H256, U256 as EthU256,
+};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{
+        AccountInfo, Address, Bytecode, EVMError, ExecutionResult, ResultAndState, SpecId, TxEnv,
+        TxKind, B256, U256,
+    },
+    Evm,
+};
+use std::{collections::BTreeMap, future::Future, sync::Arc};
+use tokio::runtime::Handle;
+
+/// Chain state as of one block, read through an [`EthClient`] on demand.
+///
+/// The EVM is synchronous, so requests are driven on the runtime the database
+/// was created on; it must be used from a blocking thread, e.g. in
+/// [`tokio::task::spawn_blocking`].
+#[derive(Debug)]
+pub struct RpcDb {
+    client: Arc<EthClient>,
+    block: BlockId,
+    runtime: Handle,
+}
+
+impl RpcDb {
+    /// Creates a database reading the state as of `block`.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn new(client: Arc<EthClient>, block: BlockId) -> Self {
+        Self {
+            client,
+            block,
+            runtime: Handle::current(),
+        }
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+impl DatabaseRef for RpcDb {
+    type Error = WalletError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>> {
+        let address = H160(address.into_array());
+        let block = Some(self.block);
+        let (balance, nonce, code) = self.block_on(async {
+            tokio::try_join!(
+                self.client.get_balance_at(address, block),
+                self.client.get_transaction_count(address, self.block),
+                self.client.get_code_at(address, block),
+            )
+        })?;
+        // Empty accounts do not exist as far as the EVM is concerned
+        if balance.is_zero() && nonce == 0 && code.is_empty() {
+            return Ok(None);
+        }
+        let code = Bytecode::new_raw(code.0.into());
+        Ok(Some(AccountInfo::new(
+            U256::from_limbs(balance.0),
+            nonce,
+            code.hash_slow(),
+            code,
+        )))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode> {
+        // Code is loaded along with its account in `basic_ref`
+        Err(WalletError::WalletError(format!(
+            "Code {} was not loaded with its account",
+            code_hash
+        )))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256> {
+        let address = H160(address.into_array());
+        let slot = H256(index.to_be_bytes());
+        let value = self.block_on(self.client.get_storage_at(address, slot, Some(self.block)))?;
+        Ok(U256::from_be_bytes(value.0))
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256> {
+        let number = u64::try_from(number)
+            .map_err(|_| WalletError::WalletError(format!("Invalid block number {}", number)))?;
+        let hash = self
+            .block_on(self.client.get_block_hash(number))?
+            .ok_or_else(|| WalletError::WalletError(format!("Block {} not found", number)))?;
+        Ok(B256::from(hash.0))
+    }
+}
+
+/// The outcome of running a transaction with [`execute`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    /// Whether the transaction executed successfully.
+    pub success: bool,
+    /// Why execution halted, e.g. out of gas, if it neither succeeded nor
+    /// reverted.
+    pub halt_reason: Option<String>,
+    /// Returned data, or the revert data.
+    pub output: Bytes,
+    /// Gas used, after refunds.
+    pub gas_used: u64,
+    /// Emitted events in execution order.
+    pub logs: Vec<Log>,
+    /// Balances, nonces and written storage of the touched accounts before
+    /// and after, in the shape of geth's prestate tracer in diff mode.
+    pub diff: DiffMode,
+}
+
+/// Runs `tx` (with `from` set) on top of `block` in an embedded EVM.
+///
+/// The state is read lazily through `client`; the nonce is not checked, but
+/// the sender must be able to pay for the value and the gas. Fees are
+/// credited to the block producer as on chain.
+pub async fn execute(
+    client: Arc<EthClient>,
+    tx: &TypedTransaction,
+    block: BlockId,
+) -> Result<Execution> {
+    let header = client
+        .get_block(block)
+        .await?
+        .ok_or_else(|| WalletError::WalletError(format!("Block {:?} not found", block)))?;
+    let number = header
+        .number
+        .ok_or_else(|| WalletError::WalletError("Cannot run on top of a pending block".into()))?;
+    let chain_id = client.get_chain_id().await?;
+    let tx_env = tx_env(tx, &header)?;
+    let free = tx_env.gas_price.is_zero();
+
+    // Pin the state to the block's number, so `latest` cannot move mid-run
+    let db = RpcDb::new(client, number.into());
+    tokio::task::spawn_blocking(move || {
+        let mut db = CacheDB::new(db);
+        let ResultAndState { result, state } = {
+            let mut evm = Evm::builder()
+                .with_db(&mut db)
+                .with_spec_id(SpecId::CANCUN)
+                .modify_cfg_env(|cfg| {
+                    cfg.chain_id = chain_id;
+                    // Unpriced calls run like eth_call does
+                    cfg.disable_base_fee = free;
+                })
+                .modify_block_env(|env| {
+                    env.number = U256::from(number.as_u64());
+                    env.coinbase = Address::from(header.author.unwrap_or_default().0);
+                    env.timestamp = to_u256(header.timestamp);
+                    env.gas_limit = to_u256(header.gas_limit);
+                    env.basefee = to_u256(header.base_fee_per_gas.unwrap_or_default());
+                    env.difficulty = to_u256(header.difficulty);
+                    env.prevrandao = Some(B256::from(header.mix_hash.unwrap_or_default().0));
+                    env.set_blob_excess_gas_and_price(
+                        header.excess_blob_gas.unwrap_or_default().low_u64(),
+                    );
+                })
+                .with_tx_env(tx_env)
+                .build();
+            evm.transact().map_err(|e| match e {
+                EVMError::Database(e) => e,
+                EVMError::Transaction(e) => {
+                    WalletError::WalletError(format!("Invalid transaction: {}", e))
+                }
+                e => WalletError::WalletError(format!("EVM error: {}", e)),
+            })?
+        };
+
+        let mut diff = DiffMode {
+            pre: BTreeMap::new(),
+            post: BTreeMap::new(),
+        };
+        for (address, account) in state.iter().filter(|(_, a)| a.is_touched()) {
+            let before = db
+                .accounts
+                .get(address)
+                .map(|a| a.info.clone())
+                .unwrap_or_default();
+            let written: Vec<_> = account
+                .storage
+                .iter()
+                .filter(|(_, slot)| slot.is_changed())
+                .map(|(index, slot)| {
+                    let key = H256(index.to_be_bytes());
+                    (
+                        (key, H256(slot.original_value().to_be_bytes())),
+                        (key, H256(slot.present_value().to_be_bytes())),
+                    )
+                })
+                .collect();
+            let (pre_storage, post_storage) = written.into_iter().unzip();
+            let address = H160(address.into_array());
+            diff.pre.insert(
+                address,
+                AccountState {
+                    balance: Some(to_eth_u256(before.balance)),
+                    nonce: Some(before.nonce.into()),
+                    code: None,
+                    storage: Some(pre_storage),
+                },
+            );
+            diff.post.insert(
+                address,
+                AccountState {
+                    balance: Some(to_eth_u256(account.info.balance)),
+                    nonce: Some(account.info.nonce.into()),
+                    code: None,
+                    storage: Some(post_storage),
+                },
+            );
+        }
+
+        Ok(match result {
+            ExecutionResult::Success {
+                gas_used,
+                logs,
+                output,
+                ..
+            } => Execution {
+                success: true,
+                halt_reason: None,
+                output: output.into_data().0.into(),
+                gas_used,
+                logs: logs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, log)| Log {
+                        address: H160(log.address.into_array()),
+                        topics: log.topics().iter().map(|t| H256(t.0)).collect(),
+                        data: log.data.data.0.into(),
+                        log_index: Some(i.into()),
+                        ..Default::default()
+                    })
+                    .collect(),
+                diff,
+            },
+            ExecutionResult::Revert { gas_used, output } => Execution {
+                success: false,
+                halt_reason: None,
+                output: output.0.into(),
+                gas_used,
+                logs: Vec::new(),
+                diff,
+            },
+            ExecutionResult::Halt { reason, gas_used } => Execution {
+                success: false,
+                halt_reason: Some(format!("{:?}", reason)),
+                output: Bytes::new(),
+                gas_used,
+                logs: Vec::new(),
+                diff,
+            },
+        })
+    })
+    .await
+    .map_err(|e| WalletError::WalletError(format!("EVM task failed: {}", e)))?
+}
+
+fn tx_env(tx: &TypedTransaction, header: &Block<H256>) -> Result<TxEnv> {
+    let caller = tx
+        .from()
+        .ok_or_else(|| WalletError::WalletError("The transaction has no sender".into()))?;
+    let transact_to = match tx.to() {
+        None => TxKind::Create,
+        Some(NameOrAddress::Address(to)) => TxKind::Call(Address::from(to.0)),
+        Some(NameOrAddress::Name(name)) => {
+            return Err(WalletError::WalletError(format!(
+                "ENS name '{}' must be resolved first",
+                name
+            )))
+        }
+    };
+    let (gas_price, gas_priority_fee) = match tx {
+        TypedTransaction::Eip1559(inner) => (
+            inner.max_fee_per_gas.unwrap_or_default(),
+            inner.max_priority_fee_per_gas.map(to_u256),
+        ),
+        _ => (tx.gas_price().unwrap_or_default(), None),
+    };
+    let access_list = tx
+        .access_list()
+        .map(|list| {
+            list.0
+                .iter()
+                .map(|item| {
+                    (
+                        Address::from(item.address.0),
+                        item.storage_keys
+                            .iter()
+                            .map(|key| U256::from_be_bytes(key.0))
+                            .collect(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(TxEnv {
+        caller: Address::from(caller.0),
+        gas_limit: tx
+            .gas()
+            .copied()
+            .unwrap_or(header.gas_limit)
+            .min(u64::MAX.into())
+            .as_u64(),
+        gas_price: to_u256(gas_price),
+        gas_priority_fee,
+        transact_to,
+        value: to_u256(tx.value().copied().unwrap_or_default()),
+        data: tx.data().cloned().unwrap_or_default().0.into(),
+        // Queued transactions may be ahead of the state, and the chain
+        // id is guarded before signing
+        nonce: None,
+        chain_id: None,
+        access_list,
+        ..Default::default()
+    })
+}
+
+fn to_u256(value: EthU256) -> U256 {
+    U256::from_limbs(value.0)
+}
+
+fn to_eth_u256(value: U256) -> EthU256 {
+    EthU256(value.into_limbs())
+}
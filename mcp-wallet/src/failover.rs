@@ -0,0 +1,729 @@
+//! Several RPC endpoints for one network, used as one.
+//!
+//! A [`FailoverClient`] sends each request to its healthiest endpoint and moves
+//! on to the next one when a request fails or times out. A JSON-RPC error the
+//! node answers with, such as a revert, is a valid answer and is returned as
+//! is. Transactions are sent once, to one endpoint: a send that failed or
+//! timed out may still have reached the node, so repeating it is not safe. Every outcome updates the endpoint's health score, a moving average of
+//! its recent successes, so an endpoint that keeps failing drops to the back
+//! until it recovers.
+//!
+//! Every endpoint is asked for its chain id when it is first used, and one
+//! that serves another chain than the network's is failed, so that a
+//! misconfigured fallback cannot answer for the wrong chain.
+//!
+//! Rate limiting (HTTP 429 or a "rate limit" JSON-RPC error), server errors
+//! (5xx) and reset connections are usually over quickly, so they are retried
+//! on the same endpoint first, after an exponential backoff with jitter. Each
+//! endpoint can also be throttled to a number of requests per second, to stay
+//! below a provider's limits in the first place.
+//!
+//! With a quorum of N, reads of chain state (balances, receipts, calls, ...)
+//! go to all endpoints at once and only a result that N of them agree on is
+//! returned. A single lying provider then causes an error instead of a wrong
+//! answer. Reads of the latest block are pinned to the lowest head any
+//! endpoint reports first, as synced nodes are often a block or two apart.
+
+use crate::transport::{HttpResponseError, Transport};
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, ProviderError, PubsubClient, RpcError};
+use ethers::types::{U256, U64};
+use futures::{channel::mpsc, future::join_all};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, value::RawValue, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Debug,
+    future::Future,
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+
+/// How long one endpoint may take to answer before the next one is tried.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Retries of a retryable error on one endpoint before the next one is tried.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry.
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Upper bound of the delay between retries.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Weight of the latest outcome in an endpoint's health score.
+const HEALTH_WEIGHT: f64 = 0.3;
+
+/// Methods that submit a transaction, which are neither retried nor failed over.
+const SEND_METHODS: &[&str] = &["eth_sendRawTransaction", "eth_sendTransaction"];
+
+/// Methods whose results are compared across endpoints in quorum mode.
+///
+/// They read chain state that every honest, synced node reports the same way.
+const QUORUM_METHODS: &[&str] = &[
+    "eth_chainId",
+    "eth_getBalance",
+    "eth_getTransactionCount",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_call",
+    "eth_getTransactionByHash",
+    "eth_getTransactionReceipt",
+    "eth_getBlockByNumber",
+    "eth_getBlockByHash",
+    "eth_getLogs",
+];
+
+/// How a [`FailoverClient`] uses its endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailoverOptions {
+    /// Endpoints that must agree on a read; `None` or 1 reads from one.
+    pub quorum: Option<usize>,
+    /// Time an endpoint may take to answer one request.
+    pub timeout: Duration,
+    /// Retries of a retryable error on the same endpoint.
+    pub max_retries: u32,
+    /// Delay before the first retry; it doubles with every further one.
+    pub backoff: Duration,
+    /// Upper bound of the delay between retries.
+    pub max_backoff: Duration,
+    /// Requests per second sent to each endpoint; unlimited if `None`.
+    pub rate_limit: Option<u32>,
+}
+
+impl Default for FailoverOptions {
+    fn default() -> Self {
+        Self {
+            quorum: None,
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            rate_limit: None,
+        }
+    }
+}
+
+impl FailoverOptions {
+    /// The delay before retry number `retry` (counting from 0): the backoff
+    /// doubled `retry` times, capped, and then a random time between half of
+    /// that and all of it, so that clients do not retry in lockstep.
+    pub fn retry_delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// The health of one endpoint, as reported by [`FailoverClient::health`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct EndpointHealth {
+    /// The endpoint URL or socket path.
+    pub url: String,
+    /// Moving average of recent outcomes, from 0 (failing) to 1 (healthy).
+    pub score: f64,
+    /// Duration of the last successful request, in milliseconds.
+    pub latency_ms: Option<u64>,
+    /// Requests answered.
+    pub successes: u64,
+    /// Requests that failed or timed out.
+    pub failures: u64,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    transport: Transport,
+    health: Mutex<EndpointHealth>,
+    /// When the rate limit lets the next request go.
+    next_slot: Mutex<Instant>,
+    /// The chain id the endpoint reported, once asked; `None` if it had none.
+    chain_id: OnceCell<Option<u64>>,
+}
+
+impl Endpoint {
+    /// Waits until a request may be sent without exceeding `rate_limit`
+    /// requests per second.
+    async fn throttle(&self, rate_limit: Option<u32>) {
+        let Some(rate_limit) = rate_limit.filter(|r| *r > 0) else {
+            return;
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + Duration::from_secs(1) / rate_limit;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+
+    fn record(&self, answered: bool, elapsed: Duration) {
+        let mut health = self.health.lock().unwrap();
+        let outcome = if answered { 1.0 } else { 0.0 };
+        health.score = (1.0 - HEALTH_WEIGHT) * health.score + HEALTH_WEIGHT * outcome;
+        if answered {
+            health.successes += 1;
+            health.latency_ms = Some(elapsed.as_millis() as u64);
+        } else {
+            health.failures += 1;
+        }
+    }
+
+    fn url(&self) -> String {
+        self.health.lock().unwrap().url.clone()
+    }
+}
+
+/// A JSON-RPC client over several endpoints of the same network.
+#[derive(Debug, Clone)]
+pub struct FailoverClient {
+    endpoints: Arc<Vec<Endpoint>>,
+    options: FailoverOptions,
+    /// Which endpoint each `eth_subscribe` subscription lives on.
+    subscriptions: Arc<Mutex<HashMap<U256, usize>>>,
+    /// The chain id every endpoint must serve: the expected one, or else the
+    /// first one an endpoint reported.
+    chain_id: Arc<Mutex<Option<u64>>>,
+}
+
+impl FailoverClient {
+    /// Creates a client for `urls`, tried in this order while all are equally
+    /// healthy.
+    pub fn new(urls: &[&str], options: FailoverOptions) -> Result<Self, String> {
+        if urls.is_empty() {
+            return Err("At least one RPC URL is needed".into());
+        }
+        if let Some(quorum) = options.quorum {
+            if quorum == 0 || quorum > urls.len() {
+                return Err(format!(
+                    "Quorum must be between 1 and the number of RPC URLs ({}), got {}",
+                    urls.len(),
+                    quorum
+                ));
+            }
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                Ok(Endpoint {
+                    transport: Transport::new(url)?,
+                    health: Mutex::new(EndpointHealth {
+                        url: url.to_string(),
+                        score: 1.0,
+                        latency_ms: None,
+                        successes: 0,
+                        failures: 0,
+                    }),
+                    next_slot: Mutex::new(Instant::now()),
+                    chain_id: OnceCell::new(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self {
+            endpoints: Arc::new(endpoints),
+            options,
+            subscriptions: Arc::default(),
+            chain_id: Arc::default(),
+        })
+    }
+
+    /// Sets the chain id every endpoint must serve; endpoints that report
+    /// another one are failed.
+    pub fn expect_chain_id(&self, chain_id: u64) {
+        *self.chain_id.lock().unwrap() = Some(chain_id);
+    }
+
+    /// Whether any endpoint can carry `eth_subscribe` notifications.
+    pub fn supports_subscriptions(&self) -> bool {
+        self.endpoints
+            .iter()
+            .any(|e| e.transport.supports_subscriptions())
+    }
+
+    /// The health of every endpoint, in configured order.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.endpoints
+            .iter()
+            .map(|e| e.health.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Endpoint indices by descending health; ties keep the configured order.
+    fn ranked(&self) -> Vec<usize> {
+        let scores: Vec<f64> = self
+            .endpoints
+            .iter()
+            .map(|e| e.health.lock().unwrap().score)
+            .collect();
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        order
+    }
+
+    /// The chain id endpoint `index` reports, asking it the first time.
+    async fn reported_chain_id(&self, index: usize) -> Result<Option<u64>, ProviderError> {
+        let method = "eth_chainId";
+        let reported = self.endpoints[index]
+            .chain_id
+            .get_or_try_init(|| async {
+                let (attempt, _) = self
+                    .send_with_retries(index, method, self.options.max_retries, |transport| {
+                        transport.request::<_, Option<U256>>(method, ())
+                    })
+                    .await;
+                match attempt {
+                    Attempt::Answered(answer) => Ok(answer.ok().flatten().map(|id| id.as_u64())),
+                    Attempt::Failed(e) => Err(e),
+                }
+            })
+            .await?;
+        Ok(*reported)
+    }
+
+    /// Makes sure endpoint `index` serves the network's chain: the expected
+    /// one, or else the one the primary endpoint reports (or, while it cannot
+    /// be reached, the first one reported).
+    ///
+    /// A single endpoint is not checked: there is nothing to compare it with
+    /// but an expected chain id, which [`crate::eth_client::EthClient`]
+    /// verifies itself before signing or sending. An endpoint that cannot
+    /// report a chain id is not failed either.
+    async fn check_chain(&self, index: usize) -> Result<(), ProviderError> {
+        if self.endpoints.len() < 2 {
+            return Ok(());
+        }
+        let Some(actual) = self.reported_chain_id(index).await? else {
+            return Ok(());
+        };
+        let known = *self.chain_id.lock().unwrap();
+        let expected = match known {
+            Some(expected) => expected,
+            None => {
+                let primary = self.reported_chain_id(0).await.ok().flatten();
+                *self
+                    .chain_id
+                    .lock()
+                    .unwrap()
+                    .get_or_insert(primary.unwrap_or(actual))
+            }
+        };
+        if actual != expected {
+            return Err(ProviderError::CustomError(format!(
+                "{} serves chain {}, not chain {}",
+                self.endpoints[index].url(),
+                actual,
+                expected
+            )));
+        }
+        Ok(())
+    }
+
+    /// Sends a request for `label` to endpoint `index` with `send`, once the
+    /// endpoint's chain is checked, retrying transient errors up to
+    /// `max_retries` times, and records the outcome. The chain check is not
+    /// counted separately: a failed one fails the request.
+    async fn attempt<'a, T, F, Fut>(
+        &'a self,
+        index: usize,
+        label: &str,
+        max_retries: u32,
+        send: F,
+    ) -> Attempt<T>
+    where
+        F: Fn(&'a Transport) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>> + 'a,
+    {
+        let endpoint = &self.endpoints[index];
+        if let Err(e) = self.check_chain(index).await {
+            endpoint.record(false, Duration::ZERO);
+            return Attempt::Failed(e);
+        }
+        let (attempt, elapsed) = self
+            .send_with_retries(index, label, max_retries, send)
+            .await;
+        endpoint.record(matches!(attempt, Attempt::Answered(_)), elapsed);
+        attempt
+    }
+
+    /// Sends a request for `label` to endpoint `index` with `send`, retrying
+    /// transient errors up to `max_retries` times. Also returns how long the
+    /// last try took.
+    async fn send_with_retries<'a, T, F, Fut>(
+        &'a self,
+        index: usize,
+        label: &str,
+        max_retries: u32,
+        send: F,
+    ) -> (Attempt<T>, Duration)
+    where
+        F: Fn(&'a Transport) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>> + 'a,
+    {
+        let endpoint = &self.endpoints[index];
+        let mut retry = 0;
+        loop {
+            endpoint.throttle(self.options.rate_limit).await;
+            let started = Instant::now();
+            let result =
+                tokio::time::timeout(self.options.timeout, send(&endpoint.transport)).await;
+            let attempt = match result {
+                Ok(Ok(value)) => Attempt::Answered(Ok(value)),
+                Ok(Err(e)) if is_retryable(&e) => {
+                    if retry < max_retries {
+                        let delay = self.options.retry_delay(retry);
+                        retry += 1;
+                        log::warn!(
+                            "{} failed on {}, retry {}/{} in {:?}: {}",
+                            label,
+                            endpoint.url(),
+                            retry,
+                            max_retries,
+                            delay,
+                            e
+                        );
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    Attempt::Failed(e)
+                }
+                // The node answered; another one would most likely say the same
+                Ok(Err(e)) if e.as_error_response().is_some() => Attempt::Answered(Err(e)),
+                Ok(Err(e)) => Attempt::Failed(e),
+                Err(_) => Attempt::Failed(ProviderError::CustomError(format!(
+                    "{} timed out after {:?} on {}",
+                    label,
+                    self.options.timeout,
+                    endpoint.url()
+                ))),
+            };
+            return (attempt, started.elapsed());
+        }
+    }
+
+    /// Tries the endpoints in `order` with `send` until one answers.
+    async fn first_answer<'a, T, F, Fut>(
+        &'a self,
+        order: &[usize],
+        label: &str,
+        send: F,
+    ) -> Result<(usize, T), ProviderError>
+    where
+        F: Fn(&'a Transport) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>> + 'a,
+    {
+        let mut last_error = None;
+        for &index in order {
+            match self
+                .attempt(index, label, self.options.max_retries, &send)
+                .await
+            {
+                Attempt::Answered(result) => return result.map(|value| (index, value)),
+                Attempt::Failed(e) => {
+                    log::warn!(
+                        "{} failed on {}, trying the next endpoint: {}",
+                        label,
+                        self.endpoints[index].url(),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            ProviderError::CustomError(format!("No RPC endpoint can serve {}", label))
+        }))
+    }
+
+    /// Sends one request to the endpoints in `order` until one answers.
+    async fn request_value(
+        &self,
+        order: &[usize],
+        method: &str,
+        params: &Value,
+    ) -> Result<(usize, Value), ProviderError> {
+        self.first_answer(order, method, |transport| {
+            transport.request::<_, Value>(method, params)
+        })
+        .await
+    }
+
+    /// Sends a transaction to the healthiest endpoint, without retries.
+    ///
+    /// Whether a send that failed or timed out reached the node is unknown, and
+    /// a repeat would be answered with "already known" or "nonce too low"
+    /// either way, so the error is returned for the caller to judge.
+    async fn send_once(&self, method: &str, params: &Value) -> Result<Value, ProviderError> {
+        let index = self.ranked()[0];
+        let attempt = self
+            .attempt(index, method, 0, |transport| {
+                transport.request::<_, Value>(method, params)
+            })
+            .await;
+        match attempt {
+            Attempt::Answered(result) => result,
+            Attempt::Failed(e) => Err(e),
+        }
+    }
+
+    /// Sends `calls` together, as one JSON-RPC batch over HTTP, and returns
+    /// their answers in order.
+    ///
+    /// The batch as a whole fails over like a single request. In quorum mode
+    /// the calls are sent one by one instead, so that each read is compared.
+    pub async fn batch(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Result<Value, ProviderError>>, ProviderError> {
+        if self.options.quorum.is_some_and(|q| q > 1) {
+            return Ok(join_all(
+                calls
+                    .iter()
+                    .map(|(method, params)| self.request::<_, Value>(method, params)),
+            )
+            .await);
+        }
+        let label = format!("A batch of {} calls", calls.len());
+        let (_, answers) = self
+            .first_answer(&self.ranked(), &label, |transport| transport.batch(calls))
+            .await?;
+        Ok(answers)
+    }
+
+    /// The lowest head block the endpoints report, which every endpoint that
+    /// answered has.
+    async fn lowest_head(&self) -> Result<U64, ProviderError> {
+        let method = "eth_blockNumber";
+        let attempts = join_all((0..self.endpoints.len()).map(|index| {
+            self.attempt(index, method, self.options.max_retries, |transport| {
+                transport.request::<_, U64>(method, ())
+            })
+        }))
+        .await;
+        attempts
+            .into_iter()
+            .filter_map(|attempt| match attempt {
+                Attempt::Answered(Ok(head)) => Some(head),
+                _ => None,
+            })
+            .min()
+            .ok_or_else(|| {
+                ProviderError::CustomError("No RPC endpoint reported its head block".into())
+            })
+    }
+
+    /// Asks every endpoint and returns the result `quorum` of them agree on.
+    async fn quorum_answer(
+        &self,
+        quorum: usize,
+        method: &str,
+        params: &Value,
+    ) -> Result<Value, ProviderError> {
+        let attempts = join_all((0..self.endpoints.len()).map(|index| {
+            self.attempt(index, method, self.options.max_retries, |transport| {
+                transport.request::<_, Value>(method, params)
+            })
+        }))
+        .await;
+
+        let mut votes: Vec<(Value, usize)> = Vec::new();
+        let mut rejections = Vec::new();
+        for attempt in attempts {
+            match attempt {
+                Attempt::Answered(Ok(value)) => match votes.iter_mut().find(|(v, _)| *v == value) {
+                    Some((_, count)) => *count += 1,
+                    None => votes.push((value, 1)),
+                },
+                Attempt::Answered(Err(e)) => rejections.push(e),
+                Attempt::Failed(e) => log::warn!("{} failed in a quorum read: {}", method, e),
+            }
+        }
+        votes.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let agreeing = votes.first().map(|(_, count)| *count).unwrap_or(0);
+        if agreeing >= quorum {
+            return Ok(votes.swap_remove(0).0);
+        }
+        // Endpoints that all rejected the request agree as well, e.g. on a revert
+        if rejections.len() >= quorum {
+            return Err(rejections.swap_remove(0));
+        }
+        Err(ProviderError::CustomError(format!(
+            "No quorum for {}: {} of {} endpoints had to agree, at most {} did",
+            method,
+            quorum,
+            self.endpoints.len(),
+            agreeing
+        )))
+    }
+}
+
+enum Attempt<T> {
+    /// The node answered, with a result or a JSON-RPC error.
+    Answered(Result<T, ProviderError>),
+    /// The endpoint could not be reached, failed or timed out.
+    Failed(ProviderError),
+}
+
+/// Whether `error` is likely transient: rate limiting, a server error or a
+/// reset connection.
+pub fn is_retryable(error: &ProviderError) -> bool {
+    match error {
+        ProviderError::JsonRpcClientError(e) => {
+            if let Some(response) = e.as_error_response() {
+                let message = response.message.to_lowercase();
+                // -32005 is the "limit exceeded" code of EIP-1474
+                return matches!(response.code, 429 | -32005)
+                    || message.contains("rate limit")
+                    || message.contains("too many requests");
+            }
+            let e: &(dyn Error + 'static) = &**e;
+            match e.downcast_ref::<HttpResponseError>() {
+                Some(response) => response.status == 429 || response.status >= 500,
+                None => is_connection_reset(e),
+            }
+        }
+        ProviderError::HTTPError(e) => is_connection_reset(e),
+        _ => false,
+    }
+}
+
+/// Whether `error` or one of its causes is a dropped connection.
+fn is_connection_reset(error: &(dyn Error + 'static)) -> bool {
+    let mut cause = Some(error);
+    while let Some(e) = cause {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            if matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+            ) {
+                return true;
+            }
+        }
+        cause = e.source();
+    }
+    false
+}
+
+/// Whether `method` is compared across endpoints in quorum mode. Reads of the
+/// pending state are not, as every node sees its own mempool.
+fn is_quorum_read(method: &str, params: &Value) -> bool {
+    let pending = params
+        .as_array()
+        .is_some_and(|params| params.iter().any(|p| p == "pending"));
+    QUORUM_METHODS.contains(&method) && !pending
+}
+
+/// The block parameters of a quorum read that refer to the latest block,
+/// explicitly or by being left out.
+fn latest_blocks<'a>(method: &str, params: &'a mut Value) -> Vec<&'a mut Value> {
+    let index = match method {
+        "eth_getBlockByNumber" => 0,
+        "eth_getBalance" | "eth_getTransactionCount" | "eth_getCode" | "eth_call" => 1,
+        "eth_getStorageAt" => 2,
+        "eth_getLogs" => {
+            let Some(filter) = params.get_mut(0).and_then(Value::as_object_mut) else {
+                return Vec::new();
+            };
+            if filter.contains_key("blockHash") {
+                return Vec::new();
+            }
+            filter.entry("fromBlock").or_insert(Value::Null);
+            filter.entry("toBlock").or_insert(Value::Null);
+            return filter
+                .iter_mut()
+                .filter(|(key, block)| {
+                    matches!(key.as_str(), "fromBlock" | "toBlock") && is_latest(block)
+                })
+                .map(|(_, block)| block)
+                .collect();
+        }
+        _ => return Vec::new(),
+    };
+    let Some(params) = params.as_array_mut() else {
+        return Vec::new();
+    };
+    if params.len() <= index {
+        params.resize(index + 1, Value::Null);
+    }
+    params
+        .get_mut(index)
+        .filter(|block| is_latest(block))
+        .into_iter()
+        .collect()
+}
+
+fn is_latest(block: &Value) -> bool {
+    block.is_null() || block == "latest"
+}
+
+#[async_trait]
+impl JsonRpcClient for FailoverClient {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let mut params = serde_json::to_value(params)?;
+        let quorum = self.options.quorum.filter(|q| *q > 1);
+        let value = match quorum {
+            Some(quorum) if is_quorum_read(method, &params) => {
+                // Nodes at different heads disagree on the latest state
+                let latest = latest_blocks(method, &mut params);
+                if !latest.is_empty() {
+                    let head = json!(self.lowest_head().await?);
+                    for block in latest {
+                        *block = head.clone();
+                    }
+                }
+                self.quorum_answer(quorum, method, &params).await?
+            }
+            _ if SEND_METHODS.contains(&method) => self.send_once(method, &params).await?,
+            _ if method == "eth_subscribe" => {
+                // Only sockets can deliver the notifications
+                let order: Vec<usize> = self
+                    .ranked()
+                    .into_iter()
+                    .filter(|i| self.endpoints[*i].transport.supports_subscriptions())
+                    .collect();
+                let (index, value) = self.request_value(&order, method, &params).await?;
+                let id: U256 = serde_json::from_value(value.clone())?;
+                self.subscriptions.lock().unwrap().insert(id, index);
+                value
+            }
+            _ => self.request_value(&self.ranked(), method, &params).await?.1,
+        };
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl PubsubClient for FailoverClient {
+    type NotificationStream = mpsc::UnboundedReceiver<Box<RawValue>>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, ProviderError> {
+        let id = id.into();
+        let index = self.subscriptions.lock().unwrap().get(&id).copied();
+        match index {
+            Some(index) => self.endpoints[index].transport.subscribe(id),
+            None => Err(ProviderError::CustomError(format!(
+                "Unknown subscription {}",
+                id
+            ))),
+        }
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), ProviderError> {
+        let id = id.into();
+        match self.subscriptions.lock().unwrap().remove(&id) {
+            Some(index) => self.endpoints[index].transport.unsubscribe(id),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! A persistent journal of the transactions the wallet signed or broadcast.
+//!
+//! Every record is decoded from the signed raw transaction, so transactions
+//! signed with `sign_tx` and sent later, sent by `eth_transfer_eth`, or sent
+//! as foreign raw bytes through `eth_send_signed_transaction` all end up with
+//! the same fields. Statuses are refreshed from receipts and the sender's
+//! on-chain nonce. The journal lives next to the wallet file of its profile.
+
+use crate::{
+    backup::RestoreMode,
+    error::{Result, WalletError},
+    eth_client::EthClient,
+};
+use ethers::{
+    types::{transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, H256, U256, U64},
+    utils::{keccak256, rlp::Rlp, to_checksum},
+};
+use serde::{Deserialize, Serialize, Serializer};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// Where a journaled transaction stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    /// Signed by the wallet but not broadcast by it.
+    Signed,
+    /// Broadcast and waiting to be mined.
+    Pending,
+    /// Mined and executed successfully.
+    Mined,
+    /// Mined but reverted.
+    Failed,
+    /// The node no longer knows the transaction and its nonce is still unused.
+    Dropped,
+    /// Another transaction with the same nonce was mined instead.
+    Replaced,
+}
+
+impl TxStatus {
+    /// Whether the status can no longer change (barring reorgs).
+    pub fn is_final(self) -> bool {
+        matches!(self, Self::Mined | Self::Failed | Self::Replaced)
+    }
+}
+
+/// A transaction the wallet signed or broadcast.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxRecord {
+    /// The transaction hash.
+    pub hash: H256,
+    /// The chain id the transaction was signed for; 0 without replay protection.
+    pub chain_id: u64,
+    /// The sender, recovered from the signature.
+    #[serde(serialize_with = "serialize_checksum")]
+    pub from: Address,
+    /// The recipient, `None` for contract creation.
+    #[serde(serialize_with = "serialize_checksum_opt")]
+    pub to: Option<Address>,
+    /// Value in wei.
+    pub value: U256,
+    /// The sender nonce.
+    pub nonce: u64,
+    /// The signed raw transaction.
+    pub raw_transaction: Bytes,
+    /// The last known status.
+    pub status: TxStatus,
+    /// The block the transaction was mined in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// The transaction this one replaced via `speed_up_tx`/`cancel_tx`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<H256>,
+    /// The journaled transaction that was mined with this one's nonce.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<H256>,
+    /// When the transaction was first recorded, in unix seconds.
+    pub created_at: u64,
+    /// When the wallet broadcast the transaction, in unix seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<u64>,
+    /// When the status last changed, in unix seconds.
+    pub updated_at: u64,
+}
+
+impl TxRecord {
+    /// Decodes a signed raw transaction into a fresh record with status
+    /// [`TxStatus::Signed`].
+    pub fn decode(raw: &[u8]) -> Result<Self> {
+        let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(raw))
+            .map_err(|e| WalletError::WalletError(format!("Invalid signed transaction: {}", e)))?;
+        let from = signature.recover(tx.sighash())?;
+        let now = unix_now();
+        Ok(Self {
+            hash: H256::from(keccak256(raw)),
+            chain_id: tx.chain_id().map(|id| id.as_u64()).unwrap_or_default(),
+            from,
+            to: tx.to_addr().copied(),
+            value: tx.value().copied().unwrap_or_default(),
+            nonce: tx.nonce().map(|n| n.as_u64()).unwrap_or_default(),
+            raw_transaction: Bytes::from(raw.to_vec()),
+            status: TxStatus::Signed,
+            block_number: None,
+            replaces: None,
+            replaced_by: None,
+            created_at: now,
+            sent_at: None,
+            updated_at: now,
+        })
+    }
+
+    fn involves(&self, account: Address) -> bool {
+        self.from == account || self.to == Some(account)
+    }
+}
+
+/// Filters for [`TxJournal::query`]. Empty fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct TxQuery {
+    /// Transactions sent from or to this address.
+    pub account: Option<Address>,
+    /// Transactions with this status.
+    pub status: Option<TxStatus>,
+    /// Recorded at or after this unix time.
+    pub since: Option<u64>,
+    /// Recorded at or before this unix time.
+    pub until: Option<u64>,
+    /// At most this many, newest first.
+    pub limit: Option<usize>,
+}
+
+/// The transaction history of one wallet.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TxJournal {
+    /// Recorded transactions, oldest first.
+    transactions: Vec<TxRecord>,
+    #[serde(skip)]
+    file_path: Option<PathBuf>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl TxJournal {
+    /// Creates an empty journal that is not backed by a file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a journal from `path`, or starts an empty one if the file does not exist.
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut journal: Self = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(WalletError::FileError(e)),
+        };
+        journal.file_path = Some(path.to_path_buf());
+        Ok(journal)
+    }
+
+    /// Writes the journal to its file if it has unsaved changes.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(path) = &self.file_path {
+            let contents = serde_json::to_string_pretty(&*self)?;
+            std::fs::write(path, contents)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Gets the file path of the journal.
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    /// Records a signed raw transaction.
+    ///
+    /// With `sent` the transaction is marked as broadcast. Recording a known
+    /// transaction again only updates that and `replaces`.
+    pub fn record(&mut self, raw: &[u8], sent: bool, replaces: Option<H256>) -> Result<TxRecord> {
+        let decoded = TxRecord::decode(raw)?;
+        let index = match self.position(decoded.hash) {
+            Some(index) => index,
+            None => {
+                self.transactions.push(decoded);
+                self.transactions.len() - 1
+            }
+        };
+        let record = &mut self.transactions[index];
+        if sent {
+            let now = unix_now();
+            record.sent_at.get_or_insert(now);
+            // A dropped transaction that is broadcast again is back in the mempool
+            if matches!(record.status, TxStatus::Signed | TxStatus::Dropped) {
+                record.status = TxStatus::Pending;
+                record.updated_at = now;
+            }
+        }
+        if replaces.is_some() {
+            record.replaces = replaces;
+        }
+        self.dirty = true;
+        Ok(record.clone())
+    }
+
+    /// The number of recorded transactions.
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Whether no transactions are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Gets a transaction by hash.
+    pub fn get(&self, hash: H256) -> Option<&TxRecord> {
+        self.transactions.iter().find(|r| r.hash == hash)
+    }
+
+    /// Lists transactions matching `query`, newest first.
+    pub fn query(&self, query: &TxQuery) -> Vec<&TxRecord> {
+        self.transactions
+            .iter()
+            .rev()
+            .filter(|r| query.account.is_none_or(|a| r.involves(a)))
+            .filter(|r| query.status.is_none_or(|s| r.status == s))
+            .filter(|r| query.since.is_none_or(|t| r.created_at >= t))
+            .filter(|r| query.until.is_none_or(|t| r.created_at <= t))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Updates the status of a transaction; returns whether it changed.
+    pub fn set_status(&mut self, hash: H256, status: TxStatus, block_number: Option<u64>) -> bool {
+        let Some(index) = self.position(hash) else {
+            return false;
+        };
+        let record = &mut self.transactions[index];
+        if record.status == status && record.block_number == block_number {
+            return false;
+        }
+        record.status = status;
+        record.block_number = block_number;
+        record.updated_at = unix_now();
+        if status == TxStatus::Mined || status == TxStatus::Failed {
+            self.link_replaced(index);
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// Points replaced siblings of the mined transaction at `index` to it.
+    fn link_replaced(&mut self, index: usize) {
+        let winner = self.transactions[index].clone();
+        for record in &mut self.transactions {
+            if record.hash != winner.hash
+                && record.from == winner.from
+                && record.chain_id == winner.chain_id
+                && record.nonce == winner.nonce
+            {
+                record.status = TxStatus::Replaced;
+                record.replaced_by = Some(winner.hash);
+                record.block_number = None;
+                record.updated_at = winner.updated_at;
+            }
+        }
+    }
+
+    /// Applies the journal of a backup and returns the hashes of the records
+    /// added and removed.
+    ///
+    /// In [`RestoreMode::Merge`] records for unknown hashes are added and the
+    /// local ones kept; in [`RestoreMode::Replace`] the backup's records
+    /// replace all local ones. With `dry_run` the journal is not modified.
+    pub fn restore(
+        &mut self,
+        backup: TxJournal,
+        mode: RestoreMode,
+        dry_run: bool,
+    ) -> (Vec<H256>, Vec<H256>) {
+        let added: Vec<H256> = backup
+            .transactions
+            .iter()
+            .filter(|r| self.get(r.hash).is_none())
+            .map(|r| r.hash)
+            .collect();
+        let removed: Vec<H256> = match mode {
+            RestoreMode::Merge => Vec::new(),
+            RestoreMode::Replace => self
+                .transactions
+                .iter()
+                .filter(|r| backup.get(r.hash).is_none())
+                .map(|r| r.hash)
+                .collect(),
+        };
+        if !dry_run {
+            match mode {
+                RestoreMode::Merge => {
+                    self.transactions.extend(
+                        backup
+                            .transactions
+                            .into_iter()
+                            .filter(|r| added.contains(&r.hash)),
+                    );
+                    // Keep the records oldest first
+                    self.transactions.sort_by_key(|r| r.created_at);
+                }
+                RestoreMode::Replace => self.transactions = backup.transactions,
+            }
+            self.dirty = true;
+        }
+        (added, removed)
+    }
+
+    fn position(&self, hash: H256) -> Option<usize> {
+        self.transactions.iter().position(|r| r.hash == hash)
+    }
+}
+
+/// Returns the journal file that belongs to a wallet file, e.g.
+/// `~/.mcp-wallet.history.json` for `~/.mcp-wallet.json`.
+pub fn history_path(wallet_path: &Path) -> PathBuf {
+    wallet_path.with_extension("history.json")
+}
+
+/// Refreshes the status of journaled transactions on the client's chain.
+///
+/// Only transactions whose status is not final are checked; pass `hashes` to
+/// limit the refresh further. Returns the number of records that changed.
+pub async fn refresh(
+    journal: &Mutex<TxJournal>,
+    client: &EthClient,
+    hashes: Option<&[H256]>,
+) -> Result<usize> {
+    let chain_id = client.get_chain_id().await?;
+    let open: Vec<TxRecord> = journal
+        .lock()
+        .await
+        .transactions
+        .iter()
+        .filter(|r| !r.status.is_final() && (r.chain_id == chain_id || r.chain_id == 0))
+        .filter(|r| hashes.is_none_or(|h| h.contains(&r.hash)))
+        .cloned()
+        .collect();
+    if open.is_empty() {
+        return Ok(0);
+    }
+
+    let mut updates = Vec::with_capacity(open.len());
+    for record in open {
+        // Read the nonce first, so a transaction mined in between is seen below
+        let nonce_used = if record.status == TxStatus::Signed {
+            false
+        } else {
+            client
+                .get_transaction_count(record.from, BlockNumber::Latest)
+                .await?
+                > record.nonce
+        };
+        let status = match client.get_transaction_receipt(record.hash).await? {
+            Some(receipt) => {
+                let block_number = receipt.block_number.map(|b| b.as_u64());
+                if receipt.status == Some(U64::zero()) {
+                    (TxStatus::Failed, block_number)
+                } else {
+                    (TxStatus::Mined, block_number)
+                }
+            }
+            None if record.status == TxStatus::Signed => continue,
+            None if nonce_used => (TxStatus::Replaced, None),
+            None => match client.get_transaction_info(record.hash).await? {
+                Some(_) => (TxStatus::Pending, None),
+                None => (TxStatus::Dropped, None),
+            },
+        };
+        updates.push((record.hash, status));
+    }
+
+    let mut journal = journal.lock().await;
+    let mut changed = 0;
+    for (hash, (status, block_number)) in updates {
+        if journal.set_status(hash, status, block_number) {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn serialize_checksum<S: Serializer>(address: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_checksum(address, None))
+}
+
+fn serialize_checksum_opt<S: Serializer>(
+    address: &Option<Address>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match address {
+        Some(address) => serialize_checksum(address, serializer),
+        None => serializer.serialize_none(),
+    }
+}
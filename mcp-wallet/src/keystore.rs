@@ -0,0 +1,163 @@
+//! Import and export of Web3 Secret Storage (V3) keystore files.
+//!
+//! These are the encrypted JSON files geth keeps in its `keystore/` directory
+//! and Foundry keeps in `~/.foundry/keystores`. Exported files carry the
+//! `address` field geth needs to index them, and are named after the account
+//! alias so `cast wallet` lists them by name.
+
+use crate::error::{Result, WalletError};
+use crate::secret::PrivateKey;
+use ethers::signers::Signer;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Describes the outcome of importing a keystore file or directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct KeystoreImportReport {
+    /// Keystores that were decrypted and imported.
+    pub imported: Vec<ImportedKeystore>,
+    /// Files in a directory that could not be imported.
+    pub skipped: Vec<SkippedKeystore>,
+    /// File-name aliases left unset because they already point elsewhere.
+    pub alias_conflicts: Vec<String>,
+}
+
+/// A keystore file that was imported into the wallet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportedKeystore {
+    /// Path of the keystore file.
+    pub file: String,
+    /// Checksummed address of the imported account.
+    pub address: String,
+    /// Alias taken from the file name, if one could be assigned.
+    pub alias: Option<String>,
+}
+
+/// A keystore file that was left out of a directory import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedKeystore {
+    /// Path of the keystore file.
+    pub file: String,
+    /// Why the file was not imported.
+    pub reason: String,
+}
+
+/// Returns Foundry's keystore directory, `~/.foundry/keystores`.
+pub fn default_keystore_dir() -> Result<PathBuf> {
+    dirs::home_dir()
+        .map(|home| home.join(".foundry").join("keystores"))
+        .ok_or_else(|| WalletError::WalletError("Could not find home directory".to_string()))
+}
+
+/// Expands a leading `~/` to the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Lists the keystore files at `path`.
+///
+/// A file is returned as is. For a directory, every regular, non-hidden file
+/// directly inside it is returned, sorted by name.
+pub fn keystore_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        if !path.exists() {
+            return Err(WalletError::KeystoreError(format!(
+                "'{}' does not exist",
+                path.display()
+            )));
+        }
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type()?.is_file() && !hidden {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Derives a wallet alias from a keystore file name.
+///
+/// A `.json` extension is dropped and `-`/`.` become `_`. Geth's generated
+/// `UTC--<time>--<address>` names carry no useful alias and yield `None`, as
+/// do names that are still not valid aliases.
+pub fn alias_from_file_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    if name.starts_with("UTC--") {
+        return None;
+    }
+    let stem = name.strip_suffix(".json").unwrap_or(name);
+    let alias: String = stem
+        .chars()
+        .map(|c| if c == '-' || c == '.' { '_' } else { c })
+        .collect();
+    let valid = !alias.is_empty()
+        && alias.len() <= 20
+        && alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(alias)
+}
+
+/// Decrypts the private key stored in a V3 keystore file.
+pub fn read_keystore(path: &Path, password: &str) -> Result<PrivateKey> {
+    let secret = Zeroizing::new(
+        eth_keystore::decrypt_key(path, password).map_err(|e| keystore_error(path, e))?,
+    );
+    PrivateKey::from_slice(&secret)
+}
+
+/// Encrypts `key` into a V3 keystore file named `name` inside `dir`.
+///
+/// The directory is created if needed; an existing file is never overwritten.
+pub fn write_keystore(dir: &Path, name: &str, key: &PrivateKey, password: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(WalletError::KeystoreError(format!(
+            "invalid keystore file name '{}'",
+            name
+        )));
+    }
+    let path = dir.join(name);
+    if path.exists() {
+        return Err(WalletError::KeystoreError(format!(
+            "'{}' already exists",
+            path.display()
+        )));
+    }
+    fs::create_dir_all(dir)?;
+
+    let mut rng = rand::thread_rng();
+    eth_keystore::encrypt_key(dir, &mut rng, key.as_bytes(), password, Some(name))
+        .map_err(|e| keystore_error(&path, e))?;
+
+    // geth indexes keystores by their `address` field, which eth-keystore omits
+    let address = key.signer()?.address();
+    let mut json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    json["address"] = serde_json::Value::String(hex::encode(address));
+    fs::write(&path, serde_json::to_string(&json)?)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(path)
+}
+
+fn keystore_error(path: &Path, error: eth_keystore::KeystoreError) -> WalletError {
+    let reason = match error {
+        eth_keystore::KeystoreError::MacMismatch => {
+            "wrong password or corrupted keystore".to_string()
+        }
+        other => other.to_string(),
+    };
+    WalletError::KeystoreError(format!("{}: {}", path.display(), reason))
+}
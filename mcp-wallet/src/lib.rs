@@ -6,13 +6,35 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+pub mod backup;
+pub mod block;
+pub mod cache;
+pub mod chain;
+pub mod confirmation;
+pub mod decode;
 /// Defines error types and a custom `Result` type for the wallet.
 pub mod error;
 pub mod eth_client;
+pub mod evm;
+pub mod failover;
+pub mod journal;
+pub mod keystore;
+pub mod logs;
 pub mod models;
+pub mod nonce;
+pub mod profile;
+pub mod receipt;
+pub mod replacement;
+/// Zeroizing containers for private keys and passwords.
+pub mod secret;
 /// The MCP service implementation.
 pub mod service;
+pub mod shamir;
+pub mod simulation;
+pub mod subscription;
+pub mod trace;
 pub mod transaction;
+pub mod transport;
 pub mod wallet;
 
 // Re-export commonly used types and traits
@@ -22,6 +44,6 @@ pub use wallet::Wallet;
 /// The prelude module provides a convenient way to import the most common types.
 pub mod prelude {
     pub use crate::error::{Result, WalletError};
-    pub use crate::models::{Eip1559TransactionRequest, Network, SignedTransaction};
+    pub use crate::models::{Eip1559TransactionRequest, SignedTransaction};
     pub use crate::wallet::Wallet;
 }
@@ -0,0 +1,230 @@
+//! Event log queries.
+//!
+//! [`fetch_page`] scans a block range with `eth_getLogs` in chunks. Providers
+//! cap the blocks or results of one request; when a request is rejected for
+//! that, the chunk is halved and retried. A page ends when it holds `limit`
+//! logs or after [`MAX_REQUESTS_PER_PAGE`] requests, and returns a [`Cursor`]
+//! to resume from, so a scan over a long history never runs unbounded.
+
+use crate::{
+    decode::{self, DecodedLog},
+    error::{Result, WalletError},
+    eth_client::EthClient,
+};
+use ethers::{
+    abi::Abi,
+    types::{Address, Filter, Log, ValueOrArray, H256},
+};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Blocks per `eth_getLogs` request until the provider asks for less.
+pub const DEFAULT_CHUNK_SIZE: u64 = 10_000;
+
+/// Logs per page unless told otherwise.
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+
+/// The most logs one page may hold.
+pub const MAX_PAGE_LIMIT: usize = 1_000;
+
+/// Requests one page may make before it returns a cursor.
+pub const MAX_REQUESTS_PER_PAGE: usize = 32;
+
+/// What to look for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// Emitting contracts; empty matches any.
+    pub addresses: Vec<Address>,
+    /// Accepted values per topic position; `None` or empty matches any.
+    pub topics: [Option<Vec<H256>>; 4],
+    /// First block to scan.
+    pub from_block: u64,
+    /// Last block to scan, inclusive.
+    pub to_block: u64,
+}
+
+impl LogFilter {
+    fn to_filter(&self, from: u64, to: u64) -> Filter {
+        self.without_range().from_block(from).to_block(to)
+    }
+
+    /// The address and topic filter alone, as `eth_subscribe` takes it.
+    pub(crate) fn without_range(&self) -> Filter {
+        let mut filter = Filter::new();
+        if !self.addresses.is_empty() {
+            filter = filter.address(self.addresses.clone());
+        }
+        for (i, topic) in self.topics.iter().enumerate() {
+            let Some(values) = topic.as_ref().filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            let values: ValueOrArray<Option<H256>> = match values.as_slice() {
+                [single] => ValueOrArray::Value(Some(*single)),
+                _ => ValueOrArray::Array(values.iter().copied().map(Some).collect()),
+            };
+            filter.topics[i] = Some(values);
+        }
+        filter
+    }
+}
+
+/// Where a paginated scan resumes: the first log not yet returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// The block to resume in.
+    pub block: u64,
+    /// The first log index in `block` not yet returned.
+    pub log_index: u64,
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.block, self.log_index)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || WalletError::WalletError(format!("Invalid cursor: {}", s));
+        let (block, log_index) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            block: block.parse().map_err(|_| invalid())?,
+            log_index: log_index.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// A decoded log with its position in the chain.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogEntry {
+    /// The block the log was emitted in.
+    pub block_number: Option<u64>,
+    /// The transaction that emitted the log.
+    pub transaction_hash: Option<H256>,
+    /// The decoded log.
+    #[serde(flatten)]
+    pub log: DecodedLog,
+}
+
+/// One page of a log scan.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogPage {
+    /// Logs in chain order.
+    pub logs: Vec<LogEntry>,
+    /// Where to continue; `None` once the range is exhausted.
+    #[serde(serialize_with = "serialize_cursor")]
+    pub next_cursor: Option<Cursor>,
+}
+
+/// Fetches up to `limit` logs matching `filter`, starting at `cursor`.
+///
+/// Logs are decoded with `abi` if given, falling back to the well-known
+/// events for logs it does not know.
+pub async fn fetch_page(
+    client: &EthClient,
+    filter: &LogFilter,
+    abi: Option<&Abi>,
+    cursor: Option<Cursor>,
+    limit: usize,
+) -> Result<LogPage> {
+    let limit = limit.clamp(1, MAX_PAGE_LIMIT);
+    let mut logs = Vec::new();
+    let mut start = cursor.map_or(filter.from_block, |c| c.block.max(filter.from_block));
+    let mut chunk = DEFAULT_CHUNK_SIZE;
+    let mut requests = 0;
+
+    while start <= filter.to_block {
+        if requests == MAX_REQUESTS_PER_PAGE {
+            return Ok(LogPage {
+                logs,
+                next_cursor: Some(Cursor {
+                    block: start,
+                    log_index: 0,
+                }),
+            });
+        }
+        requests += 1;
+        let end = start.saturating_add(chunk - 1).min(filter.to_block);
+        let found = match client.get_logs(&filter.to_filter(start, end)).await {
+            Ok(found) => found,
+            Err(e) if end > start && is_range_limit(&e) => {
+                chunk = (end - start).div_ceil(2);
+                log::debug!(
+                    "eth_getLogs rejected blocks {}..={}, retrying with {} blocks: {}",
+                    start,
+                    end,
+                    chunk,
+                    e
+                );
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for log in found {
+            let (block, index) = position(&log);
+            if cursor.is_some_and(|c| block == c.block && index < c.log_index) {
+                continue;
+            }
+            if logs.len() == limit {
+                return Ok(LogPage {
+                    logs,
+                    next_cursor: Some(Cursor {
+                        block,
+                        log_index: index,
+                    }),
+                });
+            }
+            logs.push(entry(&log, abi));
+        }
+        start = end + 1;
+    }
+    Ok(LogPage {
+        logs,
+        next_cursor: None,
+    })
+}
+
+pub(crate) fn entry(log: &Log, abi: Option<&Abi>) -> LogEntry {
+    let decoded = abi
+        .map(|abi| decode::decode_log_with(log, abi))
+        .filter(|d| d.event.is_some())
+        .unwrap_or_else(|| decode::decode_log(log));
+    LogEntry {
+        block_number: log.block_number.map(|n| n.as_u64()),
+        transaction_hash: log.transaction_hash,
+        log: decoded,
+    }
+}
+
+fn position(log: &Log) -> (u64, u64) {
+    (
+        log.block_number.map(|n| n.as_u64()).unwrap_or_default(),
+        log.log_index.map(|i| i.low_u64()).unwrap_or_default(),
+    )
+}
+
+/// Whether the provider rejected a request for spanning too many blocks or
+/// matching too many logs. Providers phrase this differently.
+fn is_range_limit(error: &WalletError) -> bool {
+    const HINTS: &[&str] = &[
+        "range",
+        "too many",
+        "exceed",
+        "max results",
+        "response size",
+    ];
+    let message = error.to_string().to_lowercase();
+    !message.contains("rate") && HINTS.iter().any(|hint| message.contains(hint))
+}
+
+fn serialize_cursor<S: serde::Serializer>(
+    cursor: &Option<Cursor>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match cursor {
+        Some(cursor) => serializer.collect_str(cursor),
+        None => serializer.serialize_none(),
+    }
+}
@@ -2,8 +2,16 @@
 
 use anyhow::Result;
 use clap::Parser;
-use mcp_wallet::{eth_client::EthClient, service::WalletHandler, wallet::Wallet, WalletError};
+use mcp_wallet::{
+    chain::ChainRegistry,
+    eth_client::EthClient,
+    journal::{self, TxJournal},
+    profile::{ProfileRegistry, WalletProfile, DEFAULT_PROFILE},
+    service::WalletHandler,
+    wallet::Wallet,
+};
 use rmcp::ServiceExt;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing_subscriber::{fmt, EnvFilter};
@@ -12,9 +20,47 @@ use tracing_subscriber::{fmt, EnvFilter};
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// The URL of the Ethereum RPC endpoint.
+    /// The Ethereum RPC endpoint: an `http(s)://` or `ws(s)://` URL, or an IPC socket path.
     #[arg(long, default_value = "http://127.0.0.1:8545")]
     rpc_url: String,
+
+    /// A further RPC endpoint of the same network to fail over to; repeatable.
+    #[arg(long = "fallback-rpc-url")]
+    fallback_rpc_urls: Vec<String>,
+
+    /// The chain id the endpoints must serve; transactions for other chains
+    /// are refused. Defaults to the chain the node reports first.
+    #[arg(long)]
+    chain_id: Option<u64>,
+
+    /// Number of endpoints that must agree on reads such as balances and receipts.
+    #[arg(long)]
+    quorum: Option<usize>,
+
+    /// Seconds an RPC endpoint may take to answer a request.
+    #[arg(long)]
+    rpc_timeout: Option<u64>,
+
+    /// Retries of rate-limited, failed or reset RPC requests per endpoint.
+    #[arg(long)]
+    rpc_retries: Option<u32>,
+
+    /// Requests per second sent to each RPC endpoint.
+    #[arg(long)]
+    rpc_rate_limit: Option<u32>,
+
+    /// The wallet profile to start with.
+    #[arg(long, env = "WALLET_PROFILE")]
+    profile: Option<String>,
+
+    /// Path to the profiles file (defaults to `~/.mcp-wallet-profiles.json`).
+    #[arg(long)]
+    profiles_file: Option<PathBuf>,
+
+    /// Path to a file of chains added to the built-in ones (defaults to
+    /// `~/.mcp-wallet-chains.json`).
+    #[arg(long)]
+    chains_file: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -42,44 +88,50 @@ async fn main() -> Result<()> {
     // Parse command-line arguments
     let args = Args::parse();
 
-    // Determine wallet file path
-    let wallet_path = dirs::home_dir()
-        .map(|mut path| {
-            path.push(".mcp-wallet.json");
-            path
-        })
-        .ok_or_else(|| {
-            WalletError::WalletError("Could not determine home directory".to_string())
-        })?;
-
-    // Load or create wallet
-    let mut wallet = match std::fs::read_to_string(&wallet_path) {
-        Ok(contents) => {
-            log::info!("Loading wallet from {}", wallet_path.display());
-            serde_json::from_str(&contents).unwrap_or_else(|e| {
-                log::warn!("Failed to parse wallet file, creating a new one: {}", e);
-                Wallet::new()
-            })
-        }
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            log::info!("Creating new wallet at {}", wallet_path.display());
-            Wallet::new()
-        }
-        Err(e) => {
-            return Err(WalletError::FileError(e).into());
-        }
+    // Load the profiles; the implicit default profile uses ~/.mcp-wallet.json
+    // and the RPC URL given on the command line.
+    let profiles_path = match args.profiles_file {
+        Some(path) => path,
+        None => ProfileRegistry::default_path()?,
     };
+    let mut profiles = ProfileRegistry::load(&profiles_path)?;
+    profiles.insert_if_missing(
+        DEFAULT_PROFILE,
+        WalletProfile {
+            fallback_rpc_urls: args.fallback_rpc_urls,
+            chain_id: args.chain_id,
+            quorum: args.quorum,
+            timeout_secs: args.rpc_timeout,
+            max_retries: args.rpc_retries,
+            rate_limit: args.rpc_rate_limit,
+            ..WalletProfile::new(&args.rpc_url)
+        },
+    )?;
+    let chains_path = match args.chains_file {
+        Some(path) => path,
+        None => ChainRegistry::default_path()?,
+    };
+    let chains = ChainRegistry::load(&chains_path)?;
+
+    let (profile_name, profile) = profiles.select(args.profile.as_deref())?;
+    log::info!("Using wallet profile '{}'", profile_name);
 
-    wallet.set_file_path(&wallet_path);
+    // Load or create wallet and its transaction history
+    let wallet_path = profile.wallet_path(&profile_name)?;
+    let wallet = Wallet::load_or_create(&wallet_path)?;
+    let journal = TxJournal::load_or_create(journal::history_path(&wallet_path))?;
 
     // Wrap the wallet in an Arc<Mutex<>> to allow shared access
     let wallet = Arc::new(Mutex::new(wallet));
 
     // Create the Ethereum RPC client
-    let eth_client = Arc::new(EthClient::new(&args.rpc_url)?);
+    let eth_client = Arc::new(EthClient::from_profile(profile)?);
 
     // Create the wallet service handler
-    let handler = WalletHandler::new(wallet.clone(), eth_client.clone());
+    let handler = WalletHandler::new(wallet.clone(), eth_client.clone())
+        .with_profiles(profiles.clone(), profile_name)
+        .with_chains(chains)
+        .with_journal(journal);
 
     // Create the stdio transport
     let transport = (tokio::io::stdin(), tokio::io::stdout());
@@ -89,14 +141,7 @@ async fn main() -> Result<()> {
     handler.serve(transport).await?;
 
     // After the server shuts down, save the wallet if it has changed.
-    let wallet = wallet.lock().await;
-    if wallet.is_dirty() {
-        if let Some(path) = wallet.file_path() {
-            log::info!("Saving wallet to {}", path.display());
-            let contents = serde_json::to_string_pretty(&*wallet)?;
-            std::fs::write(path, contents)?;
-        }
-    }
+    wallet.lock().await.save()?;
 
     Ok(())
 }
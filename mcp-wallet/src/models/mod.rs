@@ -1,9 +1,7 @@
 //! Data models for the MCP Wallet
 
-pub mod network;
 pub mod transaction;
 
-pub use self::network::Network;
 pub use self::transaction::{Eip1559TransactionRequest, SignedTransaction};
 
 use serde::{Deserialize, Serialize};
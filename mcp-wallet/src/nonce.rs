@@ -0,0 +1,299 @@
+//! Nonce management for signing accounts.
+//!
+//! The wallet keeps the next nonce of every account locally. That counter
+//! drifts when the same key sends from another tool, and it runs ahead of the
+//! chain when signed transactions never reach the node. [`NonceManager`]
+//! reconciles the local counter with `eth_getTransactionCount` and hands out
+//! nonces to concurrent sends without holding the wallet lock across RPC calls.
+
+use crate::{
+    error::{Result, WalletError},
+    eth_client::EthClient,
+    wallet::Wallet,
+};
+use ethers::{
+    providers::{ProviderError, RpcError},
+    types::Address,
+    utils::to_checksum,
+};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Transaction counts reported by the node for an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OnchainNonces {
+    /// Transactions included in the latest block.
+    pub latest: u64,
+    /// Transactions including those waiting in the node's mempool.
+    pub pending: u64,
+}
+
+/// How the local nonce relates to the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NonceStatus {
+    /// Local, latest and pending nonces agree.
+    InSync,
+    /// Local agrees with pending, but transactions are still waiting to be mined.
+    Pending,
+    /// The account sent transactions the wallet did not sign; local was behind.
+    Drifted,
+    /// The wallet signed nonces the node has never seen.
+    Gap,
+}
+
+/// A nonce range `[from, to)` that the node has not seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NonceGap {
+    /// First missing nonce.
+    pub from: u64,
+    /// One past the last missing nonce.
+    pub to: u64,
+}
+
+/// The result of reconciling one account with the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NonceSync {
+    /// Checksummed account address.
+    pub address: String,
+    /// The chain the nonces belong to.
+    pub chain_id: u64,
+    /// Local next nonce before the sync.
+    pub local: u64,
+    /// Nonces reported by the node.
+    pub chain: OnchainNonces,
+    /// Local next nonce after the sync.
+    pub next: u64,
+    /// How local and chain state compared.
+    pub status: NonceStatus,
+    /// Nonces signed locally but unknown to the node, if any.
+    pub gap: Option<NonceGap>,
+}
+
+/// Compares a local next nonce with the chain and picks the nonce to continue from.
+///
+/// A local nonce behind the chain always moves up to `pending`. A local nonce
+/// ahead of `pending` is reported as a gap and only rewound when `repair_gaps`
+/// is set, since the missing transactions may still be broadcast later.
+pub fn reconcile(
+    address: Address,
+    chain_id: u64,
+    local: u64,
+    chain: OnchainNonces,
+    repair_gaps: bool,
+) -> NonceSync {
+    let (status, gap, next) = if local < chain.pending {
+        (NonceStatus::Drifted, None, chain.pending)
+    } else if local > chain.pending {
+        let gap = NonceGap {
+            from: chain.pending,
+            to: local,
+        };
+        let next = if repair_gaps { chain.pending } else { local };
+        (NonceStatus::Gap, Some(gap), next)
+    } else if chain.pending > chain.latest {
+        (NonceStatus::Pending, None, local)
+    } else {
+        (NonceStatus::InSync, None, local)
+    };
+
+    NonceSync {
+        address: to_checksum(&address, None),
+        chain_id,
+        local,
+        chain,
+        next,
+        status,
+        gap,
+    }
+}
+
+/// How nodes say that they already have a transaction.
+const ALREADY_KNOWN: &[&str] = &["already known", "known transaction", "already imported"];
+
+/// How nodes say that a transaction's nonce is taken by another one.
+const NONCE_TAKEN: &[&str] = &["nonce too low", "replacement transaction underpriced"];
+
+/// Whether the node rejected a send because it already has the transaction.
+pub fn is_already_known(error: &ProviderError) -> bool {
+    rpc_message(error).is_some_and(|m| ALREADY_KNOWN.iter().any(|k| m.contains(k)))
+}
+
+/// Whether a failed send proves that the node never took the transaction, so
+/// that its nonce can be released.
+///
+/// Only a JSON-RPC error is proof: a timeout or a dropped connection may come
+/// after the node accepted the transaction. An error saying that the node
+/// already has it, or that the nonce is taken, is no proof either.
+pub fn is_rejected_send(error: &WalletError) -> bool {
+    let WalletError::EthersProviderError(e) = error else {
+        return false;
+    };
+    rpc_message(e).is_some_and(|m| {
+        !ALREADY_KNOWN
+            .iter()
+            .chain(NONCE_TAKEN)
+            .any(|k| m.contains(k))
+    })
+}
+
+fn rpc_message(error: &ProviderError) -> Option<String> {
+    RpcError::as_error_response(error).map(|e| e.message.to_lowercase())
+}
+
+/// Nonces given back after a failed send, per account and chain.
+type ReleasedNonces = BTreeSet<u64>;
+
+/// Coordinates the local nonces of a wallet with the chain.
+///
+/// Reservations are taken under the wallet lock, so concurrent sends always get
+/// distinct nonces. A send that fails after reserving must [`release`] its
+/// nonce; released nonces are handed out again before new ones.
+///
+/// [`release`]: NonceManager::release
+#[derive(Debug, Clone, Default)]
+pub struct NonceManager {
+    released: Arc<Mutex<HashMap<(Address, u64), ReleasedNonces>>>,
+}
+
+impl NonceManager {
+    /// Creates a nonce manager with no released nonces.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets released nonces, e.g. after the wallet was swapped out.
+    pub async fn reset(&self) {
+        self.released.lock().await.clear();
+    }
+
+    /// Reconciles one account on `chain_id` with the chain and stores the
+    /// resulting nonce.
+    ///
+    /// An account without recorded state on `chain_id` simply adopts the
+    /// chain's pending nonce.
+    pub async fn sync(
+        &self,
+        wallet: &Mutex<Wallet>,
+        client: &EthClient,
+        chain_id: u64,
+        address: Address,
+        repair_gaps: bool,
+    ) -> Result<NonceSync> {
+        let chain = client.get_nonces(address).await?;
+
+        let mut wallet = wallet.lock().await;
+        let (account, _) = wallet
+            .get_account(&format!("0x{:x}", address))
+            .ok_or(WalletError::AccountNotFound(address))?;
+        let recorded = account.chain(chain_id).is_some();
+        let local = if recorded {
+            account.nonce(chain_id)
+        } else {
+            chain.pending
+        };
+        let sync = reconcile(address, chain_id, local, chain, repair_gaps);
+        if sync.next != local || !recorded {
+            wallet.set_nonce(&format!("0x{:x}", address), chain_id, sync.next)?;
+        }
+
+        let mut released = self.released.lock().await;
+        if let Some(set) = released.get_mut(&(address, chain_id)) {
+            // Anything below pending is taken on chain, anything at or above the
+            // new next nonce will be handed out again anyway
+            set.retain(|n| *n >= chain.pending && *n < sync.next);
+        }
+        Ok(sync)
+    }
+
+    /// Reconciles every signing account in the wallet with the chain the
+    /// client is connected to.
+    pub async fn sync_all(
+        &self,
+        wallet: &Mutex<Wallet>,
+        client: &EthClient,
+        repair_gaps: bool,
+    ) -> Result<Vec<NonceSync>> {
+        let chain_id = client.get_chain_id().await?;
+        let addresses: Vec<Address> = wallet
+            .lock()
+            .await
+            .list_accounts()
+            .into_iter()
+            .filter(|(_, account)| account.private_key.is_some())
+            .map(|(address, _)| address)
+            .collect();
+
+        let mut results = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            results.push(
+                self.sync(wallet, client, chain_id, address, repair_gaps)
+                    .await?,
+            );
+        }
+        Ok(results)
+    }
+
+    /// Reserves the next nonce for `address` on `chain_id`.
+    ///
+    /// The caller must hold the wallet lock; the wallet's next nonce is advanced
+    /// unless a previously released nonce is reused.
+    pub async fn reserve(
+        &self,
+        wallet: &mut Wallet,
+        chain_id: u64,
+        address: Address,
+    ) -> Result<u64> {
+        let local = Self::local_nonce(wallet, chain_id, address)?;
+        let mut released = self.released.lock().await;
+        if let Some(nonce) = released
+            .get_mut(&(address, chain_id))
+            .and_then(|set| set.pop_first())
+        {
+            return Ok(nonce);
+        }
+        drop(released);
+
+        wallet.set_nonce(&format!("0x{:x}", address), chain_id, local + 1)?;
+        Ok(local)
+    }
+
+    /// Returns a reserved nonce that was not used.
+    ///
+    /// If it is the most recent reservation the wallet's next nonce moves back;
+    /// otherwise it is kept for the next [`reserve`](NonceManager::reserve).
+    pub async fn release(
+        &self,
+        wallet: &mut Wallet,
+        chain_id: u64,
+        address: Address,
+        nonce: u64,
+    ) -> Result<()> {
+        let local = Self::local_nonce(wallet, chain_id, address)?;
+        if nonce >= local {
+            return Ok(());
+        }
+        let mut released = self.released.lock().await;
+        let set = released.entry((address, chain_id)).or_default();
+        set.insert(nonce);
+
+        // Move the next nonce back over released nonces at the top
+        let mut next = local;
+        while next > 0 && set.remove(&(next - 1)) {
+            next -= 1;
+        }
+        if next != local {
+            wallet.set_nonce(&format!("0x{:x}", address), chain_id, next)?;
+        }
+        Ok(())
+    }
+
+    fn local_nonce(wallet: &Wallet, chain_id: u64, address: Address) -> Result<u64> {
+        wallet
+            .get_account(&format!("0x{:x}", address))
+            .map(|(account, _)| account.nonce(chain_id))
+            .ok_or(WalletError::AccountNotFound(address))
+    }
+}
@@ -0,0 +1,179 @@
+//! Named wallet profiles.
+//!
+//! A profile bundles a wallet file with the network it is normally used on, so
+//! one installation can keep e.g. `dev`, `sepolia-testing` and `demo` wallets
+//! side by side and switch between them at runtime.
+
+use crate::error::{Result, WalletError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the implicit profile backed by `~/.mcp-wallet.json`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A single named wallet profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletProfile {
+    /// Path to the wallet file. Defaults to `~/.mcp-wallet-<name>.json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_file: Option<PathBuf>,
+    /// The default RPC endpoint for this profile.
+    pub rpc_url: String,
+    /// Further endpoints of the same network, used when `rpc_url` fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_rpc_urls: Vec<String>,
+    /// Number of endpoints that must agree on a read, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<usize>,
+    /// Seconds an endpoint may take to answer a request (default 10).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Retries of rate-limited, failed or reset requests per endpoint (default 3).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Requests per second sent to each endpoint; unlimited if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u32>,
+    /// Optional chain ID expected on `rpc_url`; transactions for other chains
+    /// are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+}
+
+impl WalletProfile {
+    /// Creates a profile for the given RPC endpoint with a default wallet file.
+    pub fn new(rpc_url: impl Into<String>) -> Self {
+        Self {
+            wallet_file: None,
+            rpc_url: rpc_url.into(),
+            fallback_rpc_urls: Vec::new(),
+            quorum: None,
+            timeout_secs: None,
+            max_retries: None,
+            rate_limit: None,
+            chain_id: None,
+        }
+    }
+
+    /// All RPC endpoints of the profile, `rpc_url` first.
+    pub fn rpc_urls(&self) -> Vec<&str> {
+        std::iter::once(self.rpc_url.as_str())
+            .chain(self.fallback_rpc_urls.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Resolves the wallet file path for a profile called `name`.
+    pub fn wallet_path(&self, name: &str) -> Result<PathBuf> {
+        match &self.wallet_file {
+            Some(path) => Ok(path.clone()),
+            None => default_wallet_path(name),
+        }
+    }
+}
+
+/// A set of named profiles, usually loaded from `~/.mcp-wallet-profiles.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileRegistry {
+    /// Profile selected when none is requested explicitly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Profiles by name.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, WalletProfile>,
+}
+
+impl ProfileRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a registry from a JSON file. A missing file yields an empty registry.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let registry: Self = match std::fs::read_to_string(path.as_ref()) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(WalletError::FileError(e)),
+        };
+        for name in registry.profiles.keys() {
+            if !is_valid_profile_name(name) {
+                return Err(WalletError::InvalidProfileName(name.clone()));
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Adds or replaces a profile.
+    pub fn insert(&mut self, name: &str, profile: WalletProfile) -> Result<()> {
+        if !is_valid_profile_name(name) {
+            return Err(WalletError::InvalidProfileName(name.to_string()));
+        }
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    /// Adds `profile` under `name` unless a profile with that name already exists.
+    pub fn insert_if_missing(&mut self, name: &str, profile: WalletProfile) -> Result<()> {
+        if self.profiles.contains_key(name) {
+            return Ok(());
+        }
+        self.insert(name, profile)
+    }
+
+    /// Gets a profile by name.
+    pub fn get(&self, name: &str) -> Result<&WalletProfile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| WalletError::ProfileNotFound(name.to_string()))
+    }
+
+    /// Iterates over all profiles in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &WalletProfile)> {
+        self.profiles.iter().map(|(name, p)| (name.as_str(), p))
+    }
+
+    /// Picks the profile to start with: the requested one, else the registry
+    /// default, else [`DEFAULT_PROFILE`].
+    pub fn select(&self, requested: Option<&str>) -> Result<(String, &WalletProfile)> {
+        let name = requested
+            .or(self.default.as_deref())
+            .unwrap_or(DEFAULT_PROFILE);
+        self.get(name).map(|p| (name.to_string(), p))
+    }
+
+    /// Returns the default path of the profiles file (`~/.mcp-wallet-profiles.json`).
+    pub fn default_path() -> Result<PathBuf> {
+        home_file(".mcp-wallet-profiles.json")
+    }
+}
+
+/// Returns the default wallet file for a profile.
+///
+/// The [`DEFAULT_PROFILE`] keeps using `~/.mcp-wallet.json`; any other profile
+/// gets `~/.mcp-wallet-<name>.json`.
+pub fn default_wallet_path(name: &str) -> Result<PathBuf> {
+    if name == DEFAULT_PROFILE {
+        home_file(".mcp-wallet.json")
+    } else {
+        home_file(&format!(".mcp-wallet-{}.json", name))
+    }
+}
+
+pub(crate) fn home_file(file_name: &str) -> Result<PathBuf> {
+    dirs::home_dir()
+        .map(|mut path| {
+            path.push(file_name);
+            path
+        })
+        .ok_or_else(|| WalletError::WalletError("Could not determine home directory".to_string()))
+}
+
+/// Checks if a profile name is valid (1-32 alphanumeric, `-` or `_` characters).
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
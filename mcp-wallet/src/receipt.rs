@@ -0,0 +1,161 @@
+//! Decoded transaction receipts.
+//!
+//! A [`ReceiptReport`] adds what a receipt means to what it says: the fee
+//! paid, logs decoded with the well-known event signatures, and for reverted
+//! transactions the revert reason. Receipts do not carry revert data, so the
+//! reason is recovered by replaying the call on the state of the parent block.
+//! Transactions earlier in the same block are not replayed, so the reason is
+//! best effort.
+
+use crate::{
+    decode::{self, DecodedLog, RevertReason},
+    error::Result,
+    eth_client::EthClient,
+};
+use ethers::{
+    types::{
+        transaction::eip2718::TypedTransaction, BlockId, Transaction, TransactionReceipt,
+        TransactionRequest, H256, U64,
+    },
+    utils::{format_ether, to_checksum},
+};
+use serde::Serialize;
+
+/// The execution status of a mined transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    /// Executed successfully.
+    Success,
+    /// Reverted.
+    Failed,
+    /// The receipt has no status field (blocks before Byzantium).
+    Unknown,
+}
+
+impl From<Option<U64>> for ReceiptStatus {
+    fn from(status: Option<U64>) -> Self {
+        match status.map(|s| s.as_u64()) {
+            Some(1) => Self::Success,
+            Some(_) => Self::Failed,
+            None => Self::Unknown,
+        }
+    }
+}
+
+/// A receipt with its fee, decoded logs and revert reason.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReceiptReport {
+    /// The transaction hash.
+    pub transaction_hash: H256,
+    /// Whether the transaction succeeded.
+    pub status: ReceiptStatus,
+    /// The block the transaction was mined in.
+    pub block_number: Option<u64>,
+    /// The hash of that block.
+    pub block_hash: Option<H256>,
+    /// The sender, checksummed.
+    pub from: String,
+    /// The recipient, checksummed; `None` for contract creation.
+    pub to: Option<String>,
+    /// The address of the contract created by the transaction.
+    pub contract_address: Option<String>,
+    /// Gas used by the transaction.
+    pub gas_used: Option<String>,
+    /// The price per gas actually paid, in wei.
+    pub effective_gas_price: Option<String>,
+    /// `gas_used * effective_gas_price`, in wei.
+    pub fee_wei: Option<String>,
+    /// The fee in ETH.
+    pub fee_eth: Option<String>,
+    /// Emitted logs, decoded where the event is known.
+    pub logs: Vec<DecodedLog>,
+    /// Why a failed transaction reverted, if it could be recovered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<RevertReason>,
+}
+
+impl ReceiptReport {
+    /// Builds the report from a receipt, without the revert reason.
+    ///
+    /// `tx` supplies the gas price for receipts without `effectiveGasPrice`.
+    pub fn new(receipt: &TransactionReceipt, tx: Option<&Transaction>) -> Self {
+        let gas_price = receipt
+            .effective_gas_price
+            .or_else(|| tx.and_then(|tx| tx.gas_price));
+        let fee = receipt
+            .gas_used
+            .zip(gas_price)
+            .map(|(gas, price)| gas.saturating_mul(price));
+        Self {
+            transaction_hash: receipt.transaction_hash,
+            status: receipt.status.into(),
+            block_number: receipt.block_number.map(|b| b.as_u64()),
+            block_hash: receipt.block_hash,
+            from: to_checksum(&receipt.from, None),
+            to: receipt.to.map(|to| to_checksum(&to, None)),
+            contract_address: receipt.contract_address.map(|a| to_checksum(&a, None)),
+            gas_used: receipt.gas_used.map(|g| g.to_string()),
+            effective_gas_price: gas_price.map(|p| p.to_string()),
+            fee_wei: fee.map(|f| f.to_string()),
+            fee_eth: fee.map(format_ether),
+            logs: receipt.logs.iter().map(decode::decode_log).collect(),
+            revert_reason: None,
+        }
+    }
+}
+
+/// Fetches and decodes the receipt of `hash`; `None` while it is pending.
+pub async fn describe(client: &EthClient, hash: H256) -> Result<Option<ReceiptReport>> {
+    let Some(receipt) = client.get_transaction_receipt(hash).await? else {
+        return Ok(None);
+    };
+    let needs_tx = receipt.effective_gas_price.is_none() || receipt.status == Some(U64::zero());
+    let tx = if needs_tx {
+        client.get_transaction_info(hash).await?
+    } else {
+        None
+    };
+
+    let mut report = ReceiptReport::new(&receipt, tx.as_ref());
+    if report.status == ReceiptStatus::Failed {
+        if let (Some(tx), Some(block)) = (&tx, report.block_number) {
+            report.revert_reason = replay_revert(client, tx, block).await;
+        }
+    }
+    Ok(Some(report))
+}
+
+/// Replays a mined transaction as a call on its parent block and decodes the revert.
+///
+/// Returns `None` if the replay does not revert (the state it depended on came
+/// from earlier in the block) or the node cannot serve the historical state.
+pub async fn replay_revert(
+    client: &EthClient,
+    tx: &Transaction,
+    block_number: u64,
+) -> Option<RevertReason> {
+    let mut request = TransactionRequest::new()
+        .from(tx.from)
+        .value(tx.value)
+        .gas(tx.gas)
+        .data(tx.input.clone());
+    if let Some(to) = tx.to {
+        request = request.to(to);
+    }
+    let call = TypedTransaction::Legacy(request);
+    let block = BlockId::Number(block_number.saturating_sub(1).into());
+    match client.call(&call, Some(block)).await {
+        Ok(_) => {
+            log::debug!("Replay of 0x{:x} did not revert", tx.hash);
+            None
+        }
+        Err(e) => match decode::revert_data(&e) {
+            Some(data) => Some(decode::decode_revert(&data)),
+            None => {
+                log::debug!("Replay of 0x{:x} failed: {}", tx.hash, e);
+                None
+            }
+        },
+    }
+}
@@ -0,0 +1,215 @@
+//! Replacing stuck transactions.
+//!
+//! A pending transaction is replaced by signing another one with the same
+//! nonce and higher fees. Nodes only accept the replacement if both the max fee
+//! and the priority fee rise by at least their price bump (10% in geth), so
+//! fees are bumped by at least [`MIN_FEE_BUMP_PERCENT`] and never below what
+//! the network currently asks for. Whichever transaction of a nonce gets mined
+//! wins; [`ReplacementTracker`] remembers the candidates to tell which one did.
+
+use crate::{
+    error::{Result, WalletError},
+    eth_client::EthClient,
+    models::Eip1559TransactionRequest,
+};
+use ethers::types::{Address, Transaction, H256, U256};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The smallest fee increase nodes accept for a replacement, in percent.
+pub const MIN_FEE_BUMP_PERCENT: u64 = 10;
+
+/// The largest fee increase accepted, in percent; more is most likely a typo.
+pub const MAX_FEE_BUMP_PERCENT: u64 = 1000;
+
+/// Gas limit of the zero-value self-transfer used to cancel a transaction.
+const CANCEL_GAS: u64 = 21_000;
+
+/// What a replacement transaction does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementKind {
+    /// Resend the same call with higher fees.
+    SpeedUp,
+    /// Send zero value to self with higher fees, so the original never executes.
+    Cancel,
+}
+
+/// Raises `fee` by `percent`, rounding up, by at least one wei. Saturates at
+/// `U256::MAX` instead of overflowing.
+pub fn bump_fee(fee: U256, percent: u64) -> U256 {
+    let bumped = fee
+        .checked_mul(U256::from(100u64.saturating_add(percent)))
+        .map_or(U256::MAX, |raised| {
+            raised.saturating_add(U256::from(99)) / U256::from(100)
+        });
+    bumped.max(fee.saturating_add(U256::one()))
+}
+
+/// Builds the replacement for a pending `original` transaction.
+///
+/// Both fees are raised by `bump_percent` (at least [`MIN_FEE_BUMP_PERCENT`])
+/// and lifted to `network_fees` (`(max_fee, priority_fee)`) when the network
+/// currently asks for more.
+pub fn replacement_request(
+    original: &Transaction,
+    chain_id: u64,
+    kind: ReplacementKind,
+    bump_percent: u64,
+    network_fees: Option<(U256, U256)>,
+) -> Result<Eip1559TransactionRequest> {
+    if original.block_number.is_some() {
+        return Err(WalletError::WalletError(format!(
+            "transaction 0x{:x} is already mined",
+            original.hash
+        )));
+    }
+
+    // Legacy transactions pay `gas_price` as both fee cap and tip
+    let old_max_fee = original
+        .max_fee_per_gas
+        .or(original.gas_price)
+        .unwrap_or_default();
+    let old_priority_fee = original
+        .max_priority_fee_per_gas
+        .or(original.gas_price)
+        .unwrap_or_default();
+
+    let percent = bump_percent.max(MIN_FEE_BUMP_PERCENT);
+    let (network_max_fee, network_priority_fee) = network_fees.unwrap_or_default();
+    let priority_fee = bump_fee(old_priority_fee, percent).max(network_priority_fee);
+    let max_fee = bump_fee(old_max_fee, percent)
+        .max(network_max_fee)
+        .max(priority_fee);
+
+    let request = match kind {
+        ReplacementKind::SpeedUp => Eip1559TransactionRequest {
+            to: original.to,
+            value: original.value,
+            data: (!original.input.is_empty()).then(|| original.input.to_vec()),
+            gas: original.gas,
+            ..Default::default()
+        },
+        ReplacementKind::Cancel => Eip1559TransactionRequest {
+            to: Some(original.from),
+            value: U256::zero(),
+            data: None,
+            gas: U256::from(CANCEL_GAS),
+            ..Default::default()
+        },
+    };
+    Ok(Eip1559TransactionRequest {
+        chain_id,
+        nonce: original.nonce,
+        max_fee_per_gas: max_fee,
+        max_priority_fee_per_gas: priority_fee,
+        ..request
+    })
+}
+
+/// Transactions competing for the same sender nonce.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplacementGroup {
+    /// The sending account.
+    pub from: Address,
+    /// The chain the transactions were sent on.
+    pub chain_id: u64,
+    /// The shared nonce.
+    pub nonce: u64,
+    /// The original transaction followed by its replacements, oldest first.
+    pub candidates: Vec<H256>,
+}
+
+/// The state of a replacement group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReplacementStatus {
+    /// No transaction of the group is mined yet.
+    Pending,
+    /// One of the candidates was mined.
+    Mined {
+        /// The hash that was mined.
+        winner: H256,
+        /// The block it was included in.
+        block_number: Option<u64>,
+    },
+    /// The nonce was used by a transaction outside the group.
+    ReplacedExternally,
+}
+
+/// Sender, chain id and nonce identifying a replacement group.
+type GroupKey = (Address, u64, u64);
+
+/// Remembers replacement groups so the winning hash can be found later.
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementTracker {
+    groups: Arc<Mutex<HashMap<GroupKey, ReplacementGroup>>>,
+}
+
+impl ReplacementTracker {
+    /// Creates an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `replacement` competes with `original` for the same nonce.
+    pub async fn record(
+        &self,
+        from: Address,
+        chain_id: u64,
+        nonce: u64,
+        original: H256,
+        replacement: H256,
+    ) -> ReplacementGroup {
+        let mut groups = self.groups.lock().await;
+        let group = groups
+            .entry((from, chain_id, nonce))
+            .or_insert_with(|| ReplacementGroup {
+                from,
+                chain_id,
+                nonce,
+                candidates: vec![original],
+            });
+        for hash in [original, replacement] {
+            if !group.candidates.contains(&hash) {
+                group.candidates.push(hash);
+            }
+        }
+        group.clone()
+    }
+
+    /// Forgets all groups, e.g. after switching to another wallet or network.
+    pub async fn reset(&self) {
+        self.groups.lock().await.clear();
+    }
+
+    /// Finds the group a transaction hash belongs to.
+    pub async fn find(&self, hash: H256) -> Option<ReplacementGroup> {
+        self.groups
+            .lock()
+            .await
+            .values()
+            .find(|group| group.candidates.contains(&hash))
+            .cloned()
+    }
+
+    /// Checks which candidate of `group` was mined, if any.
+    pub async fn status(client: &EthClient, group: &ReplacementGroup) -> Result<ReplacementStatus> {
+        // Read the nonce first, so a candidate mined in between still shows up below
+        let nonces = client.get_nonces(group.from).await?;
+        for hash in group.candidates.iter().rev() {
+            if let Some(receipt) = client.get_transaction_receipt(*hash).await? {
+                return Ok(ReplacementStatus::Mined {
+                    winner: *hash,
+                    block_number: receipt.block_number.map(|b| b.as_u64()),
+                });
+            }
+        }
+        if nonces.latest > group.nonce {
+            return Ok(ReplacementStatus::ReplacedExternally);
+        }
+        Ok(ReplacementStatus::Pending)
+    }
+}
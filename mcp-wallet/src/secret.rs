@@ -0,0 +1,116 @@
+//! Secret types for private key material.
+//!
+//! Key bytes and secret strings are wiped from memory when dropped and are
+//! redacted from `Debug` output, so they do not linger in freed buffers or
+//! end up in logs.
+
+use crate::error::{Result, WalletError};
+use ethers::signers::LocalWallet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A 32-byte secp256k1 private key that is zeroized on drop.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey(Zeroizing<[u8; 32]>);
+
+impl PrivateKey {
+    /// Creates a key from raw bytes.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        if bytes.len() != key.len() {
+            return Err(WalletError::InvalidPrivateKey(
+                "expected 32 bytes".to_string(),
+            ));
+        }
+        key.copy_from_slice(bytes);
+        let key = Self(key);
+        key.signer()?;
+        Ok(key)
+    }
+
+    /// Parses a key from hex.
+    ///
+    /// Surrounding whitespace and an optional `0x` prefix are accepted. The key
+    /// must be exactly 64 hex characters, not all zeros, and a valid scalar.
+    pub fn from_hex(input: &str) -> Result<Self> {
+        let trimmed = input.trim();
+        let hex_str = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+        if hex_str.len() != 64 || !hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(WalletError::InvalidPrivateKey(
+                "expected 32-byte hex".to_string(),
+            ));
+        }
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        hex::decode_to_slice(hex_str, bytes.as_mut_slice())?;
+        if bytes.iter().all(|b| *b == 0) {
+            return Err(WalletError::InvalidPrivateKey("key is zero".to_string()));
+        }
+        Self::from_slice(bytes.as_slice())
+    }
+
+    /// Copies the key out of a signer.
+    pub fn from_signer(wallet: &LocalWallet) -> Self {
+        let mut bytes = wallet.signer().to_bytes();
+        let mut key = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&bytes);
+        bytes.zeroize();
+        Self(key)
+    }
+
+    /// Builds a signer for this key.
+    pub fn signer(&self) -> Result<LocalWallet> {
+        LocalWallet::from_bytes(self.0.as_slice())
+            .map_err(|e| WalletError::InvalidPrivateKey(e.to_string()))
+    }
+
+    /// Exposes the raw key bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PrivateKey(<redacted>)")
+    }
+}
+
+impl Serialize for PrivateKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let encoded = Zeroizing::new(hex::encode(self.0.as_slice()));
+        serializer.serialize_str(&encoded)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrivateKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let encoded = Zeroizing::<String>::deserialize(deserializer)?;
+        Self::from_hex(&encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A string holding secret input, such as a private key or a password, that is
+/// zeroized on drop.
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    /// Exposes the secret value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
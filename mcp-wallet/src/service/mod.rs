@@ -1,41 +1,52 @@
 //! The MCP service implementation for the wallet.
 
-use crate::{eth_client::EthClient, wallet::Wallet, WalletError};
-use ethers::types::{Address, H256, U256};
-use ethers::utils::to_checksum;
+use crate::{
+    backup::{BackupArchive, RestoreMode},
+    block,
+    chain::{ChainInfo, ChainRegistry},
+    confirmation::{self, TxWatcher, WaitOutcome, WatchState},
+    decode,
+    eth_client::EthClient,
+    journal::{self, TxJournal, TxQuery, TxStatus},
+    keystore,
+    logs::{self, LogFilter},
+    nonce::{is_rejected_send, NonceManager},
+    profile::{ProfileRegistry, WalletProfile, DEFAULT_PROFILE},
+    receipt,
+    replacement::{
+        self, ReplacementKind, ReplacementStatus, ReplacementTracker, MAX_FEE_BUMP_PERCENT,
+        MIN_FEE_BUMP_PERCENT,
+    },
+    secret::{PrivateKey, SecretString},
+    shamir::KeyShare,
+    simulation,
+    subscription::{Subscription, SubscriptionEvent, SubscriptionKind, Subscriptions},
+    trace::{self, Tracer},
+    wallet::Wallet,
+    WalletError,
+};
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, H256, U256,
+};
+use ethers::utils::{format_ether, to_checksum};
 use rmcp::{
     handler::server::{tool::ToolRouter, wrapper::Parameters},
-    model::{CallToolResult, ErrorData},
+    model::{
+        CallToolResult, ErrorData, LoggingLevel, LoggingMessageNotificationParam,
+        ProgressNotificationParam,
+    },
     model::{ServerCapabilities, ServerInfo},
-    tool, tool_handler, tool_router, ServerHandler,
+    service::RequestContext,
+    tool, tool_handler, tool_router, RoleServer, ServerHandler,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::sync::Mutex;
-
-/// Normalizes a private key string.
-///
-/// - Trims whitespace
-/// - Strips optional `0x` prefix
-/// - Validates it is exactly 64 hex chars and not all zeros
-/// - Returns lowercase hex string on success
-fn normalize_private_key_hex(input: &str) -> Option<String> {
-    let pk = input.trim();
-    let normalized = pk.strip_prefix("0x").unwrap_or(pk);
-    if normalized.len() != 64 {
-        return None;
-    }
-    if !normalized.chars().all(|c| c.is_ascii_hexdigit()) {
-        return None;
-    }
-    if !normalized.chars().any(|c| c != '0') {
-        return None;
-    }
-    Some(normalized.to_ascii_lowercase())
-}
+use tokio::sync::{Mutex, RwLock};
 
 // --- Tool Parameter Structs ---
 
@@ -59,7 +70,8 @@ struct SetAliasParams {
 #[derive(Deserialize, Debug, schemars::JsonSchema)]
 struct ImportPrivateKeyParams {
     /// The private key in hex format (0x-prefixed or raw 64 hex chars).
-    private_key: String,
+    #[schemars(with = "String")]
+    private_key: SecretString,
 }
 
 /// Parameters for the `create_tx` tool.
@@ -89,13 +101,214 @@ struct SignTxParams {
     from: String,
     /// The transaction to sign.
     tx_json: Value,
+    /// Simulate the transaction on the pending block first and refuse to
+    /// sign it if it would revert; defaults to true.
+    simulate: Option<bool>,
+    /// Sign even if the transaction is for another chain than the wallet's
+    /// network, or has no chain id; defaults to false.
+    allow_chain_mismatch: Option<bool>,
+}
+
+/// Parameters for the `simulate_tx` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct SimulateTxParams {
+    /// The sender: an address or alias, which need not be able to sign.
+    from: String,
+    /// The unsigned transaction, e.g. as created by `create_tx`.
+    tx_json: Value,
+    /// Block to run on top of; defaults to the latest block.
+    block: Option<BlockParam>,
+    /// Let the node run the transaction with `debug_traceCall` if it serves
+    /// it, which is faster than the embedded EVM; defaults to true.
+    node_trace: Option<bool>,
+}
+
+/// Parameters for the `trace_transaction` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct TraceTransactionParams {
+    /// The transaction hash as a hex-encoded string.
+    transaction_hash: String,
+    /// `call` (default) for the decoded call tree, `prestate` for the touched state.
+    tracer: Option<Tracer>,
+    /// With the prestate tracer, report the state before and after instead.
+    diff_mode: Option<bool>,
+}
+
+/// Parameters for the `trace_call` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct TraceCallParams {
+    /// The sender: an address or alias, which need not be able to sign.
+    from: String,
+    /// The unsigned transaction, e.g. as created by `create_tx`.
+    tx_json: Value,
+    /// Block to run on; defaults to the latest block.
+    block: Option<BlockParam>,
+    /// `call` (default) for the decoded call tree, `prestate` for the touched state.
+    tracer: Option<Tracer>,
+    /// With the prestate tracer, report the state before and after instead.
+    diff_mode: Option<bool>,
+}
+
+/// A block given by number, hash or tag.
+#[derive(Deserialize, Serialize, Debug, Clone, schemars::JsonSchema)]
+#[serde(untagged)]
+enum BlockParam {
+    /// Block number, e.g. 19000000
+    Number(u64),
+    /// Block number (decimal or 0x hex), block hash, or one of `latest`,
+    /// `earliest`, `safe`, `finalized` and `pending`
+    Id(String),
+}
+
+impl BlockParam {
+    fn id(&self) -> Result<BlockId, ErrorData> {
+        match self {
+            Self::Number(number) => Ok(BlockNumber::Number((*number).into()).into()),
+            Self::Id(input) => block::parse_block_id(input).map_err(to_invalid_params_error),
+        }
+    }
+}
+
+/// Parses an optional `block` parameter.
+fn block_id(block: &Option<BlockParam>) -> Result<Option<BlockId>, ErrorData> {
+    block.as_ref().map(BlockParam::id).transpose()
+}
+
+/// Parameters for the `eth_get_block` tool.
+#[derive(Deserialize, Debug, Default, schemars::JsonSchema)]
+struct GetBlockParams {
+    /// Block number (decimal or 0x hex), block hash, or one of `latest` (default),
+    /// `earliest`, `safe`, `finalized` and `pending`.
+    block: Option<BlockParam>,
+    /// Return full transactions instead of their hashes.
+    full_transactions: Option<bool>,
+}
+
+/// Parameters for the `eth_find_block_by_timestamp` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct FindBlockByTimestampParams {
+    /// Unix time in seconds.
+    timestamp: u64,
+}
+
+/// One value or a list of alternatives.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany {
+    /// A single value.
+    One(String),
+    /// Any of these values.
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn values(&self) -> &[String] {
+        match self {
+            Self::One(value) => std::slice::from_ref(value),
+            Self::Many(values) => values,
+        }
+    }
+}
+
+/// Parameters for the `eth_get_logs` tool.
+#[derive(Deserialize, Debug, Default, schemars::JsonSchema)]
+struct GetLogsParams {
+    /// Contract address(es) or alias(es) emitting the logs; any if omitted.
+    address: Option<OneOrMany>,
+    /// Event signature to match and decode with, e.g.
+    /// `Transfer(address indexed from, address indexed to, uint256 value)`.
+    /// Sets the first topic unless `topics` does.
+    event: Option<String>,
+    /// Contract ABI to decode with: a JSON ABI array or a list of
+    /// human-readable signatures.
+    abi: Option<Value>,
+    /// Topic filters by position: null for any, one value, or a list of
+    /// alternatives. Values are 32-byte hex, or an address or alias, which is
+    /// padded to 32 bytes.
+    topics: Option<Vec<Option<OneOrMany>>>,
+    /// First block (number, hash or tag); defaults to 10000 blocks before `to_block`.
+    from_block: Option<String>,
+    /// Last block (number, hash or tag); defaults to `latest`.
+    to_block: Option<String>,
+    /// Logs per page, at most 1000; defaults to 100.
+    limit: Option<usize>,
+    /// The `next_cursor` of the previous page.
+    cursor: Option<String>,
+}
+
+/// Parameters for the `subscribe` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct SubscribeParams {
+    /// What to listen for: `new_heads`, `logs` or `pending_transactions`.
+    kind: SubscriptionKind,
+    /// For `logs`: contract address(es) or alias(es); any if omitted.
+    address: Option<OneOrMany>,
+    /// For `logs`: event signature to match and decode with.
+    event: Option<String>,
+    /// For `logs`: contract ABI to decode with.
+    abi: Option<Value>,
+    /// For `logs`: topic filters by position, as for `eth_get_logs`.
+    topics: Option<Vec<Option<OneOrMany>>>,
+}
+
+/// Parameters for the `unsubscribe` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct UnsubscribeParams {
+    /// The id returned by `subscribe`.
+    id: u64,
 }
 
 /// Parameters for the `eth_getBalance` tool.
 #[derive(Deserialize, Debug, schemars::JsonSchema)]
 struct GetBalanceParams {
-    /// The Ethereum address to query.
+    /// The address or alias to query.
+    address: String,
+    /// Block to read the balance at; defaults to the latest block.
+    block: Option<BlockParam>,
+}
+
+/// Parameters for the `eth_get_balance_at_time` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct BalanceAtTimeParams {
+    /// The address or alias to query.
+    address: String,
+    /// Unix time in seconds.
+    timestamp: u64,
+}
+
+/// Parameters for the `eth_call` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct EthCallParams {
+    /// The contract to call (address or alias).
+    to: String,
+    /// Hex-encoded calldata, e.g. a function selector and its arguments.
+    data: Option<String>,
+    /// The caller (address or alias); defaults to the zero address.
+    from: Option<String>,
+    /// Value sent with the call, in wei.
+    value: Option<String>,
+    /// Block to call at; defaults to the latest block.
+    block: Option<BlockParam>,
+}
+
+/// Parameters for the `eth_get_code` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct GetCodeParams {
+    /// The address or alias to query.
+    address: String,
+    /// Block to read the code at; defaults to the latest block.
+    block: Option<BlockParam>,
+}
+
+/// Parameters for the `eth_get_storage_at` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct GetStorageAtParams {
+    /// The contract address or alias.
     address: String,
+    /// The storage slot: a number (decimal or 0x hex) or a 32-byte hex key.
+    slot: String,
+    /// Block to read the slot at; defaults to the latest block.
+    block: Option<BlockParam>,
 }
 
 /// Parameters for the `eth_sendSignedTransaction` tool.
@@ -103,6 +316,12 @@ struct GetBalanceParams {
 struct SendSignedTxParams {
     /// The raw, signed transaction as a hex-encoded string.
     signed_transaction_hex: String,
+    /// Simulate the transaction on the pending block first and refuse to
+    /// send it if it would revert; defaults to true.
+    simulate: Option<bool>,
+    /// Send even if the transaction is for another chain than the node's,
+    /// or has no chain id; defaults to false.
+    allow_chain_mismatch: Option<bool>,
 }
 
 /// Parameters for the `eth_getTransactionInfo` tool.
@@ -112,6 +331,46 @@ struct GetTxInfoParams {
     transaction_hash: String,
 }
 
+/// Parameters for the `speed_up_tx` and `cancel_tx` tools.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct ReplaceTxParams {
+    /// Hash of the pending transaction to replace.
+    transaction_hash: String,
+    /// Fee increase in percent; at least 10, the default, and at most 1000.
+    bump_percent: Option<u64>,
+    /// Replace even if the transaction is for another chain than the
+    /// wallet's, or the node serves another chain; defaults to false.
+    allow_chain_mismatch: Option<bool>,
+}
+
+/// Parameters for the `list_transactions` tool.
+#[derive(Deserialize, Debug, Default, schemars::JsonSchema)]
+struct ListTransactionsParams {
+    /// Only transactions sent from or to this address or alias.
+    account: Option<String>,
+    /// Only transactions with this status.
+    status: Option<TxStatus>,
+    /// Only transactions recorded at or after this unix time (seconds).
+    since: Option<u64>,
+    /// Only transactions recorded at or before this unix time (seconds).
+    until: Option<u64>,
+    /// Maximum number of transactions to return, newest first.
+    limit: Option<usize>,
+}
+
+/// Parameters for the `wait_for_transaction` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct WaitForTransactionParams {
+    /// The transaction hash as a hex-encoded string.
+    transaction_hash: String,
+    /// Blocks to wait for, counting the transaction's own block; defaults to 1.
+    confirmations: Option<u64>,
+    /// Give up after this many seconds; defaults to 120, at most a day.
+    timeout_secs: Option<u64>,
+    /// Milliseconds between polls; defaults to 2000, at most an hour.
+    poll_interval_ms: Option<u64>,
+}
+
 /// Parameters for the `eth_getTransactionReceipt` tool.
 #[derive(Deserialize, Debug, schemars::JsonSchema)]
 struct GetTxReceiptParams {
@@ -165,6 +424,12 @@ struct TransferEthParams {
     /// Aliases: chain, chainId
     #[serde(alias = "chain", alias = "chainId")]
     chain_id: Option<u64>,
+    /// Simulate the transfer on the pending block first and refuse to
+    /// send it if it would revert; defaults to true.
+    simulate: Option<bool>,
+    /// Send even if `chain_id` is not the chain of the node; defaults to
+    /// false.
+    allow_chain_mismatch: Option<bool>,
 }
 
 /// Parameters for the `resolve_alias` tool.
@@ -174,12 +439,133 @@ struct ResolveAliasParams {
     alias: String,
 }
 
+/// Parameters for the `switch_profile` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct SwitchProfileParams {
+    /// The name of the profile to activate.
+    #[serde(alias = "profile")]
+    name: String,
+}
+
+/// A network given by name or chain id.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+#[serde(untagged)]
+enum NetworkId {
+    /// Chain id, e.g. 11155111
+    ChainId(u64),
+    /// Name or decimal chain id, e.g. "sepolia"
+    Name(String),
+}
+
+/// Parameters for the `switch_network` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct SwitchNetworkParams {
+    /// The name (case-insensitive) or chain id of the network to connect to.
+    /// Aliases: name, chain_id
+    #[serde(alias = "name", alias = "chain_id")]
+    network: NetworkId,
+}
+
+/// Parameters for the `split_private_key` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct SplitPrivateKeyParams {
+    /// The identifier (address or alias) of the signing account.
+    account: String,
+    /// Number of shares required to rebuild the key.
+    threshold: u8,
+    /// Total number of shares to create.
+    shares: u8,
+}
+
+/// Parameters for the `import_key_shares` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct ImportKeySharesParams {
+    /// Share strings as produced by `split_private_key`.
+    shares: Vec<String>,
+}
+
+/// Parameters for the `export_backup` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct ExportBackupParams {
+    /// Path of the archive file to write.
+    path: String,
+    /// Password used to encrypt the archive.
+    #[schemars(with = "String")]
+    password: SecretString,
+}
+
+/// Parameters for the `import_backup` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct ImportBackupParams {
+    /// Path of the archive file to read.
+    path: String,
+    /// Password the archive was encrypted with.
+    #[schemars(with = "String")]
+    password: SecretString,
+    /// `merge` (default) keeps existing data, `replace` overwrites the wallet.
+    #[serde(default)]
+    mode: RestoreMode,
+    /// When true, only report what would change.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Parameters for the `import_keystore` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct ImportKeystoreParams {
+    /// A V3 keystore file, or a directory of them (e.g. `~/.foundry/keystores`).
+    path: String,
+    /// Password the keystores were encrypted with.
+    #[schemars(with = "String")]
+    password: SecretString,
+}
+
+/// Parameters for the `export_keystore` tool.
+#[derive(Deserialize, Debug, schemars::JsonSchema)]
+struct ExportKeystoreParams {
+    /// Address or alias of the signing account to export.
+    account: String,
+    /// Password to encrypt the keystore with.
+    #[schemars(with = "String")]
+    password: SecretString,
+    /// Target directory; defaults to `~/.foundry/keystores`.
+    dir: Option<String>,
+    /// File name; defaults to the account's first alias, then its address.
+    name: Option<String>,
+}
+
+/// Parameters for the `list_accounts` tool.
+#[derive(Deserialize, Debug, Default, schemars::JsonSchema)]
+struct ListAccountsParams {
+    /// Also fetch each account's balance in ETH, in one batch request.
+    #[serde(default)]
+    with_balances: bool,
+    /// Block to read the balances at; defaults to the latest block.
+    #[serde(default)]
+    block: Option<BlockParam>,
+}
+
+/// Parameters for the `sync_accounts` tool.
+#[derive(Deserialize, Debug, Default, schemars::JsonSchema)]
+struct SyncAccountsParams {
+    /// Rewind accounts whose local nonce is ahead of the chain to the pending nonce.
+    #[serde(default)]
+    repair_gaps: bool,
+}
+
 /// The service handler for the wallet.
 #[derive(Clone)]
 pub struct WalletHandler {
     tool_router: ToolRouter<Self>,
     wallet: Arc<Mutex<Wallet>>,
-    eth_client: Arc<EthClient>,
+    eth_client: Arc<RwLock<Arc<EthClient>>>,
+    profiles: Arc<ProfileRegistry>,
+    chains: Arc<ChainRegistry>,
+    active_profile: Arc<Mutex<String>>,
+    nonces: NonceManager,
+    replacements: ReplacementTracker,
+    journal: Arc<Mutex<TxJournal>>,
+    subscriptions: Subscriptions,
 }
 
 #[tool_router]
@@ -189,11 +575,460 @@ impl WalletHandler {
     pub fn new(wallet: Arc<Mutex<Wallet>>, eth_client: Arc<EthClient>) -> Self {
         Self {
             wallet,
-            eth_client,
+            eth_client: Arc::new(RwLock::new(eth_client)),
+            profiles: Arc::new(ProfileRegistry::new()),
+            chains: Arc::new(ChainRegistry::builtin()),
+            active_profile: Arc::new(Mutex::new(DEFAULT_PROFILE.to_string())),
+            nonces: NonceManager::new(),
+            replacements: ReplacementTracker::new(),
+            journal: Arc::new(Mutex::new(TxJournal::new())),
+            subscriptions: Subscriptions::new(),
             tool_router: Self::tool_router(),
         }
     }
 
+    /// Sets the profiles available for runtime switching and the name of the
+    /// profile the wallet and client were loaded from.
+    pub fn with_profiles(mut self, profiles: ProfileRegistry, active: impl Into<String>) -> Self {
+        self.profiles = Arc::new(profiles);
+        self.active_profile = Arc::new(Mutex::new(active.into()));
+        self
+    }
+
+    /// Sets the transaction journal of the active profile.
+    pub fn with_journal(mut self, journal: TxJournal) -> Self {
+        self.journal = Arc::new(Mutex::new(journal));
+        self
+    }
+
+    /// Sets the chains used to label outputs and check chain ids.
+    pub fn with_chains(mut self, chains: ChainRegistry) -> Self {
+        self.chains = Arc::new(chains);
+        self
+    }
+
+    /// Returns the RPC client of the active profile.
+    async fn client(&self) -> Arc<EthClient> {
+        self.eth_client.read().await.clone()
+    }
+
+    /// Describes the chain `client` is connected to, if the node tells.
+    async fn connected_chain(&self, client: &EthClient) -> Option<ChainInfo> {
+        match client.get_chain_id().await {
+            Ok(chain_id) => Some(self.chains.describe(chain_id)),
+            Err(e) => {
+                log::warn!("Failed to get the chain id: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Formats a balance in the native currency of `chain`, or in ether if
+    /// the chain is not known.
+    fn format_balance(
+        &self,
+        balance: U256,
+        chain: Option<&ChainInfo>,
+    ) -> Result<String, ErrorData> {
+        match chain {
+            Some(chain) => chain.format_amount(balance).map_err(to_internal_error),
+            None => Ok(format_ether(balance)),
+        }
+    }
+
+    /// Adds the name of the network a transaction result refers to.
+    fn label_network(&self, result: &mut Value, chain: &ChainInfo) {
+        if let Value::Object(map) = result {
+            map.insert("network".into(), json!(chain.name));
+        }
+    }
+
+    /// Adds the name of the active network to a tool result.
+    async fn label_active_network(&self, result: &mut Value, client: &EthClient) {
+        if let Some(chain) = self.connected_chain(client).await {
+            self.label_network(result, &chain);
+        }
+    }
+
+    /// Adds the network name and explorer link of a sent transaction to a
+    /// tool result.
+    fn label_tx(&self, result: &mut Value, chain: &ChainInfo, tx_hash: H256) {
+        self.label_network(result, chain);
+        if let (Value::Object(map), Some(url)) = (result, chain.tx_url(tx_hash)) {
+            map.insert("explorer_url".into(), json!(url));
+        }
+    }
+
+    /// Resolves a wallet account or alias, falling back to a plain address.
+    async fn resolve_address(&self, identifier: &str) -> Result<Address, ErrorData> {
+        let wallet = self.wallet.lock().await;
+        match wallet.get_account(identifier) {
+            Some((_, address)) => Ok(address),
+            None => Address::from_str(identifier)
+                .map_err(|_| to_invalid_params_error(format!("Unknown account: {}", identifier))),
+        }
+    }
+
+    /// Parses a log topic: 32-byte hex, or an address or alias padded to 32 bytes.
+    async fn parse_topic(&self, value: &str) -> Result<H256, ErrorData> {
+        match value.strip_prefix("0x") {
+            Some(hex) if hex.len() == 64 => H256::from_str(hex)
+                .map_err(|_| to_invalid_params_error(format!("Invalid topic: {}", value))),
+            _ => Ok(H256::from(self.resolve_address(value).await?)),
+        }
+    }
+
+    /// Builds a log filter and decoding ABI from the `eth_get_logs` style
+    /// parameters; the block range is left unset.
+    async fn log_filter(
+        &self,
+        address: &Option<OneOrMany>,
+        event: &Option<String>,
+        abi_json: &Option<Value>,
+        topics: Option<Vec<Option<OneOrMany>>>,
+    ) -> Result<(LogFilter, Option<ethers::abi::Abi>), ErrorData> {
+        let mut abi = match event {
+            Some(event) => {
+                Some(decode::parse_signatures(&[event]).map_err(to_invalid_params_error)?)
+            }
+            None => None,
+        };
+        let event_topic = abi
+            .as_ref()
+            .and_then(|abi| abi.events().next())
+            .map(|event| event.signature());
+        if let Some(value) = abi_json {
+            let extra = parse_abi_param(value)?;
+            match &mut abi {
+                Some(abi) => {
+                    for (name, events) in extra.events {
+                        abi.events.entry(name).or_default().extend(events);
+                    }
+                }
+                None => abi = Some(extra),
+            }
+        }
+
+        let mut filter = LogFilter::default();
+        for identifier in address.iter().flat_map(OneOrMany::values) {
+            filter
+                .addresses
+                .push(self.resolve_address(identifier).await?);
+        }
+        let topics = topics.unwrap_or_default();
+        if topics.len() > 4 {
+            return Err(to_invalid_params_error("At most 4 topics can be filtered"));
+        }
+        for (i, topic) in topics.iter().enumerate() {
+            if let Some(values) = topic {
+                let mut parsed = Vec::new();
+                for value in values.values() {
+                    parsed.push(self.parse_topic(value).await?);
+                }
+                filter.topics[i] = Some(parsed);
+            }
+        }
+        if filter.topics[0].is_none() {
+            filter.topics[0] = event_topic.map(|topic| vec![topic]);
+        }
+        Ok((filter, abi))
+    }
+
+    /// Records a signed raw transaction in the journal and saves it.
+    ///
+    /// Failures are only logged: the transaction was already signed or sent.
+    async fn journal_tx(&self, raw: &[u8], sent: bool, replaces: Option<H256>) {
+        let mut journal = self.journal.lock().await;
+        if let Err(e) = journal
+            .record(raw, sent, replaces)
+            .and_then(|_| journal.save())
+        {
+            log::warn!("Failed to record transaction in the journal: {}", e);
+        }
+    }
+
+    /// Refreshes open journal entries, keeping the stored statuses if the node
+    /// cannot be reached.
+    async fn refresh_journal(&self, hashes: Option<&[H256]>) {
+        let client = self.client().await;
+        match journal::refresh(&self.journal, &client, hashes).await {
+            Ok(0) => {}
+            Ok(_) => {
+                if let Err(e) = self.journal.lock().await.save() {
+                    log::warn!("Failed to save the transaction journal: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to refresh the transaction journal: {}", e),
+        }
+    }
+
+    /// Replaces a pending wallet transaction with a re-priced copy or a cancellation.
+    async fn replace_tx(
+        &self,
+        params: ReplaceTxParams,
+        kind: ReplacementKind,
+    ) -> Result<CallToolResult, ErrorData> {
+        let original_hash = parse_tx_hash(&params.transaction_hash)?;
+        let bump_percent = params.bump_percent.unwrap_or(MIN_FEE_BUMP_PERCENT);
+        if bump_percent > MAX_FEE_BUMP_PERCENT {
+            return Err(to_invalid_params_error(format!(
+                "bump_percent must be at most {}, got {}",
+                MAX_FEE_BUMP_PERCENT, bump_percent
+            )));
+        }
+        let client = self.client().await;
+        let original = client
+            .get_transaction_info(original_hash)
+            .await
+            .map_err(to_internal_error)?
+            .ok_or_else(|| {
+                to_invalid_params_error(WalletError::TransactionNotFound(original_hash))
+            })?;
+        let chain_id = match original.chain_id {
+            Some(id) => id.as_u64(),
+            None => client.get_chain_id().await.map_err(to_internal_error)?,
+        };
+        if !params.allow_chain_mismatch.unwrap_or(false) {
+            check_tx_chain(&client, Some(chain_id), true).await?;
+        }
+        // A node without fee history support still gets the minimum bump
+        let network_fees = client.estimate_eip1559_fees().await.ok();
+        let tx_request =
+            replacement::replacement_request(&original, chain_id, kind, bump_percent, network_fees)
+                .map_err(to_invalid_params_error)?;
+
+        let from = format!("0x{:x}", original.from);
+        let signed_tx = {
+            let wallet = self.wallet.lock().await;
+            wallet
+                .get_signer(&original.from)
+                .map_err(to_invalid_params_error)?;
+            wallet
+                .sign_reserved(&tx_request, &from)
+                .await
+                .map_err(to_internal_error)?
+        };
+        let raw_tx_hex = format!("0x{}", hex::encode(&signed_tx.raw_transaction));
+        let tx_hash = client
+            .send_signed_transaction(&raw_tx_hex)
+            .await
+            .map_err(to_internal_error)?;
+
+        self.journal_tx(&signed_tx.raw_transaction, true, Some(original_hash))
+            .await;
+
+        let nonce = original.nonce.as_u64();
+        let group = self
+            .replacements
+            .record(original.from, chain_id, nonce, original_hash, tx_hash)
+            .await;
+        let mut result = json!({
+            "transaction_hash": format!("0x{:x}", tx_hash),
+            "replaces": format!("0x{:x}", original_hash),
+            "kind": kind,
+            "nonce": nonce,
+            "max_fee_per_gas": tx_request.max_fee_per_gas.to_string(),
+            "max_priority_fee_per_gas": tx_request.max_priority_fee_per_gas.to_string(),
+            "candidates": group.candidates,
+        });
+        self.label_tx(&mut result, &self.chains.describe(chain_id), tx_hash);
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Lists the configured wallet profiles.
+    #[tool(description = "Lists the configured wallet profiles and marks the active one.")]
+    async fn list_profiles(&self) -> Result<CallToolResult, ErrorData> {
+        let active = self.active_profile.lock().await.clone();
+        let mut profiles = Vec::new();
+        for (name, profile) in self.profiles.iter() {
+            let wallet_file = profile.wallet_path(name).map_err(to_internal_error)?;
+            profiles.push(json!({
+                "name": name,
+                "wallet_file": wallet_file.display().to_string(),
+                "rpc_url": profile.rpc_url,
+                "fallback_rpc_urls": profile.fallback_rpc_urls,
+                "quorum": profile.quorum,
+                "chain_id": profile.chain_id,
+                "active": name == active,
+            }));
+        }
+        let result = json!({ "active": active, "profiles": profiles });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Gets the active wallet profile.
+    #[tool(description = "Gets the active wallet profile.")]
+    async fn current_profile(&self) -> Result<CallToolResult, ErrorData> {
+        let active = self.active_profile.lock().await.clone();
+        let wallet_file = self
+            .wallet
+            .lock()
+            .await
+            .file_path()
+            .map(|p| p.display().to_string());
+        let chain_id = self.profiles.get(&active).ok().and_then(|p| p.chain_id);
+        let client = self.client().await;
+        let result = json!({
+            "name": active,
+            "wallet_file": wallet_file,
+            "rpc_url": client.url(),
+            "chain_id": chain_id,
+            "endpoints": client.endpoint_health(),
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Switches to another wallet profile, saving the current wallet first.
+    #[tool(
+        description = "Switches to another wallet profile. Saves the current wallet, loads the \
+profile's wallet file and connects to its default network."
+    )]
+    async fn switch_profile(
+        &self,
+        params: Parameters<SwitchProfileParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let name = params.0.name;
+        let profile = self.profiles.get(&name).map_err(to_invalid_params_error)?;
+        let wallet_path = profile.wallet_path(&name).map_err(to_internal_error)?;
+        let client = EthClient::from_profile(profile).map_err(to_internal_error)?;
+
+        let journal_path = journal::history_path(&wallet_path);
+
+        let mut wallet = self.wallet.lock().await;
+        let mut journal = self.journal.lock().await;
+        wallet.save().map_err(to_internal_error)?;
+        journal.save().map_err(to_internal_error)?;
+        *wallet = Wallet::load_or_create(&wallet_path).map_err(to_internal_error)?;
+        *journal = TxJournal::load_or_create(&journal_path).map_err(to_internal_error)?;
+        self.nonces.reset().await;
+        self.replacements.reset().await;
+        // Subscriptions listen on the previous endpoint
+        let stopped = self.subscriptions.clear().await;
+        if stopped > 0 {
+            log::info!(
+                "Stopped {} subscription(s) of the previous profile",
+                stopped
+            );
+        }
+        *self.eth_client.write().await = Arc::new(client);
+        *self.active_profile.lock().await = name.clone();
+        log::info!("Switched to wallet profile '{}'", name);
+
+        let result = json!({
+            "name": name,
+            "wallet_file": wallet_path.display().to_string(),
+            "rpc_url": profile.rpc_url,
+            "chain_id": profile.chain_id,
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Lists the networks that can be switched to.
+    #[tool(
+        description = "Lists the networks that can be switched to with switch_network: name, chain id, currency and RPC endpoints. Marks the active one."
+    )]
+    async fn list_networks(&self) -> Result<CallToolResult, ErrorData> {
+        let active = self
+            .connected_chain(&*self.client().await)
+            .await
+            .map(|chain| chain.chain_id);
+        let networks: Vec<Value> = self
+            .chains
+            .networks()
+            .map(|chain| {
+                json!({
+                    "name": chain.name,
+                    "chain_id": chain.chain_id,
+                    "currency": chain.currency,
+                    "rpc_urls": chain.rpc_urls,
+                    "active": Some(chain.chain_id) == active,
+                })
+            })
+            .collect();
+        let result = json!({ "active": active, "networks": networks });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Gets the network the wallet is connected to.
+    #[tool(
+        description = "Gets the network the wallet is connected to: name, chain id, currency, explorer and the health of its RPC endpoints."
+    )]
+    async fn current_network(&self) -> Result<CallToolResult, ErrorData> {
+        let client = self.client().await;
+        let chain_id = client.get_chain_id().await.map_err(to_internal_error)?;
+        let chain = self.chains.describe(chain_id);
+        let result = json!({
+            "name": chain.name,
+            "chain_id": chain.chain_id,
+            "currency": chain.currency,
+            "eip1559": chain.eip1559,
+            "explorer_tx_url": chain.explorer_tx_url,
+            "rpc_url": client.url(),
+            "endpoints": client.endpoint_health(),
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Connects to another network, keeping the wallet.
+    #[tool(
+        description = "Connects to another network from list_networks, by name or chain id, keeping the current wallet. Fails without switching if the node reports a different chain id. Example: {network:'sepolia'}"
+    )]
+    async fn switch_network(
+        &self,
+        params: Parameters<SwitchNetworkParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let id = match params.0.network {
+            NetworkId::ChainId(chain_id) => chain_id.to_string(),
+            NetworkId::Name(name) => name,
+        };
+        let chain = self.chains.network(&id).map_err(to_invalid_params_error)?;
+
+        // The endpoint settings of the active profile carry over
+        let active = self.active_profile.lock().await.clone();
+        let base = self
+            .profiles
+            .get(&active)
+            .cloned()
+            .unwrap_or_else(|_| WalletProfile::new(""));
+        let profile = WalletProfile {
+            rpc_url: chain.rpc_urls[0].clone(),
+            fallback_rpc_urls: chain.rpc_urls[1..].to_vec(),
+            chain_id: Some(chain.chain_id),
+            quorum: None,
+            ..base
+        };
+        let client = EthClient::from_profile(&profile).map_err(to_internal_error)?;
+        let actual = client.get_chain_id().await.map_err(to_internal_error)?;
+        if actual != chain.chain_id {
+            return Err(to_invalid_params_error(WalletError::ChainIdMismatch {
+                expected: chain.chain_id,
+                actual,
+            }));
+        }
+
+        // Released nonces and replacement groups belong to the previous
+        // network, and subscriptions listen on its endpoint
+        self.nonces.reset().await;
+        self.replacements.reset().await;
+        let stopped = self.subscriptions.clear().await;
+        if stopped > 0 {
+            log::info!(
+                "Stopped {} subscription(s) of the previous network",
+                stopped
+            );
+        }
+        *self.eth_client.write().await = Arc::new(client);
+        log::info!("Switched to network '{}' ({})", chain.name, chain.chain_id);
+
+        let result = json!({
+            "name": chain.name,
+            "chain_id": chain.chain_id,
+            "currency": chain.currency,
+            "rpc_url": profile.rpc_url,
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
     /// Creates a new Ethereum account.
     #[tool(description = "Creates a new Ethereum account.")]
     async fn new_account(
@@ -208,93 +1043,527 @@ impl WalletHandler {
         Ok(CallToolResult::structured(result))
     }
 
-    /// Gets a transaction receipt by its hash.
-    #[tool(description = "Gets a transaction receipt by its hash.")]
+    /// Gets a decoded transaction receipt by its hash.
+    #[tool(
+        description = "Gets a transaction receipt by its hash: status (success/failed/unknown), \
+gas used, effective gas price, fee in wei and ETH, created contract address, logs decoded with \
+well-known events (ERC-20/721 Transfer, Approval, ...) and, for failed transactions, the revert \
+reason. Returns found:false while pending."
+    )]
     async fn eth_get_transaction_receipt(
         &self,
         params: Parameters<GetTxReceiptParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let tx_hash = H256::from_str(
-            params
-                .0
-                .transaction_hash
-                .strip_prefix("0x")
-                .unwrap_or(&params.0.transaction_hash),
-        )
-        .map_err(|e| to_invalid_params_error(e.to_string()))?;
-
-        let receipt_opt = self
-            .eth_client
-            .get_transaction_receipt(tx_hash)
+        let tx_hash = parse_tx_hash(&params.0.transaction_hash)?;
+        let client = self.client().await;
+        let report = receipt::describe(&client, tx_hash)
             .await
             .map_err(to_internal_error)?;
 
-        let result = if let Some(rcpt) = receipt_opt {
-            let status_num = rcpt.status.map(|s| s.as_u64()).unwrap_or(0);
-            let status_text = if status_num == 1 { "success" } else { "failed" };
-            json!({
-                "found": true,
-                "status": status_text,
-                "block_number": rcpt.block_number.map(|b| b.as_u64()),
-                "transaction_hash": format!("0x{:x}", rcpt.transaction_hash),
-            })
+        let mut result = match report {
+            Some(report) => {
+                let mut result = serde_json::to_value(report).map_err(to_internal_error)?;
+                if let Value::Object(map) = &mut result {
+                    map.insert("found".into(), json!(true));
+                }
+                result
+            }
+            None => json!({ "found": false, "status": "pending" }),
+        };
+        self.label_active_network(&mut result, &client).await;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Lists all Ethereum accounts in the wallet.
+    #[tool(
+        description = "Lists all Ethereum accounts in the wallet, optionally with their balances."
+    )]
+    async fn list_accounts(
+        &self,
+        params: Parameters<ListAccountsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let wallet = self.wallet.lock().await;
+        let accounts = wallet.list_accounts();
+        let (balances, chain) = if params.0.with_balances {
+            let addresses: Vec<Address> = accounts.iter().map(|(address, _)| *address).collect();
+            let client = self.client().await;
+            let balances = client
+                .get_balances(&addresses, block_id(&params.0.block)?)
+                .await
+                .map_err(to_state_error)?;
+            let chain = self.connected_chain(&client).await;
+            let balances = balances
+                .into_iter()
+                .map(|balance| self.format_balance(balance, chain.as_ref()))
+                .collect::<Result<Vec<_>, _>>()?;
+            (Some(balances), chain)
         } else {
-            json!({ "found": false, "status": "pending" })
+            (None, None)
+        };
+        let json_accounts: Vec<_> = accounts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (address, account))| {
+                let mut entry = json!({
+                    "address": to_checksum(&address, None),
+                    "nonces": account
+                        .chains
+                        .iter()
+                        .map(|(chain_id, state)| (*chain_id, state.nonce))
+                        .collect::<BTreeMap<_, _>>(),
+                    "aliases": account.aliases,
+                    "is_signing": account.private_key.is_some()
+                });
+                // Not yet migrated: the next nonce on whichever chain is used first
+                if let Some(nonce) = account.legacy_nonce {
+                    entry["legacy_nonce"] = json!(nonce);
+                }
+                if let Some(balances) = &balances {
+                    entry["balance"] = json!(balances[i]);
+                }
+                if let Some(chain) = &chain {
+                    entry["currency"] = json!(chain.currency);
+                }
+                entry
+            })
+            .collect();
+        let result = serde_json::to_value(json_accounts).map_err(to_internal_error)?;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Sets an alias for an Ethereum account.
+    #[tool(description = "Sets an alias for an Ethereum account.")]
+    async fn set_alias(
+        &self,
+        params: Parameters<SetAliasParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let mut wallet = self.wallet.lock().await;
+        let address = Address::from_str(&params.0.address)
+            .map_err(|_| to_internal_error(format!("Invalid address: {}", params.0.address)))?;
+        wallet
+            .set_or_update_alias(address, params.0.alias.clone())
+            .map_err(to_internal_error)?;
+        let result = Value::Null;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Imports a private key, creating or upgrading an account as needed.
+    #[tool(description = "Imports a private key, creating or upgrading an account as needed.")]
+    async fn import_private_key(
+        &self,
+        params: Parameters<ImportPrivateKeyParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let mut wallet = self.wallet.lock().await;
+        let key = PrivateKey::from_hex(params.0.private_key.expose()).map_err(|_| {
+            to_invalid_params_error("Invalid private key format (expect 32-byte hex)".to_string())
+        })?;
+        let address = wallet.import_key(key).map_err(to_internal_error)?;
+        let result = json!({ "address": to_checksum(&address, None) });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Splits an account's private key into Shamir shares.
+    #[tool(
+        description = "Splits a signing account's private key into N text shares, any \
+'threshold' of which rebuild it. Example: {account:'treasury',threshold:2,shares:3}"
+    )]
+    async fn split_private_key(
+        &self,
+        params: Parameters<SplitPrivateKeyParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let wallet = self.wallet.lock().await;
+        let shares = wallet
+            .split_private_key(&params.0.account, params.0.threshold, params.0.shares)
+            .map_err(to_invalid_params_error)?;
+        let address = shares.first().map(|s| to_checksum(&s.address, None));
+        let shares: Vec<String> = shares.iter().map(ToString::to_string).collect();
+        let result = json!({
+            "address": address,
+            "threshold": params.0.threshold,
+            "shares": shares,
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Rebuilds a private key from Shamir shares and imports it.
+    #[tool(
+        description = "Rebuilds a private key from Shamir shares (at least the threshold) and \
+imports it, creating or upgrading the account."
+    )]
+    async fn import_key_shares(
+        &self,
+        params: Parameters<ImportKeySharesParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let shares = params
+            .0
+            .shares
+            .iter()
+            .map(|s| s.parse::<KeyShare>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_invalid_params_error)?;
+        let mut wallet = self.wallet.lock().await;
+        let address = wallet
+            .import_key_shares(&shares)
+            .map_err(to_invalid_params_error)?;
+        let result = json!({ "address": to_checksum(&address, None) });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Writes a password-encrypted backup of the whole wallet to a file.
+    #[tool(
+        description = "Writes a password-encrypted, versioned backup of the whole wallet \
+(accounts, keys, aliases, nonces, transaction history) to a file."
+    )]
+    async fn export_backup(
+        &self,
+        params: Parameters<ExportBackupParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let wallet = self.wallet.lock().await;
+        let journal = self.journal.lock().await;
+        let archive = wallet
+            .export_backup(params.0.password.expose(), Some(&journal))
+            .map_err(to_internal_error)?;
+        let contents = serde_json::to_string_pretty(&archive).map_err(to_internal_error)?;
+        std::fs::write(&params.0.path, contents).map_err(to_internal_error)?;
+        let result = json!({
+            "path": params.0.path,
+            "version": archive.version,
+            "accounts": wallet.list_accounts().len(),
+            "transactions": journal.len(),
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Restores a wallet backup, merging into or replacing the current wallet.
+    #[tool(
+        description = "Restores a wallet backup file, including its transaction history. \
+mode: 'merge' (default) or 'replace'. Set dry_run:true to only report what would change."
+    )]
+    async fn import_backup(
+        &self,
+        params: Parameters<ImportBackupParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let contents = std::fs::read_to_string(&params.0.path).map_err(to_invalid_params_error)?;
+        let archive: BackupArchive =
+            serde_json::from_str(&contents).map_err(to_invalid_params_error)?;
+        let mut wallet = self.wallet.lock().await;
+        let mut journal = self.journal.lock().await;
+        let report = wallet
+            .import_backup(
+                &archive,
+                params.0.password.expose(),
+                params.0.mode,
+                params.0.dry_run,
+                Some(&mut journal),
+            )
+            .map_err(to_invalid_params_error)?;
+        if !params.0.dry_run {
+            journal.save().map_err(to_internal_error)?;
+        }
+        let result = serde_json::to_value(report).map_err(to_internal_error)?;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Imports geth/Foundry V3 keystore files.
+    #[tool(
+        description = "Imports a V3 keystore file or a directory of them (geth keystore/, \
+~/.foundry/keystores) with a password. Aliases come from the file names. \
+Example: {path:'~/.foundry/keystores',password:'...'}"
+    )]
+    async fn import_keystore(
+        &self,
+        params: Parameters<ImportKeystoreParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let path = keystore::expand_home(&params.0.path);
+        let mut wallet = self.wallet.lock().await;
+        let report = wallet
+            .import_keystore(&path, params.0.password.expose())
+            .map_err(to_invalid_params_error)?;
+        let result = serde_json::to_value(report).map_err(to_internal_error)?;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Exports a signing account as a V3 keystore file.
+    #[tool(
+        description = "Exports a signing account as a V3 keystore file usable by geth and \
+`cast wallet`. dir defaults to ~/.foundry/keystores, name to the account alias. \
+Example: {account:'treasury',password:'...'}"
+    )]
+    async fn export_keystore(
+        &self,
+        params: Parameters<ExportKeystoreParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let dir = match &params.0.dir {
+            Some(dir) => keystore::expand_home(dir),
+            None => keystore::default_keystore_dir().map_err(to_internal_error)?,
         };
+        let wallet = self.wallet.lock().await;
+        let path = wallet
+            .export_keystore(
+                &params.0.account,
+                &dir,
+                params.0.password.expose(),
+                params.0.name.as_deref(),
+            )
+            .map_err(to_invalid_params_error)?;
+        let result = json!({ "path": path.display().to_string() });
+        Ok(CallToolResult::structured(result))
+    }
 
+    /// Reconciles the nonces of all signing accounts with the chain.
+    #[tool(
+        description = "Refreshes the nonce of every signing account from eth_getTransactionCount \
+(latest and pending). Reports drift and gaps; set repair_gaps:true to rewind nonces the node \
+has never seen."
+    )]
+    async fn sync_accounts(
+        &self,
+        params: Parameters<SyncAccountsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let client = self.client().await;
+        let accounts = self
+            .nonces
+            .sync_all(&self.wallet, &client, params.0.repair_gaps)
+            .await
+            .map_err(to_internal_error)?;
+        let result = json!({ "accounts": accounts });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Re-sends a pending transaction with higher fees.
+    #[tool(
+        description = "Speeds up a pending transaction sent by a wallet account: re-signs it with \
+the same nonce and fees raised by bump_percent (min and default 10) and broadcasts it. \
+Example: {transaction_hash:'0x...'}"
+    )]
+    async fn speed_up_tx(
+        &self,
+        params: Parameters<ReplaceTxParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.replace_tx(params.0, ReplacementKind::SpeedUp).await
+    }
+
+    /// Cancels a pending transaction with a zero-value self-transfer.
+    #[tool(
+        description = "Cancels a pending transaction sent by a wallet account by broadcasting a \
+zero-value transfer to itself with the same nonce and higher fees. Example: \
+{transaction_hash:'0x...',bump_percent:25}"
+    )]
+    async fn cancel_tx(
+        &self,
+        params: Parameters<ReplaceTxParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.replace_tx(params.0, ReplacementKind::Cancel).await
+    }
+
+    /// Reports which transaction of a speed-up/cancel group was mined.
+    #[tool(
+        description = "Given any hash from a speed_up_tx/cancel_tx group, reports whether one of \
+them was mined and which hash won."
+    )]
+    async fn get_replacement_status(
+        &self,
+        params: Parameters<GetTxInfoParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let hash = parse_tx_hash(&params.0.transaction_hash)?;
+        let group = self.replacements.find(hash).await.ok_or_else(|| {
+            to_invalid_params_error(format!("No speed-up or cancel recorded for 0x{:x}", hash))
+        })?;
+        let client = self.client().await;
+        let status: ReplacementStatus = ReplacementTracker::status(&client, &group)
+            .await
+            .map_err(to_internal_error)?;
+        let mut result = serde_json::to_value(&group).map_err(to_internal_error)?;
+        if let (Value::Object(map), Value::Object(status)) = (
+            &mut result,
+            serde_json::to_value(status).map_err(to_internal_error)?,
+        ) {
+            map.extend(status);
+        }
+        self.label_active_network(&mut result, &client).await;
         Ok(CallToolResult::structured(result))
     }
 
-    /// Lists all Ethereum accounts in the wallet.
-    #[tool(description = "Lists all Ethereum accounts in the wallet.")]
-    async fn list_accounts(&self) -> Result<CallToolResult, ErrorData> {
-        let wallet = self.wallet.lock().await;
-        let accounts = wallet.list_accounts();
-        let json_accounts: Vec<_> = accounts
+    /// Lists transactions from the wallet's journal.
+    #[tool(
+        description = "Lists transactions the wallet signed or sent, newest first, with status \
+signed/pending/mined/failed/dropped/replaced refreshed from the chain. Filters: account \
+(address or alias, as sender or recipient), status, since/until (unix seconds), limit. \
+Example: {account:'Alice',status:'pending'}"
+    )]
+    async fn list_transactions(
+        &self,
+        params: Parameters<ListTransactionsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let account = match &params.0.account {
+            Some(identifier) => Some(self.resolve_address(identifier).await?),
+            None => None,
+        };
+        self.refresh_journal(None).await;
+
+        let query = TxQuery {
+            account,
+            status: params.0.status,
+            since: params.0.since,
+            until: params.0.until,
+            limit: params.0.limit,
+        };
+        let journal = self.journal.lock().await;
+        let transactions: Vec<Value> = journal
+            .query(&query)
             .into_iter()
-            .map(|(address, account)| {
-                json!({
-                    "address": to_checksum(&address, None),
-                    "nonce": account.nonce,
-                    "aliases": account.aliases,
-                    "is_signing": account.private_key.is_some()
-                })
+            .map(|record| {
+                let mut value = serde_json::to_value(record)?;
+                self.label_network(&mut value, &self.chains.describe(record.chain_id));
+                Ok(value)
             })
-            .collect();
-        let result = serde_json::to_value(json_accounts).map_err(to_internal_error)?;
+            .collect::<Result<_, serde_json::Error>>()
+            .map_err(to_internal_error)?;
+        let result = json!({
+            "count": transactions.len(),
+            "transactions": transactions,
+        });
         Ok(CallToolResult::structured(result))
     }
 
-    /// Sets an alias for an Ethereum account.
-    #[tool(description = "Sets an alias for an Ethereum account.")]
-    async fn set_alias(
+    /// Gets a transaction from the wallet's journal.
+    #[tool(
+        description = "Gets a transaction the wallet signed or sent from its journal, with the \
+status refreshed from the chain. Example: {transaction_hash:'0x...'}"
+    )]
+    async fn get_transaction(
         &self,
-        params: Parameters<SetAliasParams>,
+        params: Parameters<GetTxInfoParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let mut wallet = self.wallet.lock().await;
-        let address = Address::from_str(&params.0.address)
-            .map_err(|_| to_internal_error(format!("Invalid address: {}", params.0.address)))?;
-        wallet
-            .set_or_update_alias(address, params.0.alias.clone())
-            .map_err(to_internal_error)?;
-        let result = Value::Null;
+        let hash = parse_tx_hash(&params.0.transaction_hash)?;
+        if self.journal.lock().await.get(hash).is_none() {
+            return Err(to_invalid_params_error(WalletError::TransactionNotFound(
+                hash,
+            )));
+        }
+        self.refresh_journal(Some(&[hash])).await;
+        let journal = self.journal.lock().await;
+        let record = journal.get(hash);
+        let mut result = serde_json::to_value(record).map_err(to_internal_error)?;
+        if let Some(record) = record {
+            self.label_network(&mut result, &self.chains.describe(record.chain_id));
+        }
         Ok(CallToolResult::structured(result))
     }
 
-    /// Imports a private key, creating or upgrading an account as needed.
-    #[tool(description = "Imports a private key, creating or upgrading an account as needed.")]
-    async fn import_private_key(
+    /// Waits until a transaction has the requested number of confirmations.
+    #[tool(
+        description = "Waits until a transaction is mined and has N confirmations (default 1), \
+following reorgs, and sends progress notifications meanwhile. Ends with status confirmed \
+(with the receipt), replaced, dropped or timed_out (default timeout 120s). \
+Example: {transaction_hash:'0x...',confirmations:3,timeout_secs:300}"
+    )]
+    async fn wait_for_transaction(
         &self,
-        params: Parameters<ImportPrivateKeyParams>,
+        params: Parameters<WaitForTransactionParams>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, ErrorData> {
-        let mut wallet = self.wallet.lock().await;
-        let normalized = normalize_private_key_hex(&params.0.private_key).ok_or_else(|| {
-            to_invalid_params_error("Invalid private key format (expect 32-byte hex)".to_string())
-        })?;
-        let address = wallet
-            .import_private_key(&normalized)
-            .map_err(to_internal_error)?;
-        let result = json!({ "address": to_checksum(&address, None) });
+        let hash = parse_tx_hash(&params.0.transaction_hash)?;
+        let timeout = params
+            .0
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(confirmation::DEFAULT_TIMEOUT)
+            .min(confirmation::MAX_TIMEOUT);
+        let interval = params
+            .0
+            .poll_interval_ms
+            .map(Duration::from_millis)
+            .unwrap_or(confirmation::DEFAULT_POLL_INTERVAL)
+            .clamp(
+                confirmation::MIN_POLL_INTERVAL,
+                confirmation::MAX_POLL_INTERVAL,
+            );
+
+        let mut watcher = TxWatcher::new(hash, params.0.confirmations.unwrap_or(1));
+        if let Some(record) = self.journal.lock().await.get(hash) {
+            watcher = watcher.with_sender(record.from, record.nonce);
+        }
+        let client = self.client().await;
+        let progress_token = context.meta.get_progress_token();
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut last_reported = None;
+        let mut best_confirmations = 0;
+
+        let outcome = loop {
+            let progress = match watcher.poll(&client).await.map_err(to_internal_error)? {
+                WatchState::Done(outcome) => break outcome,
+                WatchState::Waiting(progress) => progress,
+            };
+            if let Some(token) = &progress_token {
+                if last_reported.as_ref() != Some(&progress) {
+                    // Progress may not go backwards, even when a reorg undoes confirmations
+                    best_confirmations = best_confirmations.max(progress.confirmations);
+                    let notification = ProgressNotificationParam {
+                        progress_token: token.clone(),
+                        progress: best_confirmations as f64,
+                        total: Some(progress.required as f64),
+                        message: Some(progress.message()),
+                    };
+                    if let Err(e) = context.peer.notify_progress(notification).await {
+                        log::debug!("Failed to send progress notification: {}", e);
+                    }
+                    last_reported = Some(progress);
+                }
+            }
+            if tokio::time::Instant::now() + interval > deadline {
+                break watcher.timed_out();
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = context.ct.cancelled() => {
+                    return Err(to_internal_error("wait_for_transaction was cancelled"));
+                }
+            }
+        };
+
+        // Keep the journal in line with what was observed
+        let status = match &outcome {
+            WaitOutcome::Confirmed {
+                success, receipt, ..
+            } => Some((
+                if *success {
+                    TxStatus::Mined
+                } else {
+                    TxStatus::Failed
+                },
+                receipt.block_number.map(|b| b.as_u64()),
+            )),
+            WaitOutcome::Replaced { .. } => Some((TxStatus::Replaced, None)),
+            WaitOutcome::Dropped { .. } => Some((TxStatus::Dropped, None)),
+            WaitOutcome::TimedOut { .. } => None,
+        };
+        if let Some((status, block_number)) = status {
+            let mut journal = self.journal.lock().await;
+            if journal.set_status(hash, status, block_number) {
+                if let Err(e) = journal.save() {
+                    log::warn!("Failed to save the transaction journal: {}", e);
+                }
+                drop(journal);
+                // Links a replaced transaction to the journaled one that was mined
+                self.refresh_journal(None).await;
+            }
+        }
+
+        let mut result = serde_json::to_value(&outcome).map_err(to_internal_error)?;
+        if let Value::Object(map) = &mut result {
+            map.insert("transaction_hash".into(), json!(format!("0x{:x}", hash)));
+            if let Some(winner) = self
+                .journal
+                .lock()
+                .await
+                .get(hash)
+                .and_then(|r| r.replaced_by)
+            {
+                map.insert("replaced_by".into(), json!(format!("0x{:x}", winner)));
+            }
+        }
+        self.label_active_network(&mut result, &client).await;
         Ok(CallToolResult::structured(result))
     }
 
@@ -322,21 +1591,25 @@ chain_id:31337}"
         let value = U256::from_dec_str(&params.0.value)
             .map_err(|_| to_internal_error(format!("Invalid 'value': {}", params.0.value)))?;
 
-        // Resolve chain id (from param or network)
+        // Resolve chain id (from param, config or network)
         let chain_id = if let Some(id) = params.0.chain_id {
             id
         } else {
-            self.eth_client
-                .get_chain_id()
+            self.client()
+                .await
+                .expected_chain_id()
                 .await
                 .map_err(to_internal_error)?
         };
+        self.chains
+            .check_eip1559(chain_id)
+            .map_err(to_invalid_params_error)?;
 
         let mut builder = crate::transaction::TransactionBuilder::new()
             .chain_id(chain_id)
             .to(to_address)
             .value(value)
-            .nonce(from_account.nonce);
+            .nonce(from_account.nonce(chain_id));
 
         if let Some(gas) = params.0.gas {
             builder = builder.gas(gas);
@@ -358,22 +1631,128 @@ chain_id:31337}"
         }
 
         let tx_request = builder.build();
-        let result = serde_json::to_value(&tx_request).map_err(to_internal_error)?;
+        let mut result = serde_json::to_value(&tx_request).map_err(to_internal_error)?;
+        self.label_network(&mut result, &self.chains.describe(chain_id));
         Ok(CallToolResult::structured(result))
     }
 
     /// Signs a transaction with a specified account.
     #[tool(description = "Signs a transaction with a specified account.")]
     async fn sign_tx(&self, params: Parameters<SignTxParams>) -> Result<CallToolResult, ErrorData> {
-        let mut wallet = self.wallet.lock().await;
         let tx_request: crate::models::Eip1559TransactionRequest =
             serde_json::from_value(params.0.tx_json.clone()).map_err(to_invalid_params_error)?;
+        // One client throughout, so a network switch cannot slip in between
+        let client = self.client().await;
+        if !params.0.allow_chain_mismatch.unwrap_or(false) {
+            check_tx_chain(&client, Some(tx_request.chain_id), false).await?;
+        }
+        if params.0.simulate.unwrap_or(true) {
+            let from = {
+                let wallet = self.wallet.lock().await;
+                wallet
+                    .get_account(&params.0.from)
+                    .map(|(_, address)| address)
+            }
+            .ok_or_else(|| to_internal_error(WalletError::SignerNotFound(params.0.from.clone())))?;
+            let mut tx: TypedTransaction = tx_request.clone().into();
+            tx.set_from(from);
+            simulation::simulate(&client, &tx)
+                .await
+                .map_err(to_simulation_error)?;
+        }
+        let mut wallet = self.wallet.lock().await;
         let signed_tx = wallet
             .sign_transaction(&tx_request, &params.0.from)
             .await
             .map_err(to_internal_error)?;
-        let result = serde_json::to_value(JsonSignedTransaction::from(signed_tx))
+        drop(wallet);
+        self.journal_tx(&signed_tx.raw_transaction, false, None)
+            .await;
+        let mut result = serde_json::to_value(JsonSignedTransaction::from(signed_tx))
             .map_err(to_internal_error)?;
+        self.label_network(&mut result, &self.chains.describe(tx_request.chain_id));
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Previews what an unsigned transaction would do.
+    #[tool(
+        description = "Simulates an unsigned transaction (e.g. from create_tx) on top of the \
+latest block without signing it. Reports success or the decoded revert reason, gas used, ETH and \
+ERC-20 balance changes per address, emitted events and storage writes. Runs in an embedded EVM on \
+state fetched from the node, or on the node itself if it serves debug_traceCall (engine tells \
+which)."
+    )]
+    async fn simulate_tx(
+        &self,
+        params: Parameters<SimulateTxParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let tx_request: crate::models::Eip1559TransactionRequest =
+            serde_json::from_value(params.0.tx_json.clone()).map_err(to_invalid_params_error)?;
+        let from = self.resolve_address(&params.0.from).await?;
+        let mut tx: TypedTransaction = tx_request.into();
+        tx.set_from(from);
+        let block = block_id(&params.0.block)?.unwrap_or(BlockNumber::Latest.into());
+        let client = self.client().await;
+        let node_trace = params.0.node_trace.unwrap_or(true);
+        let report = simulation::preview(&client, &tx, block, node_trace)
+            .await
+            .map_err(to_state_error)?;
+        let mut result = serde_json::to_value(report).map_err(to_internal_error)?;
+        self.label_active_network(&mut result, &client).await;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Traces a mined transaction.
+    #[tool(
+        description = "Re-executes a mined transaction with debug_traceTransaction. The default \
+'call' tracer returns a compact call tree: per frame the type, from, to, value, gas used, the \
+decoded function and return values if known (otherwise the selector), the decoded revert reason \
+and decoded events. The 'prestate' tracer returns the state the transaction touched, or with \
+diff_mode the state before and after. Needs a node with the debug namespace, such as anvil."
+    )]
+    async fn trace_transaction(
+        &self,
+        params: Parameters<TraceTransactionParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let hash = parse_tx_hash(&params.0.transaction_hash)?;
+        let client = self.client().await;
+        let mut result = trace::trace_transaction(
+            &client,
+            hash,
+            params.0.tracer.unwrap_or_default(),
+            params.0.diff_mode.unwrap_or(false),
+        )
+        .await
+        .map_err(to_internal_error)?;
+        self.label_active_network(&mut result, &client).await;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Traces an unsigned transaction without sending it.
+    #[tool(
+        description = "Runs an unsigned transaction (e.g. from create_tx) with debug_traceCall on \
+the latest or a given block and returns the same output as trace_transaction. Needs a node with \
+the debug namespace, such as anvil."
+    )]
+    async fn trace_call(
+        &self,
+        params: Parameters<TraceCallParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let tx_request: crate::models::Eip1559TransactionRequest =
+            serde_json::from_value(params.0.tx_json.clone()).map_err(to_invalid_params_error)?;
+        let from = self.resolve_address(&params.0.from).await?;
+        let mut tx: TypedTransaction = tx_request.into();
+        tx.set_from(from);
+        let block = block_id(&params.0.block)?.unwrap_or(BlockNumber::Latest.into());
+        let result = trace::trace_call(
+            &*self.client().await,
+            &tx,
+            block,
+            params.0.tracer.unwrap_or_default(),
+            params.0.diff_mode.unwrap_or(false),
+        )
+        .await
+        .map_err(to_internal_error)?;
         Ok(CallToolResult::structured(result))
     }
 
@@ -381,7 +1760,8 @@ chain_id:31337}"
     #[tool(description = "Gets the current block number of the Ethereum network.")]
     async fn eth_get_current_block(&self) -> Result<CallToolResult, ErrorData> {
         let block_number = self
-            .eth_client
+            .client()
+            .await
             .get_current_block()
             .await
             .map_err(to_internal_error)?;
@@ -389,18 +1769,332 @@ chain_id:31337}"
         Ok(CallToolResult::structured(result))
     }
 
-    /// Gets the ETH balance for a given address.
-    #[tool(description = "Gets the ETH balance for a given address.")]
+    /// Gets a block by number, hash or tag.
+    #[tool(
+        description = "Gets a block by number, hash or tag (latest, earliest, safe, finalized, \
+pending; default latest). Returns number, hash, parent hash, timestamp, miner, gas used and limit, \
+base fee and the transaction hashes, or full transactions with full_transactions."
+    )]
+    async fn eth_get_block(
+        &self,
+        params: Parameters<GetBlockParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let block = params.0.block.unwrap_or(BlockParam::Id("latest".into()));
+        let id = block.id()?;
+        let summary = block::get_block(
+            &*self.client().await,
+            id,
+            params.0.full_transactions.unwrap_or(false),
+        )
+        .await
+        .map_err(to_internal_error)?
+        .ok_or_else(|| to_invalid_params_error(format!("Block not found: {:?}", id)))?;
+        let result = serde_json::to_value(summary).map_err(to_internal_error)?;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Finds the block closest to a point in time.
+    #[tool(
+        description = "Finds the block whose timestamp is closest to a unix time in seconds, by \
+binary search. Times before genesis give block 0 and times after the head give the head."
+    )]
+    async fn eth_find_block_by_timestamp(
+        &self,
+        params: Parameters<FindBlockByTimestampParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let summary = block::closest_block(&*self.client().await, params.0.timestamp)
+            .await
+            .map_err(to_internal_error)?;
+        let result = serde_json::to_value(summary).map_err(to_internal_error)?;
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Queries event logs.
+    #[tool(
+        description = "Queries event logs by contract address, topics and block range, decoded \
+with an event signature or ABI if given and otherwise with well-known events (ERC-20/721 \
+Transfer, Approval, ...). Example: Transfer events to Bob since block N: {event:'Transfer(address \
+indexed from, address indexed to, uint256 value)', topics:[null, null, 'Bob'], from_block:'N'}. \
+Large ranges are split into chunks automatically. Results are paginated: pass next_cursor back as \
+cursor to continue; it is null when the range is exhausted."
+    )]
+    async fn eth_get_logs(
+        &self,
+        params: Parameters<GetLogsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let params = params.0;
+        let client = self.client().await;
+        let (mut filter, abi) = self
+            .log_filter(&params.address, &params.event, &params.abi, params.topics)
+            .await?;
+
+        filter.to_block =
+            resolve_block_number(&client, params.to_block.as_deref().unwrap_or("latest")).await?;
+        filter.from_block = match &params.from_block {
+            Some(input) => resolve_block_number(&client, input).await?,
+            None => filter.to_block.saturating_sub(logs::DEFAULT_CHUNK_SIZE - 1),
+        };
+        let cursor = match &params.cursor {
+            Some(cursor) => Some(cursor.parse().map_err(to_invalid_params_error)?),
+            None => None,
+        };
+
+        let page = logs::fetch_page(
+            &client,
+            &filter,
+            abi.as_ref(),
+            cursor,
+            params.limit.unwrap_or(logs::DEFAULT_PAGE_LIMIT),
+        )
+        .await
+        .map_err(to_internal_error)?;
+        let result = json!({
+            "from_block": filter.from_block,
+            "to_block": filter.to_block,
+            "count": page.logs.len(),
+            "logs": page.logs,
+            "next_cursor": page.next_cursor.map(|c| c.to_string()),
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Subscribes to new blocks, logs or pending transactions.
+    #[tool(
+        description = "Subscribes to new blocks (new_heads), matching logs (logs) or pending \
+transaction hashes (pending_transactions) over a ws:// or IPC endpoint. Each event is pushed as a \
+logging notification from logger 'subscription' with data {subscription, kind, data}: a block \
+summary, a decoded log or {hash}. Logs take the address, event, abi and topics filters of \
+eth_get_logs. Example: {kind:'logs', address:'0x...', event:'Transfer(address indexed from, \
+address indexed to, uint256 value)'}. Returns the subscription id."
+    )]
+    async fn subscribe(
+        &self,
+        params: Parameters<SubscribeParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let params = params.0;
+        let subscription = match params.kind {
+            SubscriptionKind::NewHeads => Subscription::NewHeads,
+            SubscriptionKind::PendingTransactions => Subscription::PendingTransactions,
+            SubscriptionKind::Logs => {
+                let (filter, abi) = self
+                    .log_filter(&params.address, &params.event, &params.abi, params.topics)
+                    .await?;
+                Subscription::Logs {
+                    filter,
+                    abi: abi.map(Box::new),
+                }
+            }
+        };
+
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel::<SubscriptionEvent>();
+        let id = self
+            .subscriptions
+            .subscribe(self.client().await, subscription, events)
+            .await
+            .map_err(to_internal_error)?;
+        let peer = context.peer.clone();
+        tokio::spawn(async move {
+            while let Some(event) = received.recv().await {
+                let notification = LoggingMessageNotificationParam {
+                    level: LoggingLevel::Info,
+                    logger: Some("subscription".into()),
+                    data: serde_json::to_value(&event).unwrap_or_default(),
+                };
+                // The client is gone; dropping the receiver ends the subscription
+                if peer.notify_logging_message(notification).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = json!({ "id": id, "kind": params.kind });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Stops a subscription.
+    #[tool(description = "Stops a subscription by the id subscribe returned.")]
+    async fn unsubscribe(
+        &self,
+        params: Parameters<UnsubscribeParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let id = params.0.id;
+        if !self.subscriptions.unsubscribe(id).await {
+            return Err(to_invalid_params_error(format!(
+                "No active subscription with id {}",
+                id
+            )));
+        }
+        Ok(CallToolResult::structured(
+            json!({ "id": id, "stopped": true }),
+        ))
+    }
+
+    /// Lists the active subscriptions.
+    #[tool(description = "Lists the active subscriptions with their id, kind and endpoint.")]
+    async fn list_subscriptions(&self) -> Result<CallToolResult, ErrorData> {
+        let subscriptions = self.subscriptions.list().await;
+        Ok(CallToolResult::structured(
+            json!({ "subscriptions": subscriptions }),
+        ))
+    }
+
+    /// Gets the native currency balance for a given address.
+    #[tool(
+        description = "Gets the balance in the native currency of an address or alias as balance_eth, with the currency symbol and network name, at the latest block or at a given block number, hash or tag (safe/finalized/pending). Older blocks need an archive node. Example: {address:'Alice',block:19000000}"
+    )]
     async fn eth_get_balance(
         &self,
         params: Parameters<GetBalanceParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let balance = self
-            .eth_client
-            .get_balance(&params.0.address)
+        let address = self.resolve_address(&params.0.address).await?;
+        let block = block_id(&params.0.block)?;
+        let client = self.client().await;
+        let balance = client
+            .get_balance_at(address, block)
+            .await
+            .map_err(to_state_error)?;
+        let chain = self.connected_chain(&client).await;
+        let mut result = json!({ "balance_eth": self.format_balance(balance, chain.as_ref())? });
+        if let Some(chain) = chain {
+            result["currency"] = json!(chain.currency);
+            self.label_network(&mut result, &chain);
+        }
+        if let Some(block) = params.0.block {
+            result["block"] = json!(block);
+        }
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Gets the native currency balance an address had at a point in time.
+    #[tool(
+        description = "Gets the native currency balance (balance_eth, with the currency symbol and network name) an address or alias had at a unix time in seconds, read at the last block mined at or before that time. Needs an archive node for anything but recent times. Example: {address:'Alice',timestamp:1704067200}"
+    )]
+    async fn eth_get_balance_at_time(
+        &self,
+        params: Parameters<BalanceAtTimeParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let address = self.resolve_address(&params.0.address).await?;
+        let client = self.client().await;
+        let block = block::block_at(&client, params.0.timestamp)
             .await
             .map_err(to_internal_error)?;
-        let result = json!({ "balance_eth": balance });
+        let number = block
+            .number
+            .ok_or_else(|| to_internal_error("The node returned a block without a number"))?;
+        let balance = client
+            .get_balance_at(address, Some(BlockNumber::Number(number.into()).into()))
+            .await
+            .map_err(to_state_error)?;
+        let chain = self.connected_chain(&client).await;
+        let mut result = json!({
+            "address": to_checksum(&address, None),
+            "balance_eth": self.format_balance(balance, chain.as_ref())?,
+            "balance_wei": balance.to_string(),
+            "block_number": number,
+            "block_timestamp": block.timestamp,
+        });
+        if let Some(chain) = chain {
+            result["currency"] = json!(chain.currency);
+            self.label_network(&mut result, &chain);
+        }
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Calls a contract without sending a transaction.
+    #[tool(
+        description = "Calls a contract with eth_call without sending a transaction, at the latest or a given block. Returns the output, decoded for well-known functions (ERC-20/721 balanceOf, symbol, ...), or the decoded revert reason. Example: {to:'0x...',data:'0x70a08231000000000000000000000000...',block:'finalized'}"
+    )]
+    async fn eth_call(
+        &self,
+        params: Parameters<EthCallParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let to = self.resolve_address(&params.0.to).await?;
+        let data = match &params.0.data {
+            Some(data) => hex::decode(data.strip_prefix("0x").unwrap_or(data))
+                .map_err(|e| to_invalid_params_error(format!("Invalid 'data': {}", e)))?,
+            None => Vec::new(),
+        };
+        let mut call = ethers::types::TransactionRequest::new()
+            .to(to)
+            .data(data.clone());
+        if let Some(from) = &params.0.from {
+            call = call.from(self.resolve_address(from).await?);
+        }
+        if let Some(value) = &params.0.value {
+            let value = U256::from_dec_str(value)
+                .map_err(|_| to_invalid_params_error(format!("Invalid 'value': {}", value)))?;
+            call = call.value(value);
+        }
+        let block = block_id(&params.0.block)?;
+
+        let client = self.client().await;
+        let mut result = match client.call(&call.into(), block).await {
+            Ok(output) => json!({
+                "success": true,
+                "output": format!("0x{}", hex::encode(&output)),
+                "decoded": decode::decode_call(&data, Some(&output)),
+            }),
+            Err(e) => match decode::revert_data(&e) {
+                Some(revert) => json!({
+                    "success": false,
+                    "revert_reason": decode::decode_revert(&revert),
+                }),
+                None => return Err(to_state_error(e)),
+            },
+        };
+        if let Some(block) = params.0.block {
+            result["block"] = json!(block);
+        }
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Gets the code deployed at an address.
+    #[tool(
+        description = "Gets the code deployed at an address, at the latest or a given block. Reports whether the address is a contract and the code size in bytes."
+    )]
+    async fn eth_get_code(
+        &self,
+        params: Parameters<GetCodeParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let address = self.resolve_address(&params.0.address).await?;
+        let code = self
+            .client()
+            .await
+            .get_code_at(address, block_id(&params.0.block)?)
+            .await
+            .map_err(to_state_error)?;
+        let result = json!({
+            "address": to_checksum(&address, None),
+            "is_contract": !code.is_empty(),
+            "size": code.len(),
+            "code": format!("0x{}", hex::encode(&code)),
+        });
+        Ok(CallToolResult::structured(result))
+    }
+
+    /// Reads a storage slot of a contract.
+    #[tool(
+        description = "Reads a raw 32-byte storage slot of a contract, at the latest or a given block. The slot is a number (decimal or 0x hex) or a 32-byte key. Example: {address:'0x...',slot:0}"
+    )]
+    async fn eth_get_storage_at(
+        &self,
+        params: Parameters<GetStorageAtParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let address = self.resolve_address(&params.0.address).await?;
+        let slot = parse_slot(&params.0.slot)?;
+        let value = self
+            .client()
+            .await
+            .get_storage_at(address, slot, block_id(&params.0.block)?)
+            .await
+            .map_err(to_state_error)?;
+        let result = json!({
+            "address": to_checksum(&address, None),
+            "slot": format!("{:?}", slot),
+            "value": format!("{:?}", value),
+            "value_decimal": U256::from_big_endian(value.as_bytes()).to_string(),
+        });
         Ok(CallToolResult::structured(result))
     }
 
@@ -428,12 +2122,35 @@ chain_id:31337}"
         &self,
         params: Parameters<SendSignedTxParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let tx_hash = self
-            .eth_client
-            .send_signed_transaction(&params.0.signed_transaction_hex)
+        let raw_tx_hex = &params.0.signed_transaction_hex;
+        let client = self.client().await;
+        let simulate = params.0.simulate.unwrap_or(true);
+        let check_chain = !params.0.allow_chain_mismatch.unwrap_or(false);
+        if simulate || check_chain {
+            let raw = hex::decode(raw_tx_hex.strip_prefix("0x").unwrap_or(raw_tx_hex))
+                .map_err(to_invalid_params_error)?;
+            let tx = simulation::call_from_raw(&raw).map_err(to_invalid_params_error)?;
+            if check_chain {
+                check_tx_chain(&client, tx.chain_id().map(|id| id.as_u64()), true).await?;
+            }
+            if simulate {
+                simulation::simulate(&client, &tx)
+                    .await
+                    .map_err(to_simulation_error)?;
+            }
+        }
+        let tx_hash = client
+            .send_signed_transaction(raw_tx_hex)
             .await
             .map_err(to_internal_error)?;
-        let result = json!({ "transaction_hash": format!("0x{:x}", tx_hash) });
+        // The node accepted the bytes, so they are valid hex
+        if let Ok(raw) = hex::decode(raw_tx_hex.strip_prefix("0x").unwrap_or(raw_tx_hex)) {
+            self.journal_tx(&raw, true, None).await;
+        }
+        let mut result = json!({ "transaction_hash": format!("0x{:x}", tx_hash) });
+        if let Some(chain) = self.connected_chain(&client).await {
+            self.label_tx(&mut result, &chain, tx_hash);
+        }
         Ok(CallToolResult::structured(result))
     }
 
@@ -452,13 +2169,15 @@ chain_id:31337}"
         )
         .map_err(|e| to_invalid_params_error(e.to_string()))?;
 
-        let tx_info = self
-            .eth_client
+        let client = self.client().await;
+        let tx_info = client
             .get_transaction_info(tx_hash)
             .await
             .map_err(to_internal_error)?;
 
-        let result = serde_json::to_value(tx_info).map_err(|e| to_internal_error(e.to_string()))?;
+        let mut result =
+            serde_json::to_value(tx_info).map_err(|e| to_internal_error(e.to_string()))?;
+        self.label_active_network(&mut result, &client).await;
         Ok(CallToolResult::structured(result))
     }
 
@@ -473,8 +2192,6 @@ and auto-resolved. Examples: {from:'Alice',to:'Bob',value_wei:'10000000000000000
         &self,
         params: Parameters<TransferEthParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let mut wallet = self.wallet.lock().await;
-
         // Resolve amount to wei. Prefer value_wei, fallback to value_eth.
         let value_wei = if let Some(w) = params.0.value_wei {
             match w {
@@ -513,53 +2230,117 @@ and auto-resolved. Examples: {from:'Alice',to:'Bob',value_wei:'10000000000000000
 
         // Support aliases for the `to` field (case-insensitive) before falling
         // back to strict hex parsing.
-        let to_address = if let Some(addr) = wallet.resolve_alias_case_insensitive(&params.0.to) {
-            addr
-        } else {
-            Address::from_str(&params.0.to).map_err(|_| {
-                to_invalid_params_error(format!("Invalid 'to' address: {}", params.0.to))
-            })?
+        let (to_address, from_address) = {
+            let wallet = self.wallet.lock().await;
+            let to_address = if let Some(addr) = wallet.resolve_alias_case_insensitive(&params.0.to)
+            {
+                addr
+            } else {
+                Address::from_str(&params.0.to).map_err(|_| {
+                    to_invalid_params_error(format!("Invalid 'to' address: {}", params.0.to))
+                })?
+            };
+            let (_, from_address) = wallet.get_account(&params.0.from).ok_or_else(|| {
+                to_internal_error(WalletError::SignerNotFound(params.0.from.clone()))
+            })?;
+            (to_address, from_address)
         };
         // value_wei already parsed above
 
-        // Create the transaction request
-        let (from_account, _) = wallet
-            .get_account(&params.0.from)
-            .ok_or_else(|| to_internal_error(WalletError::SignerNotFound(params.0.from.clone())))?;
+        let client = self.client().await;
 
-        // Resolve chain id (from param or network)
+        // Resolve chain id (from param, config or network)
         let chain_id = if let Some(id) = params.0.chain_id {
             id
         } else {
-            self.eth_client
-                .get_chain_id()
+            client
+                .expected_chain_id()
                 .await
                 .map_err(to_internal_error)?
         };
+        if !params.0.allow_chain_mismatch.unwrap_or(false) {
+            check_tx_chain(&client, Some(chain_id), true).await?;
+        }
+        self.chains
+            .check_eip1559(chain_id)
+            .map_err(to_invalid_params_error)?;
 
-        let tx_request = crate::models::Eip1559TransactionRequest {
-            to: Some(to_address),
-            value: value_wei,
-            chain_id,
-            nonce: from_account.nonce.into(),
-            ..Default::default()
+        // Check the transfer before a nonce is reserved; a recipient contract
+        // may need more than the default gas limit
+        let gas = if params.0.simulate.unwrap_or(true) {
+            let tx = TypedTransaction::Eip1559(
+                ethers::types::Eip1559TransactionRequest::new()
+                    .from(from_address)
+                    .to(to_address)
+                    .value(value_wei)
+                    .chain_id(chain_id),
+            );
+            let simulation = simulation::simulate(&client, &tx)
+                .await
+                .map_err(to_simulation_error)?;
+            simulation
+                .gas_estimate
+                .max(crate::models::Eip1559TransactionRequest::default().gas)
+        } else {
+            crate::models::Eip1559TransactionRequest::default().gas
         };
 
-        // Sign the transaction
-        let signed_tx = wallet
-            .sign_transaction(&tx_request, &params.0.from)
+        // Catch up with transactions sent from elsewhere, then reserve a nonce
+        self.nonces
+            .sync(&self.wallet, &client, chain_id, from_address, false)
             .await
             .map_err(to_internal_error)?;
+        let (nonce, signed_tx) = {
+            let mut wallet = self.wallet.lock().await;
+            let nonce = self
+                .nonces
+                .reserve(&mut wallet, chain_id, from_address)
+                .await
+                .map_err(to_internal_error)?;
+            let tx_request = crate::models::Eip1559TransactionRequest {
+                to: Some(to_address),
+                value: value_wei,
+                chain_id,
+                nonce: nonce.into(),
+                gas,
+                ..Default::default()
+            };
+            match wallet.sign_reserved(&tx_request, &params.0.from).await {
+                Ok(signed_tx) => (nonce, signed_tx),
+                Err(e) => {
+                    self.nonces
+                        .release(&mut wallet, chain_id, from_address, nonce)
+                        .await
+                        .map_err(to_internal_error)?;
+                    return Err(to_internal_error(e));
+                }
+            }
+        };
 
-        // Send the transaction
-        let raw_tx_hex = format!("0x{}", hex::encode(signed_tx.raw_transaction));
-        let tx_hash = self
-            .eth_client
-            .send_signed_transaction(&raw_tx_hex)
-            .await
-            .map_err(to_internal_error)?;
+        // Send the transaction; a nonce the node rejected goes back to the
+        // pool, but one that may have reached it stays used
+        let raw_tx_hex = format!("0x{}", hex::encode(&signed_tx.raw_transaction));
+        let tx_hash = match client.send_signed_transaction(&raw_tx_hex).await {
+            Ok(tx_hash) => tx_hash,
+            Err(e) => {
+                if is_rejected_send(&e) {
+                    let mut wallet = self.wallet.lock().await;
+                    self.nonces
+                        .release(&mut wallet, chain_id, from_address, nonce)
+                        .await
+                        .map_err(to_internal_error)?;
+                }
+                return Err(to_internal_error(e));
+            }
+        };
+        self.journal_tx(&signed_tx.raw_transaction, true, None)
+            .await;
 
-        let result = json!({ "transaction_hash": format!("0x{:x}", tx_hash) });
+        let mut result = json!({
+            "transaction_hash": format!("0x{:x}", tx_hash),
+            "nonce": nonce,
+        });
+        self.label_tx(&mut result, &self.chains.describe(chain_id), tx_hash);
         Ok(CallToolResult::structured(result))
     }
 }
@@ -603,6 +2384,32 @@ impl From<crate::models::SignedTransaction> for JsonSignedTransaction {
     }
 }
 
+/// Resolves a block number, hash or tag to a block number.
+async fn resolve_block_number(client: &EthClient, input: &str) -> Result<u64, ErrorData> {
+    let id = block::parse_block_id(input).map_err(to_invalid_params_error)?;
+    block::resolve_number(client, id)
+        .await
+        .map_err(to_internal_error)
+}
+
+/// Parses an ABI given as a JSON ABI array or a list of human-readable signatures.
+fn parse_abi_param(value: &Value) -> Result<ethers::abi::Abi, ErrorData> {
+    let signatures: Option<Vec<&str>> = value
+        .as_array()
+        .and_then(|items| items.iter().map(Value::as_str).collect());
+    match signatures {
+        Some(signatures) => decode::parse_signatures(&signatures).map_err(to_invalid_params_error),
+        None => serde_json::from_value(value.clone())
+            .map_err(|e| to_invalid_params_error(format!("Invalid ABI: {}", e))),
+    }
+}
+
+/// Parses a transaction hash with or without the `0x` prefix.
+fn parse_tx_hash(input: &str) -> Result<H256, ErrorData> {
+    H256::from_str(input.strip_prefix("0x").unwrap_or(input))
+        .map_err(|e| to_invalid_params_error(e.to_string()))
+}
+
 fn to_internal_error<E: std::fmt::Display>(e: E) -> ErrorData {
     ErrorData::internal_error(e.to_string(), None)
 }
@@ -610,3 +2417,93 @@ fn to_internal_error<E: std::fmt::Display>(e: E) -> ErrorData {
 fn to_invalid_params_error<E: std::fmt::Display>(e: E) -> ErrorData {
     ErrorData::invalid_params(e.to_string(), None)
 }
+
+/// Refuses a transaction for another chain than the wallet's, which could
+/// be replayed there, or one without a chain id (pre-EIP-155), which could be
+/// replayed on any chain. Before broadcasting, the node must also serve the
+/// configured chain.
+async fn check_tx_chain(
+    client: &EthClient,
+    chain_id: Option<u64>,
+    broadcast: bool,
+) -> Result<(), ErrorData> {
+    const OPT_IN: &str = "Pass allow_chain_mismatch: true to proceed anyway.";
+    let expected = if broadcast {
+        client.verify_chain_id().await
+    } else {
+        client.expected_chain_id().await
+    };
+    let expected = expected.map_err(|e| match e {
+        WalletError::ChainIdMismatch { .. } => {
+            to_invalid_params_error(format!("{}. {}", e, OPT_IN))
+        }
+        e => to_internal_error(format!(
+            "Cannot determine the wallet's chain id: {}. Configure a chain id or pass \
+allow_chain_mismatch: true.",
+            e
+        )),
+    })?;
+    match chain_id {
+        Some(actual) if actual == expected => Ok(()),
+        Some(actual) => Err(to_invalid_params_error(format!(
+            "The transaction is for chain {}, but the wallet is on chain {}. {}",
+            actual, expected, OPT_IN
+        ))),
+        None => Err(to_invalid_params_error(format!(
+            "The transaction has no chain id and could be replayed on any chain. {}",
+            OPT_IN
+        ))),
+    }
+}
+
+/// Maps a failed read of chain state; a node that pruned the state of the
+/// requested block gets a hint to use an archive node.
+fn to_state_error(e: WalletError) -> ErrorData {
+    let message = e.to_string();
+    let lower = message.to_ascii_lowercase();
+    let pruned = [
+        "missing trie node",
+        "historical state",
+        "state is not available",
+        "state not available",
+        "pruned",
+    ];
+    if pruned.iter().any(|hint| lower.contains(hint)) {
+        to_internal_error(format!(
+            "{}. The node no longer has the state of that block; historical queries need an \
+archive node.",
+            message
+        ))
+    } else {
+        to_internal_error(message)
+    }
+}
+
+/// Parses a storage slot given as a number (decimal or `0x` hex) or a
+/// 32-byte hex key.
+fn parse_slot(input: &str) -> Result<H256, ErrorData> {
+    let input = input.trim();
+    let invalid = || to_invalid_params_error(format!("Invalid storage slot: {}", input));
+    let number = match input.strip_prefix("0x") {
+        Some(hex) if hex.len() == 64 => return H256::from_str(hex).map_err(|_| invalid()),
+        Some(hex) => U256::from_str_radix(hex, 16).map_err(|_| invalid())?,
+        None => U256::from_dec_str(input).map_err(|_| invalid())?,
+    };
+    let mut slot = [0u8; 32];
+    number.to_big_endian(&mut slot);
+    Ok(H256(slot))
+}
+
+/// Maps a failed pre-send simulation; a revert carries its decoded reason as data.
+fn to_simulation_error(e: WalletError) -> ErrorData {
+    match &e {
+        WalletError::WouldRevert(reason) => {
+            ErrorData::invalid_params(e.to_string(), serde_json::to_value(reason).ok())
+        }
+        WalletError::GasLimitTooLow { .. } => to_invalid_params_error(e),
+        _ => to_internal_error(format!(
+            "Simulation failed: {}. Pass simulate: false to skip it.",
+            e
+        )),
+    }
+}
@@ -0,0 +1,200 @@
+//! Shamir secret sharing for private keys.
+//!
+//! A 32-byte private key is split byte-wise over GF(256) into `n` shares, any
+//! `m` of which rebuild the key. Shares travel as text of the form
+//!
+//! ```text
+//! mcpw-sss1:<threshold>:<index>:<address>:<share hex>:<checksum>
+//! ```
+//!
+//! where `address` identifies the account the key belongs to and `checksum` is
+//! the first four bytes of SHA-256 over the rest of the line, to catch typos.
+
+use crate::error::{Result, WalletError};
+use ethers::types::Address;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+const SHARE_PREFIX: &str = "mcpw-sss1";
+
+/// One share of a split private key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShare {
+    /// Number of shares needed to rebuild the key.
+    pub threshold: u8,
+    /// The x coordinate of this share (1-255).
+    pub index: u8,
+    /// Address of the account whose key was split.
+    pub address: Address,
+    /// The share bytes, one per key byte; wiped when dropped.
+    pub data: Zeroizing<Vec<u8>>,
+}
+
+impl KeyShare {
+    fn body(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            SHARE_PREFIX,
+            self.threshold,
+            self.index,
+            hex::encode(self.address),
+            hex::encode(self.data.as_slice())
+        )
+    }
+}
+
+impl fmt::Display for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = self.body();
+        write!(f, "{}:{}", body, checksum(&body))
+    }
+}
+
+impl FromStr for KeyShare {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let invalid = |reason: &str| WalletError::InvalidKeyShares(format!("{}: {}", reason, s));
+
+        let (body, check) = s
+            .rsplit_once(':')
+            .ok_or_else(|| invalid("malformed share"))?;
+        if checksum(body) != check.to_ascii_lowercase() {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        let parts: Vec<&str> = body.split(':').collect();
+        if parts.len() != 5 || parts[0] != SHARE_PREFIX {
+            return Err(invalid("unsupported share format"));
+        }
+        let threshold = parts[1]
+            .parse::<u8>()
+            .map_err(|_| invalid("invalid threshold"))?;
+        let index = parts[2]
+            .parse::<u8>()
+            .map_err(|_| invalid("invalid index"))?;
+        if index == 0 {
+            return Err(invalid("invalid index"));
+        }
+        let address = parts[3]
+            .parse::<Address>()
+            .map_err(|_| invalid("invalid address"))?;
+        let data =
+            Zeroizing::new(hex::decode(parts[4]).map_err(|_| invalid("invalid share data"))?);
+
+        Ok(Self {
+            threshold,
+            index,
+            address,
+            data,
+        })
+    }
+}
+
+fn checksum(body: &str) -> String {
+    hex::encode(&Sha256::digest(body.as_bytes())[..4])
+}
+
+/// Splits `secret` into `shares` shares, any `threshold` of which recover it.
+///
+/// Returns `(index, data)` pairs with indexes `1..=shares`.
+pub fn split_secret(
+    secret: &[u8],
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<(u8, Zeroizing<Vec<u8>>)>> {
+    if threshold < 2 || shares < threshold {
+        return Err(WalletError::InvalidKeyShares(format!(
+            "threshold must be at least 2 and at most the number of shares (got {} of {})",
+            threshold, shares
+        )));
+    }
+
+    let mut rng = rand::thread_rng();
+    // Allocated at full size up front, so no partial copy is left behind
+    let mut out: Vec<(u8, Zeroizing<Vec<u8>>)> = (1..=shares)
+        .map(|x| (x, Zeroizing::new(Vec::with_capacity(secret.len()))))
+        .collect();
+    // The constant term is the secret byte, so wipe them when done
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for &byte in secret {
+        // f(x) = byte + c1*x + ... + c(m-1)*x^(m-1)
+        coefficients[0] = byte;
+        rng.fill_bytes(&mut coefficients[1..]);
+        for (x, data) in out.iter_mut() {
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, &c| gf_mul(acc, *x) ^ c);
+            data.push(y);
+        }
+    }
+    Ok(out)
+}
+
+/// Recovers a secret from `(index, data)` shares by interpolating at `x = 0`.
+pub fn combine_shares(shares: &[(u8, Zeroizing<Vec<u8>>)]) -> Result<Zeroizing<Vec<u8>>> {
+    let len = shares
+        .first()
+        .map(|(_, data)| data.len())
+        .ok_or_else(|| WalletError::InvalidKeyShares("no shares provided".to_string()))?;
+    for (i, (x, data)) in shares.iter().enumerate() {
+        if *x == 0 || data.len() != len {
+            return Err(WalletError::InvalidKeyShares(
+                "shares have inconsistent lengths or indexes".to_string(),
+            ));
+        }
+        if shares[..i].iter().any(|(other, _)| other == x) {
+            return Err(WalletError::InvalidKeyShares(format!(
+                "duplicate share index {}",
+                x
+            )));
+        }
+    }
+
+    let mut secret = Zeroizing::new(vec![0u8; len]);
+    for (i, (xi, data)) in shares.iter().enumerate() {
+        // Lagrange basis polynomial l_i(0) = prod_{j != i} x_j / (x_j - x_i)
+        let mut basis = 1u8;
+        for (j, (xj, _)) in shares.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_div(*xj, xj ^ xi));
+            }
+        }
+        for (out, &y) in secret.iter_mut().zip(data.iter()) {
+            *out ^= gf_mul(y, basis);
+        }
+    }
+    Ok(secret)
+}
+
+/// Multiplies in GF(2^8) with the AES reduction polynomial.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// Divides in GF(2^8); `b` must be non-zero.
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the multiplicative inverse of b
+    let mut inverse = 1u8;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+    gf_mul(a, inverse)
+}
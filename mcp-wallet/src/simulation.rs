@@ -0,0 +1,306 @@
+//! Simulating transactions before they are signed or sent.
+//!
+//! A transaction is run with `eth_call` and `eth_estimateGas` against the
+//! pending block, so a revert is reported with its decoded reason before any
+//! gas is spent. The gas estimate is also checked against the transaction's
+//! gas limit, which would otherwise only fail on chain.
+//!
+//! [`preview`] goes further and reports what a transaction would change. It
+//! runs the transaction in an embedded EVM on top of a block, with the state
+//! fetched through the node's standard methods, see [`crate::evm`]. Nodes
+//! that serve `debug_traceCall` can run it themselves instead, which takes two
+//! requests rather than one per touched account and slot: the `callTracer`
+//! yields the events and the outcome, the `prestateTracer` in diff mode the
+//! balance and storage changes.
+
+use crate::{
+    decode::{self, DecodedLog, RevertReason},
+    error::{Result, WalletError},
+    eth_client::EthClient,
+    evm,
+    trace::{CallFrame, Tracer},
+};
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes, DiffMode, Log,
+    H256, I256, U256,
+};
+use ethers::utils::{keccak256, rlp::Rlp, to_checksum};
+use serde::Serialize;
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The result of a successful simulation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Simulation {
+    /// Gas the transaction is expected to use.
+    pub gas_estimate: U256,
+    /// Data returned by the call.
+    pub output: Bytes,
+}
+
+/// Simulates `tx` (with `from` set) on the pending block.
+///
+/// Fails with [`WalletError::WouldRevert`] if the call reverts and with
+/// [`WalletError::GasLimitTooLow`] if `tx` has a gas limit below the estimate.
+pub async fn simulate(client: &EthClient, tx: &TypedTransaction) -> Result<Simulation> {
+    let pending = Some(BlockId::Number(BlockNumber::Pending));
+    let output = client
+        .call(&for_simulation(tx, true), pending)
+        .await
+        .map_err(reverted_or_unchanged)?;
+
+    // Estimate without the limit, so a too-low limit shows up as such
+    let gas_estimate = client
+        .estimate_gas(&for_simulation(tx, false), pending)
+        .await
+        .map_err(reverted_or_unchanged)?;
+    if let Some(limit) = tx.gas() {
+        if gas_estimate > *limit {
+            return Err(WalletError::GasLimitTooLow {
+                needed: gas_estimate,
+                limit: *limit,
+            });
+        }
+    }
+    Ok(Simulation {
+        gas_estimate,
+        output,
+    })
+}
+
+/// Decodes a signed raw transaction into a call that can be simulated.
+pub fn call_from_raw(raw: &[u8]) -> Result<TypedTransaction> {
+    let (mut tx, signature) = TypedTransaction::decode_signed(&Rlp::new(raw))
+        .map_err(|e| WalletError::WalletError(format!("Invalid signed transaction: {}", e)))?;
+    let from = signature.recover(tx.sighash())?;
+    tx.set_from(from);
+    Ok(tx)
+}
+
+/// Where a [`preview`] ran the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Engine {
+    /// The node, with `debug_traceCall`.
+    Node,
+    /// The embedded EVM, on state fetched from the node.
+    Local,
+}
+
+/// What a transaction would change, as reported by [`preview`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationReport {
+    /// Where the transaction was run.
+    pub engine: Engine,
+    /// Whether the transaction would execute successfully.
+    pub success: bool,
+    /// Why it would revert.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<RevertReason>,
+    /// Why execution would stop otherwise, e.g. running out of gas.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Gas the transaction would use, in decimal.
+    pub gas_used: String,
+    /// ETH balance changes, including fees paid to the block producer.
+    pub balance_changes: Vec<BalanceChange>,
+    /// ERC-20 balance changes, summed from `Transfer` events.
+    pub token_changes: Vec<TokenChange>,
+    /// Emitted events in execution order, decoded if known.
+    pub events: Vec<DecodedLog>,
+    /// Storage slots the transaction would write.
+    pub storage_changes: Vec<StorageChange>,
+}
+
+/// The ETH balance change of one account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceChange {
+    /// The account, checksummed.
+    pub address: String,
+    /// Balance before, in wei.
+    pub before: String,
+    /// Balance after, in wei.
+    pub after: String,
+    /// Signed change, in wei.
+    pub delta: String,
+}
+
+/// The ERC-20 balance change of one holder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenChange {
+    /// The token contract, checksummed.
+    pub token: String,
+    /// The holder, checksummed.
+    pub address: String,
+    /// Signed change in the token's smallest unit.
+    pub delta: String,
+}
+
+/// A storage slot written by the transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageChange {
+    /// The contract, checksummed.
+    pub address: String,
+    /// The storage slot.
+    pub slot: H256,
+    /// The value before.
+    pub before: H256,
+    /// The value after.
+    pub after: H256,
+}
+
+/// Previews what `tx` (with `from` set) would do on top of `block`.
+///
+/// With `node_trace` the node is asked to run the transaction with
+/// `debug_traceCall` first; if it cannot, or without `node_trace`, it runs
+/// in the embedded EVM. Older blocks need a node that keeps their state.
+pub async fn preview(
+    client: &Arc<EthClient>,
+    tx: &TypedTransaction,
+    block: BlockId,
+    node_trace: bool,
+) -> Result<SimulationReport> {
+    let tx = for_simulation(tx, true);
+    if node_trace {
+        match preview_on_node(client, &tx, block).await {
+            Ok(report) => return Ok(report),
+            Err(e) => log::debug!("debug_traceCall failed, simulating locally: {}", e),
+        }
+    }
+
+    let run = evm::execute(client.clone(), &tx, block).await?;
+    let revert_reason =
+        (!run.success && run.halt_reason.is_none()).then(|| decode::decode_revert(&run.output));
+    Ok(SimulationReport {
+        engine: Engine::Local,
+        success: run.success,
+        revert_reason,
+        error: run.halt_reason,
+        gas_used: run.gas_used.to_string(),
+        balance_changes: balance_changes(&run.diff),
+        token_changes: token_changes(&run.logs),
+        events: run.logs.iter().map(decode::decode_log).collect(),
+        storage_changes: storage_changes(&run.diff),
+    })
+}
+
+/// Runs the preview on the node with its built-in tracers.
+async fn preview_on_node(
+    client: &EthClient,
+    tx: &TypedTransaction,
+    block: BlockId,
+) -> Result<SimulationReport> {
+    let calls = client
+        .trace_call(tx, block, Tracer::Call.name(), Tracer::Call.config(false))
+        .await?;
+    let diff = client
+        .trace_call(
+            tx,
+            block,
+            Tracer::Prestate.name(),
+            Tracer::Prestate.config(true),
+        )
+        .await?;
+    let calls: CallFrame = serde_json::from_value(calls)?;
+    let diff: DiffMode = serde_json::from_value(diff)?;
+
+    let logs = calls.flat_logs();
+    let revert_reason = calls
+        .error
+        .as_ref()
+        .map(|_| decode::decode_revert(calls.output.as_deref().unwrap_or_default()));
+    Ok(SimulationReport {
+        engine: Engine::Node,
+        success: calls.error.is_none(),
+        revert_reason,
+        error: calls.error.filter(|e| e != "execution reverted"),
+        gas_used: calls.gas_used.to_string(),
+        balance_changes: balance_changes(&diff),
+        token_changes: token_changes(&logs),
+        events: logs.iter().map(decode::decode_log).collect(),
+        storage_changes: storage_changes(&diff),
+    })
+}
+
+fn balance_changes(diff: &DiffMode) -> Vec<BalanceChange> {
+    diff.post
+        .iter()
+        .filter_map(|(address, post)| {
+            let after = post.balance?;
+            let before = diff
+                .pre
+                .get(address)
+                .and_then(|pre| pre.balance)
+                .unwrap_or_default();
+            (after != before).then(|| BalanceChange {
+                address: to_checksum(address, None),
+                before: before.to_string(),
+                after: after.to_string(),
+                delta: (I256::from_raw(after) - I256::from_raw(before)).to_string(),
+            })
+        })
+        .collect()
+}
+
+fn token_changes(logs: &[Log]) -> Vec<TokenChange> {
+    let transfer = H256::from(keccak256("Transfer(address,address,uint256)"));
+    let mut deltas: BTreeMap<(Address, Address), I256> = BTreeMap::new();
+    // ERC-721 transfers have the token id as a fourth topic and no data
+    let erc20 = logs
+        .iter()
+        .filter(|l| l.topics.len() == 3 && l.topics[0] == transfer && l.data.len() == 32);
+    for log in erc20 {
+        let amount = I256::from_raw(U256::from_big_endian(&log.data));
+        let from = Address::from(log.topics[1]);
+        let to = Address::from(log.topics[2]);
+        *deltas.entry((log.address, from)).or_default() -= amount;
+        *deltas.entry((log.address, to)).or_default() += amount;
+    }
+    deltas
+        .into_iter()
+        .filter(|(_, delta)| !delta.is_zero())
+        .map(|((token, holder), delta)| TokenChange {
+            token: to_checksum(&token, None),
+            address: to_checksum(&holder, None),
+            delta: delta.to_string(),
+        })
+        .collect()
+}
+
+fn storage_changes(diff: &DiffMode) -> Vec<StorageChange> {
+    let mut changes = Vec::new();
+    for (address, post) in &diff.post {
+        let pre = diff.pre.get(address).and_then(|pre| pre.storage.as_ref());
+        for (slot, after) in post.storage.iter().flatten() {
+            changes.push(StorageChange {
+                address: to_checksum(address, None),
+                slot: *slot,
+                before: pre.and_then(|s| s.get(slot)).copied().unwrap_or_default(),
+                after: *after,
+            });
+        }
+    }
+    changes
+}
+
+fn reverted_or_unchanged(error: WalletError) -> WalletError {
+    match decode::revert_data(&error) {
+        Some(data) => WalletError::WouldRevert(decode::decode_revert(&data)),
+        None => error,
+    }
+}
+
+/// Drops the nonce, which may be ahead of the pending state for queued
+/// transactions, and unless `keep_gas` the gas limit.
+fn for_simulation(tx: &TypedTransaction, keep_gas: bool) -> TypedTransaction {
+    let mut tx = tx.clone();
+    let (nonce, gas) = match &mut tx {
+        TypedTransaction::Legacy(inner) => (&mut inner.nonce, &mut inner.gas),
+        TypedTransaction::Eip2930(inner) => (&mut inner.tx.nonce, &mut inner.tx.gas),
+        TypedTransaction::Eip1559(inner) => (&mut inner.nonce, &mut inner.gas),
+    };
+    *nonce = None;
+    if !keep_gas {
+        *gas = None;
+    }
+    tx
+}
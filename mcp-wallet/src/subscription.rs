@@ -0,0 +1,252 @@
+//! Live chain event subscriptions.
+//!
+//! A subscription runs `eth_subscribe` over a WebSocket endpoint in a
+//! background task and hands every event it receives to a channel, from which
+//! the service forwards it to the MCP client as a notification. Dropping the
+//! receiving end, unsubscribing or clearing the [`Subscriptions`] ends the task
+//! and sends `eth_unsubscribe` to the node.
+
+use crate::{
+    block,
+    error::{Result, WalletError},
+    eth_client::EthClient,
+    failover::FailoverClient,
+    logs::{self, LogFilter},
+};
+use ethers::{abi::Abi, providers::SubscriptionStream, utils::to_checksum};
+use futures::StreamExt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::{
+    sync::{mpsc, oneshot, Mutex},
+    task::JoinHandle,
+};
+
+/// The kinds of events that can be subscribed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionKind {
+    /// Every new block header (`newHeads`).
+    NewHeads,
+    /// Logs matching an address and topic filter as they are mined.
+    Logs,
+    /// Hashes of transactions entering the node's mempool
+    /// (`newPendingTransactions`).
+    PendingTransactions,
+}
+
+/// What a subscription listens for.
+#[derive(Debug, Clone)]
+pub enum Subscription {
+    /// New block headers, reported as [`block::BlockSummary`].
+    NewHeads,
+    /// Logs matching `filter`, whose block range is ignored, decoded with
+    /// `abi` if given and otherwise with the well-known events.
+    Logs {
+        /// The emitting contracts and topics to match.
+        filter: LogFilter,
+        /// Extra events to decode with.
+        abi: Option<Box<Abi>>,
+    },
+    /// Pending transaction hashes.
+    PendingTransactions,
+}
+
+impl Subscription {
+    /// The kind of events the subscription yields.
+    pub fn kind(&self) -> SubscriptionKind {
+        match self {
+            Subscription::NewHeads => SubscriptionKind::NewHeads,
+            Subscription::Logs { .. } => SubscriptionKind::Logs,
+            Subscription::PendingTransactions => SubscriptionKind::PendingTransactions,
+        }
+    }
+}
+
+/// One event delivered by a subscription.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubscriptionEvent {
+    /// The id returned when subscribing.
+    pub subscription: u64,
+    /// The kind of event.
+    pub kind: SubscriptionKind,
+    /// A block summary, a decoded log or `{hash}` of a pending transaction.
+    pub data: Value,
+}
+
+/// An active subscription, as listed by [`Subscriptions::list`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubscriptionInfo {
+    /// The subscription id.
+    pub id: u64,
+    /// The kind of events it yields.
+    pub kind: SubscriptionKind,
+    /// The endpoint it listens on.
+    pub rpc_url: String,
+    /// For logs, the contracts matched, checksummed; empty matches any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Active {
+    next_id: u64,
+    tasks: BTreeMap<u64, (SubscriptionInfo, JoinHandle<()>)>,
+}
+
+/// The subscriptions of one server, each running in its own task.
+#[derive(Debug, Clone, Default)]
+pub struct Subscriptions {
+    active: Arc<Mutex<Active>>,
+}
+
+impl Subscriptions {
+    /// Creates an empty set of subscriptions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts `subscription` on `client` and sends its events to `events`.
+    ///
+    /// Returns the subscription id once the node accepted it. Fails if the
+    /// endpoint cannot push notifications or the node rejects the request.
+    pub async fn subscribe(
+        &self,
+        client: Arc<EthClient>,
+        subscription: Subscription,
+        events: mpsc::UnboundedSender<SubscriptionEvent>,
+    ) -> Result<u64> {
+        let id = {
+            let mut active = self.active.lock().await;
+            active.next_id += 1;
+            active.next_id
+        };
+        let info = SubscriptionInfo {
+            id,
+            kind: subscription.kind(),
+            rpc_url: client.url().to_string(),
+            addresses: match &subscription {
+                Subscription::Logs { filter, .. } => filter
+                    .addresses
+                    .iter()
+                    .map(|a| to_checksum(a, None))
+                    .collect(),
+                _ => Vec::new(),
+            },
+        };
+
+        let (ready, started) = oneshot::channel();
+        let task = tokio::spawn(run(client, subscription, id, events, ready));
+        match started.await {
+            Ok(Ok(())) => {
+                log::info!("Started {:?} subscription {}", info.kind, id);
+                self.active.lock().await.tasks.insert(id, (info, task));
+                Ok(id)
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(WalletError::WalletError(
+                "Subscription task ended before it started".into(),
+            )),
+        }
+    }
+
+    /// Stops subscription `id`; returns whether it was active.
+    pub async fn unsubscribe(&self, id: u64) -> bool {
+        match self.active.lock().await.tasks.remove(&id) {
+            Some((_, task)) => {
+                task.abort();
+                log::info!("Stopped subscription {}", id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops all subscriptions and returns how many were active.
+    pub async fn clear(&self) -> usize {
+        let tasks = std::mem::take(&mut self.active.lock().await.tasks);
+        for (_, task) in tasks.values() {
+            task.abort();
+        }
+        tasks.len()
+    }
+
+    /// Lists the running subscriptions, forgetting those whose stream ended.
+    pub async fn list(&self) -> Vec<SubscriptionInfo> {
+        let mut active = self.active.lock().await;
+        active.tasks.retain(|_, (_, task)| !task.is_finished());
+        active
+            .tasks
+            .values()
+            .map(|(info, _)| info.clone())
+            .collect()
+    }
+}
+
+type Ready = oneshot::Sender<Result<()>>;
+
+async fn run(
+    client: Arc<EthClient>,
+    subscription: Subscription,
+    id: u64,
+    events: mpsc::UnboundedSender<SubscriptionEvent>,
+    ready: Ready,
+) {
+    let kind = subscription.kind();
+    let send = |data: Value| {
+        events
+            .send(SubscriptionEvent {
+                subscription: id,
+                kind,
+                data,
+            })
+            .is_ok()
+    };
+    match &subscription {
+        Subscription::NewHeads => {
+            let stream = client.subscribe_blocks().await;
+            forward(stream, ready, |header| {
+                send(serde_json::to_value(block::summary(&header)).unwrap_or_default())
+            })
+            .await
+        }
+        Subscription::Logs { filter, abi } => {
+            let stream = client.subscribe_logs(&filter.without_range()).await;
+            forward(stream, ready, |log| {
+                let entry = logs::entry(&log, abi.as_deref());
+                send(serde_json::to_value(entry).unwrap_or_default())
+            })
+            .await
+        }
+        Subscription::PendingTransactions => {
+            let stream = client.subscribe_pending_txs().await;
+            forward(stream, ready, |hash| send(json!({ "hash": hash }))).await
+        }
+    }
+    log::info!("Subscription {} ended", id);
+}
+
+/// Reports whether the subscription started, then passes each item to `send`
+/// until the stream ends or `send` returns false.
+async fn forward<R: DeserializeOwned + Unpin>(
+    stream: Result<SubscriptionStream<'_, FailoverClient, R>>,
+    ready: Ready,
+    mut send: impl FnMut(R) -> bool,
+) {
+    let mut stream = match stream {
+        Ok(stream) => {
+            let _ = ready.send(Ok(()));
+            stream
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+    while let Some(item) = stream.next().await {
+        if !send(item) {
+            break;
+        }
+    }
+}
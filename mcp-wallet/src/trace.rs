@@ -0,0 +1,238 @@
+//! Tracing transactions and calls with the node's built-in tracers.
+//!
+//! `debug_traceTransaction` and `debug_traceCall` are served by geth, anvil,
+//! reth and other nodes with the `debug` namespace enabled. The `callTracer`
+//! result is turned into a compact tree in which every frame's input, output,
+//! revert data and logs are decoded with the well-known signatures from
+//! [`crate::decode`]; the `prestateTracer` result is returned as the node
+//! sends it.
+
+use crate::{
+    decode::{self, DecodedCall, DecodedLog, RevertReason},
+    error::Result,
+    eth_client::EthClient,
+};
+use ethers::{
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, Log, H256, U256, U64,
+    },
+    utils::to_checksum,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A geth built-in tracer.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Tracer {
+    /// The call tree with logs (`callTracer`).
+    #[default]
+    Call,
+    /// The state the transaction read, or with `diff_mode` what it changed
+    /// (`prestateTracer`).
+    Prestate,
+}
+
+impl Tracer {
+    /// The tracer name the node expects.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Call => "callTracer",
+            Self::Prestate => "prestateTracer",
+        }
+    }
+
+    /// The `tracerConfig`; `diff_mode` only applies to the prestate tracer.
+    pub fn config(self, diff_mode: bool) -> Value {
+        match self {
+            Self::Call => json!({ "withLog": true }),
+            Self::Prestate => json!({ "diffMode": diff_mode }),
+        }
+    }
+}
+
+/// A `callTracer` frame as the node returns it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CallFrame {
+    #[serde(default, rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub from: Address,
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub value: Option<U256>,
+    #[serde(default)]
+    pub gas_used: U256,
+    #[serde(default)]
+    pub input: Bytes,
+    #[serde(default)]
+    pub output: Option<Bytes>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+    #[serde(default)]
+    pub logs: Vec<CallLog>,
+}
+
+/// A log emitted in a `callTracer` frame.
+#[derive(Debug, Deserialize)]
+pub(crate) struct CallLog {
+    pub address: Address,
+    #[serde(default)]
+    pub topics: Vec<H256>,
+    #[serde(default)]
+    pub data: Bytes,
+    /// Number of subcalls the frame made before the log was emitted.
+    #[serde(default)]
+    pub position: Option<U64>,
+}
+
+impl CallLog {
+    fn to_log(&self, log_index: Option<usize>) -> Log {
+        Log {
+            address: self.address,
+            topics: self.topics.clone(),
+            data: self.data.clone(),
+            log_index: log_index.map(Into::into),
+            ..Default::default()
+        }
+    }
+}
+
+impl CallFrame {
+    /// All logs of the call tree in the order they were emitted.
+    pub fn flat_logs(&self) -> Vec<Log> {
+        let mut out = Vec::new();
+        self.collect_logs(&mut out);
+        out
+    }
+
+    fn collect_logs(&self, out: &mut Vec<Log>) {
+        let calls = self.calls.len();
+        let mut logs = self.logs.iter().peekable();
+        for i in 0..=calls {
+            while let Some(log) = logs.next_if(|l| l.position.map_or(calls, |p| p.as_usize()) <= i)
+            {
+                out.push(log.to_log(Some(out.len())));
+            }
+            if let Some(call) = self.calls.get(i) {
+                call.collect_logs(out);
+            }
+        }
+    }
+}
+
+/// One frame of a compact, decoded call tree.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceFrame {
+    /// `CALL`, `STATICCALL`, `DELEGATECALL`, `CREATE` and so on.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The caller, checksummed.
+    pub from: String,
+    /// The callee, checksummed; the new contract for creations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// Value sent in wei, omitted if zero.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Gas used by the frame and its subcalls, in decimal.
+    pub gas_used: String,
+    /// The called function, if it is a known one.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub call: Option<DecodedCall>,
+    /// The 4-byte selector of an unknown function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    /// Why the frame failed, as reported by the node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The decoded revert data of a failed frame.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<RevertReason>,
+    /// Logs emitted directly by this frame.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<DecodedLog>,
+    /// Subcalls in execution order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<TraceFrame>,
+}
+
+impl From<&CallFrame> for TraceFrame {
+    fn from(frame: &CallFrame) -> Self {
+        let creation = frame.kind.starts_with("CREATE");
+        let failed = frame.error.is_some();
+        // Init code is not call data, and only successful output is return data
+        let call = (!creation)
+            .then(|| {
+                let output = frame.output.as_deref().filter(|_| !failed);
+                decode::decode_call(&frame.input, output)
+            })
+            .flatten();
+        let selector = (!creation && call.is_none() && frame.input.len() >= 4)
+            .then(|| format!("0x{}", hex::encode(&frame.input[..4])));
+        Self {
+            kind: frame.kind.clone(),
+            from: to_checksum(&frame.from, None),
+            to: frame.to.map(|to| to_checksum(&to, None)),
+            value: frame.value.filter(|v| !v.is_zero()).map(|v| v.to_string()),
+            gas_used: frame.gas_used.to_string(),
+            call,
+            selector,
+            error: frame.error.clone(),
+            revert_reason: failed
+                .then(|| decode::decode_revert(frame.output.as_deref().unwrap_or_default())),
+            events: frame
+                .logs
+                .iter()
+                .map(|l| decode::decode_log(&l.to_log(None)))
+                .collect(),
+            calls: frame.calls.iter().map(TraceFrame::from).collect(),
+        }
+    }
+}
+
+/// Traces a mined transaction.
+///
+/// Returns the compact call tree for [`Tracer::Call`] and the node's result
+/// for [`Tracer::Prestate`].
+pub async fn trace_transaction(
+    client: &EthClient,
+    hash: H256,
+    tracer: Tracer,
+    diff_mode: bool,
+) -> Result<Value> {
+    let raw = client
+        .trace_transaction(hash, tracer.name(), tracer.config(diff_mode))
+        .await?;
+    shape(tracer, raw)
+}
+
+/// Traces `tx` (with `from` set) on top of `block`; see [`trace_transaction`].
+pub async fn trace_call(
+    client: &EthClient,
+    tx: &TypedTransaction,
+    block: BlockId,
+    tracer: Tracer,
+    diff_mode: bool,
+) -> Result<Value> {
+    let raw = client
+        .trace_call(tx, block, tracer.name(), tracer.config(diff_mode))
+        .await?;
+    shape(tracer, raw)
+}
+
+fn shape(tracer: Tracer, raw: Value) -> Result<Value> {
+    match tracer {
+        Tracer::Call => {
+            let frame: CallFrame = serde_json::from_value(raw)?;
+            Ok(serde_json::to_value(TraceFrame::from(&frame))?)
+        }
+        Tracer::Prestate => Ok(raw),
+    }
+}
@@ -0,0 +1,458 @@
+//! The JSON-RPC transports an [`crate::eth_client::EthClient`] can talk over.
+//!
+//! The transport is picked from the endpoint URL: `http://` and `https://`
+//! use plain HTTP requests, `ws://` and `wss://` a WebSocket, and anything
+//! without a scheme is taken as the path of a node's IPC socket, e.g.
+//! `/tmp/anvil.ipc` (or a named pipe on Windows). WebSockets and IPC sockets
+//! also carry `eth_subscribe` notifications. They are connected on the first
+//! request, so clients can still be created outside of an async context. A
+//! failed connection is retried on the next request, and one the node closed
+//! is opened again.
+//!
+//! Several calls can be sent together with [`Transport::batch`]: over HTTP as
+//! one JSON-RPC batch request, over sockets as concurrent requests, which
+//! share the connection anyway.
+
+use async_trait::async_trait;
+use ethers::providers::{
+    HttpClientError, Ipc, IpcError, JsonRpcClient, JsonRpcError, ProviderError, PubsubClient,
+    RpcError, Ws, WsClientError,
+};
+use ethers::types::U256;
+use futures::{channel::mpsc, future::join_all};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, value::RawValue, Value};
+use std::{
+    fmt::Debug,
+    future::Future,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use url::Url;
+
+/// Longest part of an unexpected HTTP response body kept in errors.
+const MAX_BODY_IN_ERROR: usize = 200;
+
+/// A JSON-RPC transport chosen from the endpoint URL.
+#[derive(Debug, Clone)]
+pub enum Transport {
+    /// Plain HTTP requests.
+    Http(HttpEndpoint),
+    /// A WebSocket, connected on first use.
+    Ws {
+        /// The `ws://` or `wss://` URL.
+        url: String,
+        /// The connection once established.
+        connection: Arc<Connection<Ws>>,
+    },
+    /// A Unix domain socket or named pipe, connected on first use.
+    Ipc {
+        /// The socket path.
+        path: PathBuf,
+        /// The connection once established.
+        connection: Arc<Connection<Ipc>>,
+    },
+}
+
+impl Transport {
+    /// Creates the transport for `url` without connecting yet.
+    pub fn new(url: &str) -> Result<Self, String> {
+        let Some((scheme, _)) = url.split_once("://") else {
+            if url.trim().is_empty() {
+                return Err("The RPC URL is empty".into());
+            }
+            if is_host_and_port(url) {
+                return Err(format!(
+                    "RPC URL '{}' has no scheme, add http://, https://, ws:// or wss://",
+                    url
+                ));
+            }
+            return Ok(Transport::Ipc {
+                path: PathBuf::from(url),
+                connection: Arc::default(),
+            });
+        };
+        match scheme.to_ascii_lowercase().as_str() {
+            "http" | "https" => Url::parse(url)
+                .map(|url| Transport::Http(HttpEndpoint::new(url)))
+                .map_err(|e| e.to_string()),
+            "ws" | "wss" => {
+                url::Url::parse(url).map_err(|e| e.to_string())?;
+                Ok(Transport::Ws {
+                    url: url.to_string(),
+                    connection: Arc::default(),
+                })
+            }
+            _ => Err(format!(
+                "Unsupported RPC URL '{}', expected http(s)://, ws(s):// or an IPC socket path",
+                url
+            )),
+        }
+    }
+
+    /// Whether the transport can carry `eth_subscribe` notifications.
+    pub fn supports_subscriptions(&self) -> bool {
+        !matches!(self, Transport::Http(_))
+    }
+
+    /// Sends `calls` together and returns their answers in order.
+    ///
+    /// The outer error means the batch as a whole failed, e.g. because the
+    /// endpoint is unreachable; each call can still fail on its own.
+    pub async fn batch(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Result<Value, ProviderError>>, ProviderError> {
+        match self {
+            Transport::Http(http) => http.batch(calls).await,
+            _ => Ok(join_all(
+                calls
+                    .iter()
+                    .map(|(method, params)| self.request::<_, Value>(method, params)),
+            )
+            .await),
+        }
+    }
+
+    /// The socket carrying the notifications, which exists once `eth_subscribe`
+    /// went through.
+    fn connected(&self) -> Result<Connected, ProviderError> {
+        let not_connected = || ProviderError::CustomError("The RPC socket is not connected".into());
+        match self {
+            Transport::Ws { connection, .. } => connection
+                .get()
+                .map(|(_, ws)| Connected::Ws(ws))
+                .ok_or_else(not_connected),
+            Transport::Ipc { connection, .. } => connection
+                .get()
+                .map(|(_, ipc)| Connected::Ipc(ipc))
+                .ok_or_else(not_connected),
+            Transport::Http(_) => Err(ProviderError::UnsupportedRPC),
+        }
+    }
+}
+
+/// Whether `url` looks like `host:port`, e.g. `localhost:8545`, rather than a
+/// socket path.
+fn is_host_and_port(url: &str) -> bool {
+    match url.rsplit_once(':') {
+        Some((host, port)) => {
+            !host.is_empty()
+                && !host.contains(['/', '\\'])
+                && !port.is_empty()
+                && port.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// A socket connection, opened on first use and again after it was closed.
+#[derive(Debug)]
+pub struct Connection<T> {
+    /// The open connection and its generation.
+    current: Mutex<Option<(u64, T)>>,
+    /// Held while connecting, so concurrent requests share one attempt.
+    connecting: tokio::sync::Mutex<u64>,
+}
+
+impl<T> Default for Connection<T> {
+    fn default() -> Self {
+        Self {
+            current: Mutex::new(None),
+            connecting: tokio::sync::Mutex::new(0),
+        }
+    }
+}
+
+impl<T: Clone> Connection<T> {
+    fn get(&self) -> Option<(u64, T)> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Returns the open connection, connecting with `connect` first if needed.
+    async fn get_or_connect<E, F>(
+        &self,
+        connect: impl FnOnce() -> F,
+    ) -> Result<(u64, T), ProviderError>
+    where
+        E: Into<ProviderError>,
+        F: Future<Output = Result<T, E>>,
+    {
+        if let Some(current) = self.get() {
+            return Ok(current);
+        }
+        let mut generation = self.connecting.lock().await;
+        if let Some(current) = self.get() {
+            return Ok(current);
+        }
+        let connection = connect().await.map_err(Into::into)?;
+        *generation += 1;
+        *self.current.lock().unwrap() = Some((*generation, connection.clone()));
+        Ok((*generation, connection))
+    }
+
+    /// Drops connection `generation`, so the next request opens a new one.
+    /// A newer connection is kept.
+    fn reset(&self, generation: u64) {
+        let mut current = self.current.lock().unwrap();
+        if matches!(*current, Some((open, _)) if open == generation) {
+            *current = None;
+        }
+    }
+}
+
+/// A JSON-RPC endpoint reached with HTTP POST requests.
+///
+/// Unlike ethers' `Http`, it keeps the status of responses that carry no
+/// JSON-RPC answer, so rate limiting (429) and server errors (5xx) can be told
+/// apart from other failures.
+#[derive(Debug, Clone)]
+pub struct HttpEndpoint {
+    url: Url,
+    client: reqwest::Client,
+    next_id: Arc<AtomicU64>,
+}
+
+impl HttpEndpoint {
+    /// Creates an endpoint for `url`.
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    async fn request(&self, method: &str, params: impl Serialize) -> Result<Value, ProviderError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let payload = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = self.post(&payload).await?;
+        match response
+            .json()
+            .map(|reply| answer(reply, response.success()))
+        {
+            Some(Some(answer)) => answer,
+            _ => Err(response.into()),
+        }
+    }
+
+    /// Sends `calls` as one JSON-RPC batch and returns their answers in order.
+    async fn batch(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Result<Value, ProviderError>>, ProviderError> {
+        let first_id = self
+            .next_id
+            .fetch_add(calls.len() as u64, Ordering::Relaxed);
+        let payload: Vec<Value> = calls
+            .iter()
+            .zip(first_id..)
+            .map(|((method, params), id)| {
+                json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+            })
+            .collect();
+        let response = self.post(&payload).await?;
+        let replies = match response.json() {
+            Some(Value::Array(replies)) => replies,
+            // A node rejecting the whole batch answers with a single error
+            Some(reply) => match answer(reply, response.success()) {
+                Some(Err(e)) => return Err(e),
+                _ => return Err(response.into()),
+            },
+            None => return Err(response.into()),
+        };
+
+        // Answers may come in any order
+        let mut answers: Vec<Option<Result<Value, ProviderError>>> =
+            calls.iter().map(|_| None).collect();
+        for reply in replies {
+            let index = reply["id"]
+                .as_u64()
+                .and_then(|id| id.checked_sub(first_id))
+                .map(|i| i as usize)
+                .filter(|i| *i < calls.len());
+            if let (Some(index), Some(answer)) = (index, answer(reply, response.success())) {
+                answers[index] = Some(answer);
+            }
+        }
+        Ok(answers
+            .into_iter()
+            .zip(calls)
+            .map(|(answer, (method, _))| {
+                answer.unwrap_or_else(|| {
+                    Err(ProviderError::CustomError(format!(
+                        "The batch response has no answer to {}",
+                        method
+                    )))
+                })
+            })
+            .collect())
+    }
+
+    async fn post(&self, payload: &impl Serialize) -> Result<HttpResponse, ProviderError> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(payload)
+            .send()
+            .await?;
+        Ok(HttpResponse {
+            status: response.status().as_u16(),
+            body: response.bytes().await?.to_vec(),
+        })
+    }
+}
+
+/// A raw HTTP response to a JSON-RPC request.
+struct HttpResponse {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    fn json(&self) -> Option<Value> {
+        serde_json::from_slice(&self.body).ok()
+    }
+}
+
+impl From<HttpResponse> for ProviderError {
+    fn from(response: HttpResponse) -> Self {
+        HttpResponseError {
+            status: response.status,
+            body: String::from_utf8_lossy(&response.body)
+                .chars()
+                .take(MAX_BODY_IN_ERROR)
+                .collect(),
+        }
+        .into()
+    }
+}
+
+/// The answer in one JSON-RPC reply object: its error, or its result if the
+/// HTTP status was a success.
+fn answer(mut reply: Value, success: bool) -> Option<Result<Value, ProviderError>> {
+    if let Some(error) = reply.get("error") {
+        return Some(
+            match serde_json::from_value::<JsonRpcError>(error.clone()) {
+                Ok(error) => Err(HttpClientError::JsonRpcError(error).into()),
+                Err(e) => Err(e.into()),
+            },
+        );
+    }
+    match reply.get_mut("result") {
+        Some(result) if success => Some(Ok(result.take())),
+        _ => None,
+    }
+}
+
+/// An HTTP response without a JSON-RPC answer, e.g. a 429 or 502 page.
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP {status}: {body}")]
+pub struct HttpResponseError {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The start of the response body.
+    pub body: String,
+}
+
+impl RpcError for HttpResponseError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        None
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        None
+    }
+}
+
+impl From<HttpResponseError> for ProviderError {
+    fn from(e: HttpResponseError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+enum Connected {
+    Ws(Ws),
+    Ipc(Ipc),
+}
+
+#[async_trait]
+impl JsonRpcClient for Transport {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            Transport::Http(http) => {
+                Ok(serde_json::from_value(http.request(method, params).await?)?)
+            }
+            Transport::Ws { url, connection } => {
+                let (generation, ws) = connection
+                    .get_or_connect(|| {
+                        log::debug!("Connecting to {}", url);
+                        Ws::connect(url.as_str())
+                    })
+                    .await?;
+                let result = ws.request(method, params).await;
+                if let Err(
+                    WsClientError::UnexpectedClose
+                    | WsClientError::DeadChannel
+                    | WsClientError::TooManyReconnects,
+                ) = &result
+                {
+                    log::warn!("The WebSocket to {} was closed", url);
+                    connection.reset(generation);
+                }
+                Ok(result?)
+            }
+            Transport::Ipc { path, connection } => {
+                let (generation, ipc) = connection
+                    .get_or_connect(|| {
+                        log::debug!("Connecting to IPC socket {}", path.display());
+                        Ipc::connect(path)
+                    })
+                    .await?;
+                let result = ipc.request(method, params).await;
+                if let Err(
+                    IpcError::ServerExit
+                    | IpcError::ChannelError(_)
+                    | IpcError::RequestCancelled(_)
+                    | IpcError::IoError(_),
+                ) = &result
+                {
+                    log::warn!("The IPC socket {} was closed", path.display());
+                    connection.reset(generation);
+                }
+                Ok(result?)
+            }
+        }
+    }
+}
+
+impl PubsubClient for Transport {
+    type NotificationStream = mpsc::UnboundedReceiver<Box<RawValue>>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, ProviderError> {
+        match self.connected()? {
+            Connected::Ws(ws) => Ok(ws.subscribe(id)?),
+            Connected::Ipc(ipc) => Ok(ipc.subscribe(id)?),
+        }
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), ProviderError> {
+        match self.connected()? {
+            Connected::Ws(ws) => Ok(ws.unsubscribe(id)?),
+            Connected::Ipc(ipc) => Ok(ipc.unsubscribe(id)?),
+        }
+    }
+}
@@ -1,38 +1,63 @@
 //! Wallet module for managing Ethereum accounts and aliases
 
 use crate::{
+    backup::{BackupArchive, RestoreMode, RestoreReport},
     error::{Result, WalletError},
+    journal::TxJournal,
+    keystore::{self, ImportedKeystore, KeystoreImportReport, SkippedKeystore},
     models::{Eip1559TransactionRequest, SignedTransaction},
+    secret::PrivateKey,
+    shamir::{self, KeyShare},
 };
 use ethers::{
     core::types::{transaction::eip2718::TypedTransaction, U256},
     signers::{LocalWallet, Signer},
     types::Address,
+    utils::to_checksum,
 };
 use rand::thread_rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Account state that is tracked separately on every chain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainState {
+    /// The next nonce to be used for a transaction on this chain.
+    pub nonce: u64,
+}
 
 /// Represents a wallet account with its associated data.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
-    /// Private key in hex format.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub private_key: Option<String>,
-    /// The next nonce to be used for a transaction.
-    pub nonce: u64,
+    /// Private key, stored as hex in the wallet file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<PrivateKey>,
+    /// Per-chain state, keyed by chain id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chains: BTreeMap<u64, ChainState>,
+    /// The single nonce of wallet files written before per-chain state.
+    ///
+    /// It seeds the first chain the account is used on and is then dropped.
+    #[serde(
+        default,
+        rename = "nonce",
+        deserialize_with = "deserialize_legacy_nonce",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub legacy_nonce: Option<u64>,
     /// List of aliases associated with this account.
     pub aliases: Vec<String>,
 }
 
 impl Account {
     /// Creates a new account from a private key.
-    pub fn new_with_private_key(private_key: String) -> Self {
+    pub fn new_with_private_key(private_key: PrivateKey) -> Self {
         Self {
             private_key: Some(private_key),
-            nonce: 0,
+            chains: BTreeMap::new(),
+            legacy_nonce: None,
             aliases: Vec::new(),
         }
     }
@@ -41,10 +66,42 @@ impl Account {
     pub fn new_watch_only() -> Self {
         Self {
             private_key: None,
-            nonce: 0,
+            chains: BTreeMap::new(),
+            legacy_nonce: None,
             aliases: Vec::new(),
         }
     }
+
+    /// Returns the next nonce on `chain_id`.
+    ///
+    /// Without recorded state for the chain this is the migrated legacy nonce,
+    /// or zero.
+    pub fn nonce(&self, chain_id: u64) -> u64 {
+        self.chains
+            .get(&chain_id)
+            .map(|state| state.nonce)
+            .or(self.legacy_nonce)
+            .unwrap_or(0)
+    }
+
+    /// Returns the recorded state on `chain_id`, if any.
+    pub fn chain(&self, chain_id: u64) -> Option<&ChainState> {
+        self.chains.get(&chain_id)
+    }
+
+    /// Records the next nonce on `chain_id`, completing any legacy migration.
+    fn set_nonce(&mut self, chain_id: u64, nonce: u64) {
+        self.chains.entry(chain_id).or_default().nonce = nonce;
+        self.legacy_nonce = None;
+    }
+}
+
+/// Reads the legacy `nonce` field; a zero nonce carries nothing to migrate.
+fn deserialize_legacy_nonce<'de, D>(deserializer: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<u64>::deserialize(deserializer)?.filter(|nonce| *nonce > 0))
 }
 
 /// Main wallet structure containing all accounts and aliases.
@@ -71,9 +128,8 @@ impl Wallet {
     /// Gets the signer for an account by its address.
     pub fn get_signer(&self, address: &Address) -> Result<LocalWallet, WalletError> {
         if let Some(acc) = self.accounts.get(address) {
-            if let Some(pk_hex) = &acc.private_key {
-                LocalWallet::from_str(pk_hex)
-                    .map_err(|e| WalletError::InvalidPrivateKey(e.to_string()))
+            if let Some(key) = &acc.private_key {
+                key.signer()
             } else {
                 // Watch-only account: no signer available
                 Err(WalletError::SignerNotFound(format!("0x{:x}", address)))
@@ -88,41 +144,192 @@ impl Wallet {
     /// Returns the address of the new account.
     pub fn create_account(&mut self, alias: &str) -> Result<Address> {
         let wallet = LocalWallet::new(&mut thread_rng());
-        self.add_account(wallet, Some(alias))
+        let address = wallet.address();
+        self.add_account(PrivateKey::from_signer(&wallet), address, Some(alias))
     }
 
     /// Imports an account from a private key string.
     pub fn import_private_key(&mut self, private_key: &str) -> Result<Address> {
-        let wallet = private_key
-            .parse::<LocalWallet>()
-            .map_err(|e| WalletError::InvalidPrivateKey(e.to_string()))?;
+        self.import_key(PrivateKey::from_hex(private_key)?)
+    }
 
-        let address = wallet.address();
+    /// Imports an account from a parsed private key.
+    ///
+    /// A watch-only account with the same address is upgraded to a signing account.
+    pub fn import_key(&mut self, key: PrivateKey) -> Result<Address> {
+        let address = key.signer()?.address();
 
         if let Some(account) = self.accounts.get_mut(&address) {
             // Account exists
             if account.private_key.is_none() {
                 // Upgrade watch-only to signing account
-                let pk_hex = hex::encode(wallet.signer().to_bytes());
-                account.private_key = Some(pk_hex);
+                account.private_key = Some(key);
                 self.mark_dirty();
             }
             return Ok(address);
         }
 
         // New account path
-        self.add_account(wallet, None)
+        self.add_account(key, address, None)
     }
 
-    /// Adds an account to the wallet.
-    fn add_account(&mut self, wallet: LocalWallet, alias: Option<&str>) -> Result<Address> {
-        let address = wallet.address();
+    /// Splits the private key of an account into `shares` Shamir shares, any
+    /// `threshold` of which can rebuild it with [`Wallet::import_key_shares`].
+    pub fn split_private_key(
+        &self,
+        identifier: &str,
+        threshold: u8,
+        shares: u8,
+    ) -> Result<Vec<KeyShare>> {
+        let (account, address) = self
+            .get_account(identifier)
+            .ok_or_else(|| WalletError::SignerNotFound(identifier.to_string()))?;
+        let key = account
+            .private_key
+            .as_ref()
+            .ok_or_else(|| WalletError::SignerNotFound(format!("0x{:x}", address)))?;
+
+        let parts = shamir::split_secret(key.as_bytes(), threshold, shares)?;
+        Ok(parts
+            .into_iter()
+            .map(|(index, data)| KeyShare {
+                threshold,
+                index,
+                address,
+                data,
+            })
+            .collect())
+    }
+
+    /// Rebuilds a private key from Shamir shares and imports it.
+    ///
+    /// All shares must belong to the same account and at least `threshold` of
+    /// them are required. The rebuilt key must derive the address recorded in
+    /// the shares, otherwise nothing is imported.
+    pub fn import_key_shares(&mut self, shares: &[KeyShare]) -> Result<Address> {
+        let first = shares
+            .first()
+            .ok_or_else(|| WalletError::InvalidKeyShares("no shares provided".to_string()))?;
+        if shares
+            .iter()
+            .any(|s| s.address != first.address || s.threshold != first.threshold)
+        {
+            return Err(WalletError::InvalidKeyShares(
+                "shares belong to different keys".to_string(),
+            ));
+        }
+        if shares.len() < first.threshold as usize {
+            return Err(WalletError::InvalidKeyShares(format!(
+                "need {} shares, got {}",
+                first.threshold,
+                shares.len()
+            )));
+        }
+
+        let parts: Vec<(u8, Zeroizing<Vec<u8>>)> =
+            shares.iter().map(|s| (s.index, s.data.clone())).collect();
+        let secret = shamir::combine_shares(&parts)?;
+        let key = PrivateKey::from_slice(&secret)?;
+        if key.signer()?.address() != first.address {
+            return Err(WalletError::InvalidKeyShares(format!(
+                "shares do not rebuild the key for {}",
+                to_checksum(&first.address, None)
+            )));
+        }
+
+        self.import_key(key)
+    }
+
+    /// Imports V3 keystore files, as written by geth and Foundry.
+    ///
+    /// `path` is a single keystore file or a directory of them, all decrypted
+    /// with `password`. Each account gets an alias derived from its file name
+    /// unless that alias already points to another address. A single file that
+    /// fails to import is an error; in a directory such files are skipped and
+    /// listed in the report.
+    pub fn import_keystore(&mut self, path: &Path, password: &str) -> Result<KeystoreImportReport> {
+        let mut report = KeystoreImportReport::default();
+        let single = !path.is_dir();
+
+        for file in keystore::keystore_files(path)? {
+            let name = file.display().to_string();
+            let key = match keystore::read_keystore(&file, password) {
+                Ok(key) => key,
+                Err(e) if !single => {
+                    report.skipped.push(SkippedKeystore {
+                        file: name,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let address = self.import_key(key)?;
+
+            let alias = match keystore::alias_from_file_name(&file) {
+                Some(alias) => match self.aliases.get(&alias) {
+                    Some(current) if *current == address => Some(alias),
+                    Some(_) => {
+                        report.alias_conflicts.push(alias);
+                        None
+                    }
+                    None => {
+                        self.add_alias(address, alias.clone())?;
+                        Some(alias)
+                    }
+                },
+                None => None,
+            };
+            report.imported.push(ImportedKeystore {
+                file: name,
+                address: to_checksum(&address, None),
+                alias,
+            });
+        }
+        Ok(report)
+    }
 
+    /// Exports a signing account as a V3 keystore file in `dir`.
+    ///
+    /// The file is named `name`, or else the account's first alias, or else its
+    /// lowercase hex address. Existing files are never overwritten.
+    pub fn export_keystore(
+        &self,
+        identifier: &str,
+        dir: &Path,
+        password: &str,
+        name: Option<&str>,
+    ) -> Result<PathBuf> {
+        let (account, address) = self
+            .get_account(identifier)
+            .ok_or_else(|| WalletError::SignerNotFound(identifier.to_string()))?;
+        let key = account
+            .private_key
+            .as_ref()
+            .ok_or_else(|| WalletError::SignerNotFound(format!("0x{:x}", address)))?;
+
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => account
+                .aliases
+                .first()
+                .cloned()
+                .unwrap_or_else(|| hex::encode(address)),
+        };
+        keystore::write_keystore(dir, &name, key, password)
+    }
+
+    /// Adds an account to the wallet.
+    fn add_account(
+        &mut self,
+        private_key: PrivateKey,
+        address: Address,
+        alias: Option<&str>,
+    ) -> Result<Address> {
         /*if self.accounts.contains_key(&address) {
             return Err(WalletError::AccountAlreadyExists(address));
         }*/
 
-        let private_key = hex::encode(wallet.signer().to_bytes());
         let mut account = Account::new_with_private_key(private_key);
 
         if let Some(alias) = alias {
@@ -258,14 +465,14 @@ impl Wallet {
             .collect()
     }
 
-    /// Sets the nonce for a specific account.
-    pub fn set_nonce(&mut self, identifier: &str, nonce: u64) -> Result<()> {
+    /// Sets the nonce of an account on `chain_id`.
+    pub fn set_nonce(&mut self, identifier: &str, chain_id: u64, nonce: u64) -> Result<()> {
         let (_, address) = self
             .get_account(identifier)
             .ok_or_else(|| WalletError::SignerNotFound(identifier.to_string()))?;
 
         if let Some(account) = self.accounts.get_mut(&address) {
-            account.nonce = nonce;
+            account.set_nonce(chain_id, nonce);
             self.mark_dirty();
             Ok(())
         } else {
@@ -275,7 +482,8 @@ impl Wallet {
 
     /// Signs a transaction request with the specified account.
     ///
-    /// This method also increments the nonce of the signing account upon success.
+    /// The nonce is checked against, and on success advances, the account's
+    /// state on the transaction's `chain_id`.
     pub async fn sign_transaction(
         &mut self,
         tx_request: &Eip1559TransactionRequest,
@@ -286,27 +494,55 @@ impl Wallet {
             .ok_or_else(|| WalletError::SignerNotFound(from_identifier.to_string()))?;
 
         // Validate the transaction nonce
-        if tx_request.nonce != U256::from(account.nonce) {
+        let chain_id = tx_request.chain_id;
+        let expected = account.nonce(chain_id);
+        if tx_request.nonce != U256::from(expected) {
             return Err(WalletError::NonceMismatch {
-                expected: account.nonce,
+                expected,
                 actual: tx_request.nonce.as_u64(),
             });
         }
 
-        let signer = self.get_signer(&from_address)?;
-
-        let typed_tx: TypedTransaction = tx_request.clone().into();
-        let signature = signer.sign_transaction(&typed_tx).await?;
+        let signed = self.sign_with(&from_address, tx_request).await?;
 
         // Increment the nonce after successful signing
         if let Some(account) = self.accounts.get_mut(&from_address) {
-            account.nonce += 1;
+            account.set_nonce(chain_id, expected + 1);
             self.mark_dirty();
         } else {
             // This should ideally not happen if get_account succeeded
             return Err(WalletError::AccountNotFound(from_address));
         }
 
+        Ok(signed)
+    }
+
+    /// Signs a transaction whose nonce was reserved through a
+    /// [`NonceManager`](crate::nonce::NonceManager).
+    ///
+    /// Unlike [`Wallet::sign_transaction`], the nonce is neither checked against
+    /// nor advanced in the account.
+    pub async fn sign_reserved(
+        &self,
+        tx_request: &Eip1559TransactionRequest,
+        from_identifier: &str,
+    ) -> Result<SignedTransaction> {
+        let (_, from_address) = self
+            .get_account(from_identifier)
+            .ok_or_else(|| WalletError::SignerNotFound(from_identifier.to_string()))?;
+        self.sign_with(&from_address, tx_request).await
+    }
+
+    async fn sign_with(
+        &self,
+        from_address: &Address,
+        tx_request: &Eip1559TransactionRequest,
+    ) -> Result<SignedTransaction> {
+        let signer = self.get_signer(from_address)?;
+
+        let typed_tx: TypedTransaction = tx_request.clone().into();
+        let signature = signer.sign_transaction(&typed_tx).await?;
+
         let rlp_signed = typed_tx.rlp_signed(&signature);
         let hash = typed_tx.hash(&signature);
 
@@ -318,6 +554,183 @@ impl Wallet {
         })
     }
 
+    /// Loads a wallet from `path`, or creates an empty one if the file does not exist.
+    ///
+    /// A file that cannot be parsed is logged and replaced by an empty wallet,
+    /// matching the server's startup behavior. The returned wallet remembers
+    /// `path` so it can later be persisted with [`Wallet::save`].
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut wallet = match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                // The file holds the private keys
+                let contents = Zeroizing::new(contents);
+                log::info!("Loading wallet from {}", path.display());
+                serde_json::from_str(&contents).unwrap_or_else(|e| {
+                    log::warn!("Failed to parse wallet file, creating a new one: {}", e);
+                    Wallet::new()
+                })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::info!("Creating new wallet at {}", path.display());
+                Wallet::new()
+            }
+            Err(e) => return Err(WalletError::FileError(e)),
+        };
+        wallet.set_file_path(path);
+        Ok(wallet)
+    }
+
+    /// Writes the wallet to its file path if it has unsaved changes.
+    ///
+    /// Does nothing when the wallet is clean or has no file path.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(path) = &self.file_path {
+            log::info!("Saving wallet to {}", path.display());
+            let contents = Zeroizing::new(serde_json::to_string_pretty(&*self)?);
+            std::fs::write(path, contents.as_bytes())?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Exports the whole wallet state, and the transaction history in
+    /// `journal` if given, as a password-encrypted backup archive.
+    pub fn export_backup(
+        &self,
+        password: &str,
+        journal: Option<&TxJournal>,
+    ) -> Result<BackupArchive> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(self)?);
+        let journal = journal.map(serde_json::to_vec).transpose()?;
+        BackupArchive::seal(&plaintext, journal.as_deref(), password)
+    }
+
+    /// Restores a backup archive into this wallet.
+    ///
+    /// In [`RestoreMode::Merge`] missing accounts and aliases are added, watch-only
+    /// accounts gain keys from the backup and nonces take the higher value; aliases
+    /// that already point elsewhere are reported as conflicts and left alone. In
+    /// [`RestoreMode::Replace`] the wallet contents are replaced by the backup.
+    /// A transaction history in the archive is restored into `journal` the
+    /// same way, see [`TxJournal::restore`]. With `dry_run` neither is
+    /// modified and the report describes what would change.
+    pub fn import_backup(
+        &mut self,
+        archive: &BackupArchive,
+        password: &str,
+        mode: RestoreMode,
+        dry_run: bool,
+        journal: Option<&mut TxJournal>,
+    ) -> Result<RestoreReport> {
+        let contents = archive.open(password)?;
+        let backup: Wallet = serde_json::from_slice(&contents.wallet)?;
+        let backup_journal: Option<TxJournal> = contents
+            .journal
+            .as_deref()
+            .map(serde_json::from_slice)
+            .transpose()?;
+
+        let mut report = RestoreReport {
+            mode,
+            dry_run,
+            ..Default::default()
+        };
+
+        let (accounts, aliases) = match mode {
+            RestoreMode::Replace => (backup.accounts, backup.aliases),
+            RestoreMode::Merge => {
+                let mut accounts = self.accounts.clone();
+                let mut aliases = self.aliases.clone();
+                for (address, account) in backup.accounts {
+                    match accounts.get_mut(&address) {
+                        Some(local) => {
+                            if local.private_key.is_none() {
+                                local.private_key = account.private_key;
+                            }
+                            for (chain_id, state) in account.chains {
+                                let nonce = local.nonce(chain_id).max(state.nonce);
+                                local.set_nonce(chain_id, nonce);
+                            }
+                            if local.chains.is_empty() {
+                                local.legacy_nonce = local.legacy_nonce.max(account.legacy_nonce);
+                            }
+                        }
+                        None => {
+                            accounts.insert(
+                                address,
+                                Account {
+                                    aliases: Vec::new(),
+                                    ..account
+                                },
+                            );
+                        }
+                    }
+                }
+                for (alias, address) in backup.aliases {
+                    match aliases.get(&alias) {
+                        Some(current) if *current == address => {}
+                        Some(_) => report.alias_conflicts.push(alias),
+                        None => {
+                            if let Some(account) = accounts.get_mut(&address) {
+                                account.aliases.push(alias.clone());
+                            }
+                            aliases.insert(alias, address);
+                        }
+                    }
+                }
+                (accounts, aliases)
+            }
+        };
+
+        for (address, account) in &accounts {
+            match self.accounts.get(address) {
+                None => report.accounts_added.push(to_checksum(address, None)),
+                Some(current) => {
+                    if current.private_key.is_none() && account.private_key.is_some() {
+                        report.keys_added.push(to_checksum(address, None));
+                    }
+                    if current.chains != account.chains
+                        || current.legacy_nonce != account.legacy_nonce
+                    {
+                        report.nonces_changed.push(to_checksum(address, None));
+                    }
+                }
+            }
+        }
+        for address in self.accounts.keys() {
+            if !accounts.contains_key(address) {
+                report.accounts_removed.push(to_checksum(address, None));
+            }
+        }
+        for (alias, address) in &aliases {
+            if self.aliases.get(alias) != Some(address) {
+                report.aliases_set.push(alias.clone());
+            }
+        }
+        for alias in self.aliases.keys() {
+            if !aliases.contains_key(alias) {
+                report.aliases_removed.push(alias.clone());
+            }
+        }
+        if let (Some(journal), Some(backup_journal)) = (journal, backup_journal) {
+            let (added, removed) = journal.restore(backup_journal, mode, dry_run);
+            report.transactions_added = added.iter().map(|h| format!("{:?}", h)).collect();
+            report.transactions_removed = removed.iter().map(|h| format!("{:?}", h)).collect();
+        }
+        report.sort();
+
+        if !dry_run {
+            self.accounts = accounts;
+            self.aliases = aliases;
+            self.mark_dirty();
+        }
+        Ok(report)
+    }
+
     /// Gets the file path of the wallet.
     pub fn file_path(&self) -> Option<&Path> {
         self.file_path.as_deref()
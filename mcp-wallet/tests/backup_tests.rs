@@ -0,0 +1,184 @@
+//! Tests for encrypted wallet backup archives.
+
+use ethers::core::types::{Address, U256};
+use mcp_wallet::{
+    backup::{BackupArchive, RestoreMode},
+    error::WalletError,
+    journal::TxJournal,
+    models::Eip1559TransactionRequest,
+    wallet::Wallet,
+};
+
+const PK: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+fn source_wallet() -> (Wallet, Address) {
+    let mut wallet = Wallet::new();
+    let address = wallet.import_private_key(PK).unwrap();
+    wallet.add_alias(address, "treasury".to_string()).unwrap();
+    wallet.set_nonce("treasury", 1, 7).unwrap();
+    (wallet, address)
+}
+
+#[test]
+fn test_backup_roundtrip_and_integrity() {
+    let (wallet, _) = source_wallet();
+    let archive = wallet.export_backup("correct horse", None).unwrap();
+    assert_eq!(archive.version, 2);
+    assert!(archive.journal.is_none());
+
+    // The archive survives a trip through JSON and decrypts with the right password
+    let json = serde_json::to_string(&archive).unwrap();
+    let parsed: BackupArchive = serde_json::from_str(&json).unwrap();
+    assert!(parsed.open("correct horse").is_ok());
+
+    // Wrong password fails the integrity check
+    let err = parsed.open("wrong").unwrap_err();
+    assert!(matches!(err, WalletError::BackupError(_)));
+
+    // A tampered header fails even with the right password
+    let mut tampered = parsed.clone();
+    tampered.created_at += 1;
+    assert!(tampered.open("correct horse").is_err());
+
+    // Unknown versions are rejected
+    let mut future = parsed.clone();
+    future.version = 99;
+    assert!(future.open("correct horse").is_err());
+
+    // So are key derivation costs above the exporter's, before any work is done
+    for (log_n, r, p) in [(40, 8, 1), (15, 1 << 20, 1), (15, 8, 1 << 20)] {
+        let mut costly = parsed.clone();
+        costly.kdf.log_n = log_n;
+        costly.kdf.r = r;
+        costly.kdf.p = p;
+        let err = costly.open("correct horse").unwrap_err();
+        assert!(err.to_string().contains("scrypt parameters"), "{}", err);
+    }
+}
+
+#[test]
+fn test_restore_merge_dry_run_and_apply() {
+    let (source, address) = source_wallet();
+    let archive = source.export_backup("pw", None).unwrap();
+
+    // Target has the same address as watch-only, plus its own account
+    let mut target = Wallet::new();
+    target.add_alias(address, "watch".to_string()).unwrap();
+    let own = target.create_account("own").unwrap();
+
+    let report = target
+        .import_backup(&archive, "pw", RestoreMode::Merge, true, None)
+        .unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.keys_added.len(), 1);
+    assert_eq!(report.nonces_changed.len(), 1);
+    assert_eq!(report.aliases_set, vec!["treasury".to_string()]);
+    assert!(report.accounts_removed.is_empty());
+
+    // Dry run leaves the wallet untouched
+    let (account, _) = target.get_account("watch").unwrap();
+    assert!(account.private_key.is_none());
+    assert!(target.get_account("treasury").is_none());
+
+    target
+        .import_backup(&archive, "pw", RestoreMode::Merge, false, None)
+        .unwrap();
+    let (account, resolved) = target.get_account("treasury").unwrap();
+    assert_eq!(resolved, address);
+    assert!(account.private_key.is_some());
+    assert_eq!(account.nonce(1), 7);
+    assert!(account.aliases.contains(&"watch".to_string()));
+    assert!(target.get_account(&format!("0x{:x}", own)).is_some());
+}
+
+#[test]
+fn test_restore_replace_reports_removals() {
+    let (source, address) = source_wallet();
+    let archive = source.export_backup("pw", None).unwrap();
+
+    let mut target = Wallet::new();
+    let own = target.create_account("own").unwrap();
+
+    let report = target
+        .import_backup(&archive, "pw", RestoreMode::Replace, false, None)
+        .unwrap();
+    assert_eq!(report.accounts_removed.len(), 1);
+    assert_eq!(report.aliases_removed, vec!["own".to_string()]);
+    assert_eq!(report.accounts_added.len(), 1);
+
+    assert!(target.get_account("own").is_none());
+    assert!(target.get_account(&format!("0x{:x}", own)).is_none());
+    assert_eq!(target.get_account("treasury").unwrap().1, address);
+    assert!(target.is_dirty());
+}
+
+/// Records a signed transfer from `from` with `nonce` in `journal`.
+async fn record(journal: &mut TxJournal, wallet: &Wallet, from: Address, nonce: u64) -> String {
+    let tx = Eip1559TransactionRequest {
+        chain_id: 1,
+        to: Some(Address::repeat_byte(0xbb)),
+        value: U256::from(5),
+        nonce: nonce.into(),
+        max_fee_per_gas: 100.into(),
+        ..Default::default()
+    };
+    let raw = wallet
+        .sign_reserved(&tx, &format!("0x{:x}", from))
+        .await
+        .unwrap()
+        .raw_transaction;
+    format!("{:?}", journal.record(&raw, true, None).unwrap().hash)
+}
+
+#[tokio::test]
+async fn test_backup_restores_the_journal() {
+    let (source, address) = source_wallet();
+    let mut history = TxJournal::new();
+    let first = record(&mut history, &source, address, 0).await;
+    let second = record(&mut history, &source, address, 1).await;
+    let archive = source.export_backup("pw", Some(&history)).unwrap();
+    assert!(archive.journal.is_some());
+
+    // The journal is covered by the MAC
+    let mut tampered = archive.clone();
+    tampered.journal.as_mut().unwrap().iv = "00".repeat(16);
+    assert!(tampered.open("pw").is_err());
+
+    // Merging keeps local records and adds the missing ones
+    let mut target = Wallet::new();
+    let mut journal = TxJournal::new();
+    let own = record(&mut journal, &source, address, 5).await;
+    record(&mut journal, &source, address, 0).await;
+    let report = target
+        .import_backup(&archive, "pw", RestoreMode::Merge, true, Some(&mut journal))
+        .unwrap();
+    assert_eq!(report.transactions_added, vec![second.clone()]);
+    assert!(report.transactions_removed.is_empty());
+    assert_eq!(journal.len(), 2);
+
+    target
+        .import_backup(
+            &archive,
+            "pw",
+            RestoreMode::Merge,
+            false,
+            Some(&mut journal),
+        )
+        .unwrap();
+    assert_eq!(journal.len(), 3);
+
+    // Replacing drops the records the backup does not have
+    let report = target
+        .import_backup(
+            &archive,
+            "pw",
+            RestoreMode::Replace,
+            false,
+            Some(&mut journal),
+        )
+        .unwrap();
+    assert!(report.transactions_added.is_empty());
+    assert_eq!(report.transactions_removed, vec![own]);
+    assert_eq!(journal.len(), 2);
+    assert!(journal.get(first.parse().unwrap()).is_some());
+}
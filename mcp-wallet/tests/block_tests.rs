@@ -0,0 +1,169 @@
+//! Tests for block lookups and finding blocks by timestamp.
+
+use ethers::types::{BlockId, BlockNumber, H256};
+use mcp_wallet::{
+    block::{self, BlockTransactions},
+    eth_client::EthClient,
+};
+use serde_json::{json, Value};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+mod common;
+
+/// Block `n` of a chain with 12-second slots starting at unix time 1000.
+fn block_json(number: u64) -> Value {
+    json!({
+        "hash": format!("{:?}", H256::from_low_u64_be(number + 1)),
+        "parentHash": format!("{:?}", H256::from_low_u64_be(number)),
+        "number": format!("0x{:x}", number),
+        "timestamp": format!("0x{:x}", 1000 + 12 * number),
+        "miner": format!("0x{}", "11".repeat(20)),
+        "gasUsed": "0x5208",
+        "gasLimit": "0x1c9c380",
+        "baseFeePerGas": "0x3b9aca00",
+        "transactions": [],
+    })
+}
+
+fn number_param(param: &Value, head: u64) -> u64 {
+    match param.as_str().unwrap() {
+        "latest" => head,
+        hex => u64::from_str_radix(hex.trim_start_matches("0x"), 16).unwrap(),
+    }
+}
+
+#[test]
+fn test_parse_block_id() {
+    assert_eq!(
+        block::parse_block_id("finalized").unwrap(),
+        BlockId::Number(BlockNumber::Finalized)
+    );
+    assert_eq!(
+        block::parse_block_id("Latest").unwrap(),
+        BlockId::Number(BlockNumber::Latest)
+    );
+    assert_eq!(
+        block::parse_block_id("1234").unwrap(),
+        BlockId::Number(BlockNumber::Number(1234.into()))
+    );
+    assert_eq!(
+        block::parse_block_id("0x4d2").unwrap(),
+        BlockId::Number(BlockNumber::Number(1234.into()))
+    );
+    let hash = H256::repeat_byte(7);
+    assert_eq!(
+        block::parse_block_id(&format!("{:?}", hash)).unwrap(),
+        BlockId::Hash(hash)
+    );
+    assert!(block::parse_block_id("yesterday").is_err());
+    assert!(block::parse_block_id("0xzz").is_err());
+}
+
+#[tokio::test]
+async fn test_get_block_summary_with_full_transactions() {
+    let tx_hash = format!("{:?}", H256::repeat_byte(0xab));
+    let url = common::spawn_rpc(move |method, params| {
+        let mut block = block_json(10);
+        match method {
+            "eth_getBlockByNumber" if params[0] == "safe" => {
+                if params[1] == true {
+                    block["transactions"] = json!([{
+                        "hash": tx_hash,
+                        "nonce": "0x0",
+                        "blockNumber": "0xa",
+                        "from": format!("0x{}", "aa".repeat(20)),
+                        "to": format!("0x{}", "bb".repeat(20)),
+                        "value": "0x1",
+                        "gas": "0x5208",
+                        "gasPrice": "0x1",
+                        "input": "0x",
+                        "v": "0x1b",
+                        "r": "0x1",
+                        "s": "0x1",
+                    }]);
+                } else {
+                    block["transactions"] = json!([tx_hash]);
+                }
+                block
+            }
+            _ => Value::Null,
+        }
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let id = block::parse_block_id("safe").unwrap();
+    let summary = block::get_block(&client, id, false).await.unwrap().unwrap();
+    assert_eq!(summary.number, Some(10));
+    assert_eq!(summary.timestamp, 1120);
+    assert_eq!(summary.gas_used, "21000");
+    assert_eq!(summary.gas_limit, "30000000");
+    assert_eq!(summary.base_fee_per_gas.as_deref(), Some("1000000000"));
+    assert_eq!(
+        summary.miner.as_deref(),
+        Some("0x1111111111111111111111111111111111111111")
+    );
+    assert_eq!(summary.transaction_count, 1);
+    assert_eq!(
+        summary.transactions,
+        BlockTransactions::Hashes(vec![H256::repeat_byte(0xab)])
+    );
+
+    let full = block::get_block(&client, id, true).await.unwrap().unwrap();
+    match full.transactions {
+        BlockTransactions::Full(txs) => assert_eq!(txs[0].value, 1.into()),
+        other => panic!("expected full transactions, got {:?}", other),
+    }
+
+    let missing = block::parse_block_id("12345").unwrap();
+    assert!(block::get_block(&client, missing, false)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_closest_block_binary_search() {
+    let head = 1_000_000;
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_getBlockByNumber" => {
+            counter.fetch_add(1, Ordering::SeqCst);
+            block_json(number_param(&params[0], head))
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    // Exactly on block 500000, and 5 and 7 seconds after it
+    let exact = block::closest_block(&client, 1000 + 12 * 500_000)
+        .await
+        .unwrap();
+    assert_eq!(exact.number, Some(500_000));
+    assert!(requests.load(Ordering::SeqCst) <= 23);
+    let nearer_before = block::closest_block(&client, 1005 + 12 * 500_000)
+        .await
+        .unwrap();
+    assert_eq!(nearer_before.number, Some(500_000));
+    let nearer_after = block::closest_block(&client, 1007 + 12 * 500_000)
+        .await
+        .unwrap();
+    assert_eq!(nearer_after.number, Some(500_001));
+
+    assert_eq!(
+        block::closest_block(&client, 0).await.unwrap().number,
+        Some(0)
+    );
+    assert_eq!(
+        block::closest_block(&client, u64::MAX)
+            .await
+            .unwrap()
+            .number,
+        Some(head)
+    );
+}
@@ -0,0 +1,170 @@
+//! Tests for caching immutable RPC results and batching reads.
+
+use ethers::types::{Address, BlockId, H256, U256};
+use mcp_wallet::eth_client::EthClient;
+use serde_json::{json, Value};
+use std::sync::{atomic::Ordering, Arc, Mutex};
+
+mod common;
+
+type Calls = Arc<Mutex<Vec<String>>>;
+
+fn count(calls: &Calls, method: &str) -> usize {
+    calls
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|m| *m == method)
+        .count()
+}
+
+fn block_json(number: u64) -> Value {
+    json!({
+        "number": format!("0x{:x}", number),
+        "hash": format!("{:?}", H256::from_low_u64_be(number)),
+        "parentHash": format!("{:?}", H256::from_low_u64_be(number - 1)),
+        "timestamp": "0x64",
+        "transactions": [],
+    })
+}
+
+/// A node whose finalized block is 10.
+async fn spawn_node() -> (EthClient, Calls) {
+    let calls: Calls = Arc::default();
+    let seen = calls.clone();
+    let url = common::spawn_rpc(move |method, params| {
+        seen.lock().unwrap().push(method.to_string());
+        match method {
+            "eth_chainId" => json!("0x1"),
+            "eth_getCode" if params[0] == json!(format!("{:?}", Address::repeat_byte(0xc0))) => {
+                json!("0x6001")
+            }
+            "eth_getCode" => json!("0x"),
+            "eth_getTransactionReceipt" => {
+                let hash = params[0].as_str().unwrap();
+                let block = if hash.ends_with('5') { 5 } else { 15 };
+                common::receipt_json(hash, block)
+            }
+            "eth_getBlockByNumber" if params[0] == "finalized" => block_json(10),
+            "eth_getBlockByNumber" => {
+                let number = params[0].as_str().unwrap().trim_start_matches("0x");
+                block_json(u64::from_str_radix(number, 16).unwrap())
+            }
+            "eth_getBlockByHash" => {
+                let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+                block_json(hash.to_low_u64_be())
+            }
+            _ => Value::Null,
+        }
+    })
+    .await;
+    (EthClient::new(&url).unwrap(), calls)
+}
+
+#[tokio::test]
+async fn test_chain_id_and_finalized_code_are_fetched_once() {
+    let (client, calls) = spawn_node().await;
+    for _ in 0..3 {
+        assert_eq!(client.get_chain_id().await.unwrap(), 1);
+    }
+    assert_eq!(count(&calls, "eth_chainId"), 1);
+
+    let contract = Address::repeat_byte(0xc0);
+    let account = Address::repeat_byte(0xaa);
+    let finalized = Some(BlockId::from(5u64));
+    for _ in 0..2 {
+        assert_eq!(
+            client
+                .get_code_at(contract, finalized)
+                .await
+                .unwrap()
+                .to_vec(),
+            [0x60, 0x01]
+        );
+        assert!(client
+            .get_code_at(account, finalized)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+    assert_eq!(count(&calls, "eth_getCode"), 2);
+
+    // The latest and recent code can still change
+    for _ in 0..2 {
+        client.get_code(contract).await.unwrap();
+        client
+            .get_code_at(contract, Some(BlockId::from(15u64)))
+            .await
+            .unwrap();
+    }
+    assert_eq!(count(&calls, "eth_getCode"), 6);
+}
+
+#[tokio::test]
+async fn test_only_finalized_receipts_and_blocks_are_cached() {
+    let (client, calls) = spawn_node().await;
+    let final_tx = H256::from_low_u64_be(5);
+    let recent_tx = H256::from_low_u64_be(6);
+    for _ in 0..2 {
+        let receipt = client.get_transaction_receipt(final_tx).await.unwrap();
+        assert_eq!(receipt.unwrap().block_number, Some(5.into()));
+        client.get_transaction_receipt(recent_tx).await.unwrap();
+    }
+    // One call for the finalized receipt, two for the one in block 15
+    assert_eq!(count(&calls, "eth_getTransactionReceipt"), 3);
+
+    let blocks_before = count(&calls, "eth_getBlockByNumber");
+    let block = client
+        .get_block(BlockId::from(7u64))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        client.get_block_hash(7).await.unwrap(),
+        Some(H256::from_low_u64_be(7))
+    );
+    let by_hash = client
+        .get_block(BlockId::Hash(block.hash.unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(by_hash, Some(block));
+    assert_eq!(count(&calls, "eth_getBlockByNumber"), blocks_before + 1);
+    assert_eq!(count(&calls, "eth_getBlockByHash"), 0);
+
+    // Block 15 may still be reorged away
+    client.get_block_hash(15).await.unwrap();
+    client.get_block_hash(15).await.unwrap();
+    assert_eq!(count(&calls, "eth_getBlockByNumber"), blocks_before + 3);
+}
+
+#[tokio::test]
+async fn test_balances_are_read_in_one_batch() {
+    let (url, requests) = common::spawn_flaky_rpc(Vec::new(), |method, params| {
+        match (method, params[0].as_str()) {
+            ("eth_getBalance", Some(address)) => {
+                let address: Address = address.parse().unwrap();
+                json!(format!("0x{:x}", address.as_bytes()[0]))
+            }
+            _ => common::rpc_error(-32601, "method not found", None),
+        }
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let addresses: Vec<Address> = (1..=3).map(Address::repeat_byte).collect();
+    let balances = client.get_balances(&addresses, None).await.unwrap();
+    assert_eq!(balances, [U256::from(1), U256::from(2), U256::from(3)]);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert!(client.get_balances(&[], None).await.unwrap().is_empty());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // A failed call in the batch fails the read
+    let rejecting = common::spawn_rpc(|method, _| match method {
+        "eth_getBalance" => common::rpc_error(-32000, "header not found", None),
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&rejecting).unwrap();
+    let error = client.get_balances(&addresses, None).await.unwrap_err();
+    assert!(error.to_string().contains("header not found"), "{}", error);
+}
@@ -0,0 +1,241 @@
+//! Tests for refusing to sign or send transactions for another chain.
+
+use common::{call, serve, structured};
+use ethers::{
+    signers::LocalWallet,
+    types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest, H256},
+};
+use mcp_wallet::{eth_client::EthClient, WalletError};
+use rmcp::{service::RunningService, RoleClient};
+use serde_json::{json, Value};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+mod common;
+
+/// A node that reports `chain_id` and counts the raw transactions sent to it.
+async fn spawn_node(chain_id: u64, sent: Arc<AtomicUsize>) -> String {
+    common::spawn_rpc(move |method, _| match method {
+        "eth_chainId" => json!(format!("0x{:x}", chain_id)),
+        "eth_sendRawTransaction" => {
+            sent.fetch_add(1, Ordering::SeqCst);
+            json!(format!("{:?}", H256::from_low_u64_be(7)))
+        }
+        _ => Value::Null,
+    })
+    .await
+}
+
+/// A transaction from `alice` for `chain_id`, as create_tx builds it.
+async fn create_tx(client: &RunningService<RoleClient, ()>, chain_id: Option<u64>) -> Value {
+    let mut args = json!({
+        "from": "alice",
+        "to": format!("{:?}", Address::repeat_byte(2)),
+        "value": "1",
+    });
+    if let Some(chain_id) = chain_id {
+        args["chain_id"] = json!(chain_id);
+    }
+    structured(client, "create_tx", args).await
+}
+
+async fn sign(client: &RunningService<RoleClient, ()>, tx: Value, allow: bool) -> Value {
+    let args = json!({
+        "from": "alice",
+        "tx_json": tx,
+        "simulate": false,
+        "allow_chain_mismatch": allow,
+    });
+    structured(client, "sign_tx", args).await
+}
+
+#[tokio::test]
+async fn test_sign_refuses_other_chains() {
+    let url = spawn_node(1, Arc::default()).await;
+    let client = serve(EthClient::new(&url).unwrap()).await;
+    structured(&client, "new_account", json!({ "alias": "alice" })).await;
+
+    // The expected chain is learned from the node
+    let tx = create_tx(&client, None).await;
+    assert_eq!(tx["chain_id"], 1);
+    assert!(sign(&client, tx, false).await["raw_transaction"].is_string());
+
+    let tx = create_tx(&client, Some(10)).await;
+    let err = call(
+        &client,
+        "sign_tx",
+        json!({ "from": "alice", "tx_json": tx, "simulate": false }),
+    )
+    .await
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("chain 10"), "{}", err);
+    assert!(err.contains("allow_chain_mismatch"), "{}", err);
+
+    // Unless the caller opts in
+    let signed = sign(&client, tx, true).await;
+    assert_eq!(signed["network"], "OP Mainnet");
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_send_checks_the_raw_transaction_chain() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let url = spawn_node(1, sent.clone()).await;
+    let client = serve(EthClient::new(&url).unwrap()).await;
+    structured(&client, "new_account", json!({ "alias": "alice" })).await;
+
+    let send = |raw: Value, allow: bool| {
+        let client = &client;
+        async move {
+            call(
+                client,
+                "eth_send_signed_transaction",
+                json!({
+                    "signed_transaction_hex": raw,
+                    "simulate": false,
+                    "allow_chain_mismatch": allow,
+                }),
+            )
+            .await
+        }
+    };
+
+    let tx = create_tx(&client, Some(8453)).await;
+    let raw = sign(&client, tx, true).await["raw_transaction"].clone();
+    assert!(send(raw.clone(), false).await.is_err());
+    assert_eq!(sent.load(Ordering::SeqCst), 0);
+    send(raw, true).await.unwrap();
+    assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+    // A legacy transaction without a chain id is valid on every chain
+    let signer = LocalWallet::new(&mut rand::thread_rng());
+    let legacy: TypedTransaction = TransactionRequest::new()
+        .to(Address::repeat_byte(2))
+        .value(1)
+        .nonce(0)
+        .gas(21_000)
+        .gas_price(1)
+        .into();
+    let signature = signer.sign_hash(legacy.sighash()).unwrap();
+    let raw = json!(legacy.rlp_signed(&signature).to_string());
+    let err = send(raw, false).await.unwrap_err().to_string();
+    assert!(err.contains("no chain id"), "{}", err);
+
+    let tx = create_tx(&client, None).await;
+    let raw = sign(&client, tx, false).await["raw_transaction"].clone();
+    send(raw, false).await.unwrap();
+    assert_eq!(sent.load(Ordering::SeqCst), 2);
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_configured_chain_must_match_the_node() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let url = spawn_node(1, sent.clone()).await;
+    let eth_client = EthClient::new(&url)
+        .unwrap()
+        .with_expected_chain_id(Some(10));
+    assert_eq!(eth_client.expected_chain_id().await.unwrap(), 10);
+    assert!(matches!(
+        eth_client.verify_chain_id().await,
+        Err(WalletError::ChainIdMismatch {
+            expected: 10,
+            actual: 1
+        })
+    ));
+
+    let client = serve(eth_client).await;
+    structured(&client, "new_account", json!({ "alias": "alice" })).await;
+
+    // Signing for the configured chain needs no node
+    let tx = create_tx(&client, None).await;
+    assert_eq!(tx["chain_id"], 10);
+    let raw = sign(&client, tx, false).await["raw_transaction"].clone();
+
+    // But the node serves another chain, so nothing is broadcast
+    let err = call(
+        &client,
+        "eth_send_signed_transaction",
+        json!({ "signed_transaction_hex": raw, "simulate": false }),
+    )
+    .await
+    .unwrap_err()
+    .to_string();
+    let mismatch = WalletError::ChainIdMismatch {
+        expected: 10,
+        actual: 1,
+    };
+    assert!(err.contains(&mismatch.to_string()), "{}", err);
+    let res = call(
+        &client,
+        "eth_transfer_eth",
+        json!({ "from": "alice", "to": "alice", "value_wei": "1", "simulate": false }),
+    )
+    .await;
+    assert!(res.is_err());
+    assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_replacements_check_the_chain() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let from: Arc<Mutex<String>> = Arc::default();
+    let (counter, sender) = (sent.clone(), from.clone());
+    // A node on chain 1 that knows a pending transaction for chain 8453
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_chainId" => json!("0x1"),
+        "eth_getTransactionByHash" => json!({
+            "hash": params[0],
+            "nonce": "0x0",
+            "from": *sender.lock().unwrap(),
+            "to": format!("{:?}", Address::repeat_byte(2)),
+            "value": "0x1",
+            "gas": "0x5208",
+            "maxFeePerGas": "0x64",
+            "maxPriorityFeePerGas": "0x1",
+            "type": "0x2",
+            "chainId": "0x2105",
+            "input": "0x",
+            "v": "0x0",
+            "r": "0x1",
+            "s": "0x1",
+        }),
+        "eth_sendRawTransaction" => {
+            counter.fetch_add(1, Ordering::SeqCst);
+            json!(format!("{:?}", H256::from_low_u64_be(8)))
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = serve(EthClient::new(&url).unwrap()).await;
+    let alice = structured(&client, "new_account", json!({ "alias": "alice" })).await;
+    *from.lock().unwrap() = alice["address"].as_str().unwrap().to_string();
+
+    let original = format!("{:?}", H256::from_low_u64_be(7));
+    for tool in ["speed_up_tx", "cancel_tx"] {
+        let err = call(&client, tool, json!({ "transaction_hash": original }))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("chain 8453"), "{}", err);
+    }
+    assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+    let replaced = structured(
+        &client,
+        "speed_up_tx",
+        json!({ "transaction_hash": original, "allow_chain_mismatch": true }),
+    )
+    .await;
+    assert_eq!(replaced["replaces"], json!(original));
+    assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+    client.cancel().await.unwrap();
+}
@@ -0,0 +1,218 @@
+//! Tests for the chain registry and chain labels in tool results.
+
+use ethers::types::{Address, H256};
+use mcp_wallet::{
+    chain::{ChainInfo, ChainRegistry},
+    eth_client::EthClient,
+    service::WalletHandler,
+    wallet::Wallet,
+    WalletError,
+};
+use rmcp::{model::CallToolRequestParam, serve_client, service::ServiceExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::{io::duplex, sync::Mutex};
+
+mod common;
+
+#[test]
+fn test_builtin_chains() {
+    let chains = ChainRegistry::builtin();
+    let anvil = chains.get(31337).unwrap();
+    assert_eq!(anvil.name, "Anvil");
+    assert_eq!(anvil.tx_url(H256::zero()), None);
+
+    assert_eq!(chains.find("sepolia").unwrap().chain_id, 11155111);
+    assert_eq!(chains.find("137").unwrap().currency, "POL");
+    assert!(chains.find("goerli").is_none());
+
+    let mainnet = chains.get(1).unwrap();
+    assert_eq!(
+        mainnet.tx_url(H256::from_low_u64_be(1)).unwrap(),
+        format!("https://etherscan.io/tx/{:?}", H256::from_low_u64_be(1))
+    );
+    assert_eq!(
+        mainnet.address_url(Address::repeat_byte(0xab)).unwrap(),
+        "https://etherscan.io/address/0xABaBaBaBABabABabAbAbABAbABabababaBaBABaB"
+    );
+
+    // Unknown chains are described generically and accept EIP-1559
+    let unknown = chains.describe(424242);
+    assert_eq!(unknown.name, "Chain 424242");
+    assert_eq!(unknown.currency, "ETH");
+    assert!(chains.check_eip1559(424242).is_ok());
+}
+
+#[test]
+fn test_load_chains_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let chains = ChainRegistry::load(dir.path().join("absent.json")).unwrap();
+    assert_eq!(chains, ChainRegistry::builtin());
+
+    let path = dir.path().join("chains.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "chains": [
+                { "chain_id": 7777777, "name": "Zora" },
+                { "chain_id": 31337, "name": "Local fork", "eip1559": false },
+                {
+                    "chain_id": 56,
+                    "name": "BNB Smart Chain",
+                    "currency": "BNB",
+                    "explorer_tx_url": "https://bscscan.com/tx/{hash}"
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let chains = ChainRegistry::load(&path).unwrap();
+
+    let zora = chains.get(7777777).unwrap();
+    assert_eq!((zora.currency.as_str(), zora.decimals), ("ETH", 18));
+    assert!(zora.eip1559);
+    assert_eq!(chains.get(31337).unwrap().name, "Local fork");
+    assert!(matches!(
+        chains.check_eip1559(31337),
+        Err(WalletError::Eip1559Unsupported(_))
+    ));
+    let bsc = chains.find("bnb smart chain").unwrap();
+    assert!(bsc
+        .tx_url(H256::zero())
+        .unwrap()
+        .starts_with("https://bscscan.com/tx/0x"));
+    assert_eq!(bsc.address_url(Address::zero()), None);
+    // Built-in chains not in the file are kept
+    assert_eq!(chains.get(1).unwrap().name, "Ethereum");
+
+    std::fs::write(&path, "{ not json").unwrap();
+    assert!(ChainRegistry::load(&path).is_err());
+}
+
+#[tokio::test]
+async fn test_balances_use_the_currency_decimals() {
+    let url = common::spawn_rpc(|method, _| match method {
+        "eth_chainId" => json!("0x1092"),
+        "eth_getBalance" => json!("0x1e8480"),
+        _ => Value::Null,
+    })
+    .await;
+    let chain = ChainInfo {
+        currency: "SIX".to_string(),
+        decimals: 6,
+        ..ChainInfo::unknown(4242)
+    };
+    assert_eq!(chain.format_amount(1_500_000.into()).unwrap(), "1.500000");
+    let client = common::serve_with(EthClient::new(&url).unwrap(), move |handler| {
+        let mut chains = ChainRegistry::builtin();
+        chains.extend([chain]);
+        handler.with_chains(chains)
+    })
+    .await;
+    common::structured(&client, "new_account", json!({ "alias": "alice" })).await;
+
+    let balance =
+        common::structured(&client, "eth_get_balance", json!({ "address": "alice" })).await;
+    assert_eq!(balance["balance_eth"], "2.000000");
+    assert_eq!(balance["currency"], "SIX");
+    assert_eq!(balance["network"], "Chain 4242");
+    let accounts =
+        common::structured(&client, "list_accounts", json!({ "with_balances": true })).await;
+    assert_eq!(accounts[0]["balance"], "2.000000");
+    assert_eq!(accounts[0]["currency"], "SIX");
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tools_label_outputs_and_check_chains() {
+    let url = common::spawn_rpc(|method, _| match method {
+        "eth_chainId" => json!("0x1"),
+        "eth_getBalance" => json!("0xde0b6b3a7640000"),
+        "eth_sendRawTransaction" => json!(format!("{:?}", H256::from_low_u64_be(7))),
+        _ => Value::Null,
+    })
+    .await;
+    let dir = tempfile::tempdir().unwrap();
+    let wallet = Arc::new(Mutex::new(
+        Wallet::load_or_create(dir.path().join("wallet.json")).unwrap(),
+    ));
+    let mut chains = ChainRegistry::builtin();
+    chains.extend([ChainInfo {
+        eip1559: false,
+        ..ChainInfo::unknown(56)
+    }]);
+
+    let (client_stream, server_stream) = duplex(4096);
+    let eth_client = Arc::new(EthClient::new(&url).unwrap());
+    tokio::spawn(async move {
+        let server = WalletHandler::new(wallet, eth_client)
+            .with_chains(chains)
+            .serve(server_stream)
+            .await
+            .unwrap();
+        server.waiting().await.unwrap();
+    });
+    let client = serve_client((), client_stream).await.unwrap();
+
+    let call = |name: &'static str, args: Value| {
+        let client = &client;
+        async move {
+            client
+                .call_tool(CallToolRequestParam {
+                    name: name.into(),
+                    arguments: args.as_object().cloned(),
+                })
+                .await
+        }
+    };
+
+    let balance = call(
+        "eth_get_balance",
+        json!({ "address": format!("{:?}", Address::repeat_byte(1)) }),
+    )
+    .await
+    .unwrap()
+    .structured_content
+    .unwrap();
+    assert_eq!(balance["balance_eth"], "1.000000000000000000");
+    assert_eq!(balance["currency"], "ETH");
+    assert_eq!(balance["network"], "Ethereum");
+
+    let sent = call(
+        "eth_send_signed_transaction",
+        json!({
+            "signed_transaction_hex": "0x02",
+            "simulate": false,
+            "allow_chain_mismatch": true,
+        }),
+    )
+    .await
+    .unwrap()
+    .structured_content
+    .unwrap();
+    assert_eq!(sent["network"], "Ethereum");
+    assert_eq!(
+        sent["explorer_url"],
+        format!("https://etherscan.io/tx/{:?}", H256::from_low_u64_be(7))
+    );
+
+    // No EIP-1559 transaction is built for a chain without it
+    call("new_account", json!({ "alias": "alice" }))
+        .await
+        .unwrap();
+    let res = call(
+        "create_tx",
+        json!({
+            "from": "alice",
+            "to": format!("{:?}", Address::repeat_byte(2)),
+            "value": "1",
+            "chain_id": 56,
+        }),
+    )
+    .await;
+    let err = res.unwrap_err().to_string();
+    assert!(err.contains("EIP-1559"), "{}", err);
+
+    client.cancel().await.unwrap();
+}
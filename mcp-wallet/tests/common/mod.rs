@@ -0,0 +1,342 @@
+//! Shared helpers for integration tests.
+
+#![allow(dead_code)]
+
+use futures::{SinkExt, StreamExt};
+use mcp_wallet::{eth_client::EthClient, service::WalletHandler, wallet::Wallet};
+use rmcp::{
+    model::{CallToolRequestParam, CallToolResult},
+    serve_client,
+    service::{RunningService, ServiceExt},
+    RoleClient, ServiceError,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{duplex, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, UnixListener},
+    sync::{mpsc, Mutex as AsyncMutex},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Answers a JSON-RPC call given its method and params.
+pub type RpcHandler = dyn Fn(&str, &Value) -> Value + Send + Sync;
+
+/// Key marking a handler result that is sent as a JSON-RPC error instead.
+const RPC_ERROR: &str = "__rpc_error";
+
+/// A handler result that makes the server answer with a JSON-RPC error.
+pub fn rpc_error(code: i64, message: &str, data: Option<&str>) -> Value {
+    json!({ RPC_ERROR: { "code": code, "message": message, "data": data } })
+}
+
+/// Serves JSON-RPC over HTTP on a local port and returns its URL.
+///
+/// Each request is answered with the result `handler` returns for it; a
+/// `Value::Null` result is sent as is, as nodes do for unknown hashes, and
+/// [`rpc_error`] results are sent as errors.
+pub async fn spawn_rpc<F>(handler: F) -> String
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+{
+    spawn_flaky_rpc(Vec::new(), handler).await.0
+}
+
+/// A failure the flaky RPC stand-in answers a request with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flake {
+    /// An HTTP error status with a plain text body, e.g. 429 or 503.
+    Status(u16),
+    /// The connection is reset without an answer.
+    Reset,
+}
+
+/// Like [`spawn_rpc`], but answers the first requests with `flakes`, in
+/// order. Also returns the number of HTTP requests received so far.
+pub async fn spawn_flaky_rpc<F>(flakes: Vec<Flake>, handler: F) -> (String, Arc<AtomicUsize>)
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+{
+    let handler: Arc<RpcHandler> = Arc::new(handler);
+    let flakes = Arc::new(Mutex::new(VecDeque::from(flakes)));
+    let requests = Arc::new(AtomicUsize::new(0));
+    let received = requests.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let handler = handler.clone();
+            let flakes = flakes.clone();
+            let received = received.clone();
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                        continue;
+                    };
+                    let headers = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                    let length = headers
+                        .lines()
+                        .find_map(|l| {
+                            let (k, v) = l.split_once(':')?;
+                            k.eq_ignore_ascii_case("content-length")
+                                .then(|| v.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    let body_end = header_end + 4 + length;
+                    if buf.len() < body_end {
+                        continue;
+                    }
+                    let request: Value = serde_json::from_slice(&buf[header_end + 4..body_end])
+                        .unwrap_or(Value::Null);
+                    buf.drain(..body_end);
+                    received.fetch_add(1, Ordering::SeqCst);
+
+                    let flake = flakes.lock().unwrap().pop_front();
+                    match flake {
+                        Some(Flake::Status(status)) => {
+                            let body = "upstream unavailable";
+                            let response = format!(
+                                "HTTP/1.1 {} Error\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+                                status,
+                                body.len(),
+                                body
+                            );
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+                        Some(Flake::Reset) => {
+                            // Closing with a zero linger sends a RST
+                            let _ = socket.set_linger(Some(Duration::ZERO));
+                            return;
+                        }
+                        None => {}
+                    }
+
+                    let body = match &request {
+                        Value::Array(calls) => {
+                            Value::Array(calls.iter().map(|c| answer(&*handler, c)).collect())
+                        }
+                        call => answer(&*handler, call),
+                    }
+                    .to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (url, requests)
+}
+
+/// Answers one JSON-RPC call with `handler`.
+fn answer(handler: &RpcHandler, call: &Value) -> Value {
+    let result = handler(call["method"].as_str().unwrap_or_default(), &call["params"]);
+    match result.get(RPC_ERROR) {
+        Some(error) => json!({ "jsonrpc": "2.0", "id": call["id"], "error": error }),
+        None => json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }),
+    }
+}
+
+/// Serves JSON-RPC over a WebSocket on a local port, answering calls like
+/// [`spawn_rpc`]. Messages sent to the returned channel are pushed to the
+/// connected client as they are, e.g. `eth_subscription` notifications.
+pub async fn spawn_ws_rpc<F>(handler: F) -> (String, mpsc::UnboundedSender<Value>)
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (push, mut pushed) = mpsc::unbounded_channel::<Value>();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        loop {
+            tokio::select! {
+                message = ws.next() => {
+                    let Some(Ok(Message::Text(text))) = message else {
+                        return;
+                    };
+                    let call: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+                    let reply = answer(&handler, &call).to_string();
+                    if ws.send(Message::Text(reply)).await.is_err() {
+                        return;
+                    }
+                }
+                Some(message) = pushed.recv() => {
+                    if ws.send(Message::Text(message.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    (url, push)
+}
+
+/// Serves JSON-RPC on a Unix domain socket at `path`, like a node's IPC
+/// endpoint, answering calls like [`spawn_rpc`]. Messages sent to the returned
+/// channel are pushed to the connected client as they are; dropping it closes
+/// the connection, like a node shutting down.
+pub async fn spawn_ipc_rpc<F>(path: &Path, handler: F) -> mpsc::UnboundedSender<Value>
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+{
+    let listener = UnixListener::bind(path).unwrap();
+    let (push, mut pushed) = mpsc::unbounded_channel::<Value>();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            tokio::select! {
+                read = socket.read(&mut chunk) => {
+                    let n = read.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    // Requests are JSON values back to back, without framing
+                    let mut calls = serde_json::Deserializer::from_slice(&buf).into_iter::<Value>();
+                    let mut replies = Vec::new();
+                    for call in calls.by_ref().map_while(Result::ok) {
+                        replies.push(answer(&handler, &call));
+                    }
+                    let consumed = calls.byte_offset();
+                    buf.drain(..consumed);
+                    for reply in replies {
+                        if socket.write_all(reply.to_string().as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                message = pushed.recv() => {
+                    let Some(message) = message else {
+                        return;
+                    };
+                    if socket.write_all(message.to_string().as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    push
+}
+
+/// A successful receipt for `hash` mined in block `block_number`.
+pub fn receipt_json(hash: &str, block_number: u64) -> Value {
+    json!({
+        "transactionHash": hash,
+        "transactionIndex": "0x0",
+        "blockHash": format!("0x{:064x}", block_number),
+        "blockNumber": format!("0x{:x}", block_number),
+        "from": format!("0x{}", "aa".repeat(20)),
+        "to": format!("0x{}", "bb".repeat(20)),
+        "cumulativeGasUsed": "0x5208",
+        "gasUsed": "0x5208",
+        "logs": [],
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "status": "0x1",
+        "effectiveGasPrice": "0x1",
+        "type": "0x2",
+    })
+}
+
+/// Serves a wallet stored in a temporary directory and connected to
+/// `eth_client` over MCP, and returns a client for it.
+pub async fn serve(eth_client: EthClient) -> RunningService<RoleClient, ()> {
+    serve_with(eth_client, |handler| handler).await
+}
+
+/// Like [`serve`], with the handler set up further by `configure`, e.g. with
+/// more chains or a journal.
+pub async fn serve_with<F>(eth_client: EthClient, configure: F) -> RunningService<RoleClient, ()>
+where
+    F: FnOnce(WalletHandler) -> WalletHandler + Send + 'static,
+{
+    let dir = tempfile::tempdir().unwrap();
+    let wallet = Wallet::load_or_create(dir.path().join("wallet.json")).unwrap();
+    serve_wallet_with(wallet, eth_client, Some(dir), configure).await
+}
+
+/// Serves `wallet` as is, e.g. one parsed from an older wallet file.
+pub async fn serve_wallet(wallet: Wallet, eth_client: EthClient) -> RunningService<RoleClient, ()> {
+    serve_wallet_with(wallet, eth_client, None, |handler| handler).await
+}
+
+/// Serves `wallet`, keeping `dir` (the wallet's directory) until the server
+/// stops.
+async fn serve_wallet_with<F>(
+    wallet: Wallet,
+    eth_client: EthClient,
+    dir: Option<tempfile::TempDir>,
+    configure: F,
+) -> RunningService<RoleClient, ()>
+where
+    F: FnOnce(WalletHandler) -> WalletHandler + Send + 'static,
+{
+    let wallet = Arc::new(AsyncMutex::new(wallet));
+    let eth_client = Arc::new(eth_client);
+    let (client_stream, server_stream) = duplex(4096);
+    tokio::spawn(async move {
+        let _dir = dir;
+        let server = configure(WalletHandler::new(wallet, eth_client))
+            .serve(server_stream)
+            .await
+            .unwrap();
+        server.waiting().await.unwrap();
+    });
+    serve_client((), client_stream).await.unwrap()
+}
+
+/// Calls the tool `name` with `args`, a JSON object.
+pub async fn call(
+    client: &RunningService<RoleClient, ()>,
+    name: &'static str,
+    args: Value,
+) -> Result<CallToolResult, ServiceError> {
+    client
+        .call_tool(CallToolRequestParam {
+            name: name.into(),
+            arguments: args.as_object().cloned(),
+        })
+        .await
+}
+
+/// Calls the tool `name` and returns its structured result, panicking if it
+/// fails.
+pub async fn structured(
+    client: &RunningService<RoleClient, ()>,
+    name: &'static str,
+    args: Value,
+) -> Value {
+    call(client, name, args)
+        .await
+        .unwrap_or_else(|e| panic!("{} failed: {}", name, e))
+        .structured_content
+        .unwrap()
+}
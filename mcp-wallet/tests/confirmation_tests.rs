@@ -0,0 +1,261 @@
+//! Tests for waiting on confirmations.
+
+use ethers::types::{Address, H256};
+use mcp_wallet::{
+    confirmation::{TxWatcher, WaitOutcome, WatchState, DROP_AFTER_MISSES},
+    eth_client::EthClient,
+    journal::{self, TxJournal, TxStatus},
+    models::Eip1559TransactionRequest,
+    wallet::Wallet,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+mod common;
+
+/// The parts of a chain the watcher looks at.
+#[derive(Default)]
+struct Chain {
+    head: u64,
+    /// Block number and hash of the receipt the node serves.
+    receipt: Option<(u64, H256)>,
+    canonical: HashMap<u64, H256>,
+    tx_known: bool,
+    latest_nonce: u64,
+}
+
+async fn spawn_chain(chain: Arc<Mutex<Chain>>) -> EthClient {
+    let url = common::spawn_rpc(move |method, params| {
+        let chain = chain.lock().unwrap();
+        match method {
+            "eth_blockNumber" => json!(format!("0x{:x}", chain.head)),
+            "eth_getTransactionCount" => json!(format!("0x{:x}", chain.latest_nonce)),
+            "eth_getTransactionReceipt" => match chain.receipt {
+                Some((number, hash)) => {
+                    let mut receipt = common::receipt_json(params[0].as_str().unwrap(), number);
+                    receipt["blockHash"] = json!(format!("{:?}", hash));
+                    receipt
+                }
+                None => Value::Null,
+            },
+            "eth_getBlockByNumber" => {
+                // Tags such as `finalized` are not served
+                let number = params[0]
+                    .as_str()
+                    .and_then(|n| u64::from_str_radix(n.trim_start_matches("0x"), 16).ok());
+                match number.and_then(|n| chain.canonical.get(&n).map(|hash| (n, hash))) {
+                    Some((number, hash)) => json!({
+                        "hash": format!("{:?}", hash),
+                        "number": format!("0x{:x}", number),
+                    }),
+                    None => Value::Null,
+                }
+            }
+            "eth_getTransactionByHash" if chain.tx_known => json!({
+                "hash": params[0],
+                "nonce": "0x5",
+                "from": format!("{:?}", Address::repeat_byte(0xaa)),
+                "to": format!("{:?}", Address::repeat_byte(0xbb)),
+                "value": "0x0",
+                "gas": "0x5208",
+                "gasPrice": "0x1",
+                "input": "0x",
+                "v": "0x1b",
+                "r": "0x1",
+                "s": "0x1",
+            }),
+            _ => Value::Null,
+        }
+    })
+    .await;
+    EthClient::new(&url).unwrap()
+}
+
+fn progress(state: WatchState) -> (u64, Option<u64>, u32) {
+    match state {
+        WatchState::Waiting(p) => (p.confirmations, p.block_number, p.reorgs),
+        WatchState::Done(outcome) => panic!("unexpected outcome {:?}", outcome),
+    }
+}
+
+#[tokio::test]
+async fn test_waits_for_confirmations() {
+    let block = H256::repeat_byte(0x10);
+    let chain = Arc::new(Mutex::new(Chain {
+        head: 10,
+        receipt: Some((10, block)),
+        canonical: HashMap::from([(10, block)]),
+        ..Default::default()
+    }));
+    let client = spawn_chain(chain.clone()).await;
+    let mut watcher = TxWatcher::new(H256::repeat_byte(1), 3);
+
+    assert_eq!(
+        progress(watcher.poll(&client).await.unwrap()),
+        (1, Some(10), 0)
+    );
+    // Giving up now reports where the transaction stood
+    match watcher.timed_out() {
+        WaitOutcome::TimedOut { progress } => assert_eq!(progress.confirmations, 1),
+        other => panic!("unexpected outcome {:?}", other),
+    }
+
+    chain.lock().unwrap().head = 12;
+    match watcher.poll(&client).await.unwrap() {
+        WatchState::Done(WaitOutcome::Confirmed {
+            success,
+            confirmations,
+            reorgs,
+            ..
+        }) => {
+            assert!(success);
+            assert_eq!(confirmations, 3);
+            assert_eq!(reorgs, 0);
+        }
+        other => panic!("unexpected state {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_detects_reorg() {
+    let (old_block, new_block) = (H256::repeat_byte(0x10), H256::repeat_byte(0x11));
+    let chain = Arc::new(Mutex::new(Chain {
+        head: 10,
+        receipt: Some((10, old_block)),
+        canonical: HashMap::from([(10, old_block)]),
+        tx_known: true,
+        ..Default::default()
+    }));
+    let client = spawn_chain(chain.clone()).await;
+    let mut watcher = TxWatcher::new(H256::repeat_byte(1), 2);
+    assert_eq!(
+        progress(watcher.poll(&client).await.unwrap()),
+        (1, Some(10), 0)
+    );
+
+    // Block 10 is replaced while the node still serves the old receipt
+    chain.lock().unwrap().canonical.insert(10, new_block);
+    assert_eq!(progress(watcher.poll(&client).await.unwrap()), (0, None, 1));
+    assert_eq!(progress(watcher.poll(&client).await.unwrap()), (0, None, 1));
+
+    // The transaction is mined again in block 11
+    {
+        let mut chain = chain.lock().unwrap();
+        let block = H256::repeat_byte(0x12);
+        chain.receipt = Some((11, block));
+        chain.canonical.insert(11, block);
+        chain.head = 12;
+    }
+    match watcher.poll(&client).await.unwrap() {
+        WatchState::Done(WaitOutcome::Confirmed {
+            confirmations,
+            reorgs,
+            receipt,
+            ..
+        }) => {
+            assert_eq!(confirmations, 2);
+            assert_eq!(reorgs, 1);
+            assert_eq!(receipt.block_number.map(|b| b.as_u64()), Some(11));
+        }
+        other => panic!("unexpected state {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_reports_replaced_and_dropped() {
+    let chain = Arc::new(Mutex::new(Chain {
+        head: 10,
+        tx_known: true,
+        latest_nonce: 5,
+        ..Default::default()
+    }));
+    let client = spawn_chain(chain.clone()).await;
+
+    // The sender comes from the node on the first poll
+    let mut watcher = TxWatcher::new(H256::repeat_byte(1), 1);
+    assert_eq!(progress(watcher.poll(&client).await.unwrap()), (0, None, 0));
+    chain.lock().unwrap().latest_nonce = 6;
+    assert_eq!(
+        watcher.poll(&client).await.unwrap(),
+        WatchState::Done(WaitOutcome::Replaced {
+            nonce: 5,
+            reorgs: 0
+        })
+    );
+
+    // A transaction the node forgot, with its nonce still unused
+    let mut watcher = TxWatcher::new(H256::repeat_byte(2), 1).with_sender(Address::zero(), 6);
+    chain.lock().unwrap().tx_known = false;
+    for _ in 1..DROP_AFTER_MISSES {
+        progress(watcher.poll(&client).await.unwrap());
+    }
+    assert_eq!(
+        watcher.poll(&client).await.unwrap(),
+        WatchState::Done(WaitOutcome::Dropped { reorgs: 0 })
+    );
+}
+
+#[tokio::test]
+async fn test_wait_for_transaction_saves_the_outcome() {
+    let mut wallet = Wallet::new();
+    let from = wallet
+        .import_private_key("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .unwrap();
+    let tx = Eip1559TransactionRequest {
+        chain_id: 31337,
+        to: Some(Address::repeat_byte(0xbb)),
+        nonce: 5.into(),
+        max_fee_per_gas: 100.into(),
+        ..Default::default()
+    };
+    let raw = wallet
+        .sign_reserved(&tx, &format!("0x{:x}", from))
+        .await
+        .unwrap()
+        .raw_transaction;
+    let dir = tempdir().unwrap();
+    let path = journal::history_path(&dir.path().join("wallet.json"));
+    let mut journal = TxJournal::load_or_create(&path).unwrap();
+    let hash = journal.record(&raw, true, None).unwrap().hash;
+    journal.save().unwrap();
+
+    let block = H256::repeat_byte(0x10);
+    let chain = Arc::new(Mutex::new(Chain {
+        head: 10,
+        receipt: Some((10, block)),
+        canonical: HashMap::from([(10, block)]),
+        tx_known: true,
+        latest_nonce: 6,
+    }));
+    let client = common::serve_with(spawn_chain(chain).await, move |handler| {
+        handler.with_journal(journal)
+    })
+    .await;
+
+    // Out of range durations are capped instead of overflowing the clock
+    let result = common::structured(
+        &client,
+        "wait_for_transaction",
+        json!({ "transaction_hash": format!("{:?}", hash), "timeout_secs": u64::MAX }),
+    )
+    .await;
+    assert_eq!(result["status"], "confirmed");
+    let saved = TxJournal::load_or_create(&path).unwrap();
+    assert_eq!(saved.get(hash).unwrap().status, TxStatus::Mined);
+    assert_eq!(saved.get(hash).unwrap().block_number, Some(10));
+
+    let result = common::structured(
+        &client,
+        "wait_for_transaction",
+        json!({
+            "transaction_hash": format!("{:?}", hash),
+            "confirmations": 5,
+            "timeout_secs": 0,
+            "poll_interval_ms": u64::MAX,
+        }),
+    )
+    .await;
+    assert_eq!(result["status"], "timed_out");
+}
@@ -0,0 +1,192 @@
+//! Tests for clients over several RPC endpoints.
+
+use mcp_wallet::{eth_client::EthClient, failover::FailoverOptions, profile::WalletProfile};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+mod common;
+
+const ADDRESS: &str = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+
+/// Serves every balance as `wei`.
+async fn balance_rpc(wei: u64) -> String {
+    common::spawn_rpc(move |method, _| match method {
+        "eth_getBalance" => json!(format!("0x{:x}", wei)),
+        "eth_blockNumber" => json!("0x10"),
+        _ => Value::Null,
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_failing_endpoint_falls_back_and_loses_priority() {
+    // Nothing listens on port 9
+    let dead = "http://127.0.0.1:9";
+    let good = balance_rpc(1_000_000_000_000_000_000).await;
+    let profile = WalletProfile {
+        fallback_rpc_urls: vec![good.clone()],
+        ..WalletProfile::new(dead)
+    };
+    let client = EthClient::from_profile(&profile).unwrap();
+    assert_eq!(client.url(), dead);
+
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "1.000000000000000000"
+    );
+    let health = client.endpoint_health();
+    assert_eq!((health[0].failures, health[0].successes), (1, 0));
+    assert_eq!((health[1].failures, health[1].successes), (0, 1));
+    assert!(health[0].score < health[1].score);
+
+    // The healthier fallback is now tried first, so the dead one is skipped
+    assert_eq!(client.get_current_block().await.unwrap(), 16);
+    let health = client.endpoint_health();
+    assert_eq!(health[0].failures, 1);
+    assert_eq!(health[1].successes, 2);
+
+    // A JSON-RPC error is the node's answer and is not retried elsewhere
+    let reverting =
+        common::spawn_rpc(|_, _| common::rpc_error(-32000, "execution reverted", None)).await;
+    let client =
+        EthClient::with_endpoints(&[&reverting, &good], FailoverOptions::default()).unwrap();
+    assert!(client.get_balance(ADDRESS).await.is_err());
+    assert_eq!(client.endpoint_health()[1].successes, 0);
+}
+
+#[tokio::test]
+async fn test_slow_endpoint_times_out_and_fails_over() {
+    // Accepts connections but never answers
+    let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let silent_url = format!("http://{}", silent.local_addr().unwrap());
+    let good = balance_rpc(5).await;
+
+    let options = FailoverOptions {
+        timeout: Duration::from_millis(200),
+        ..Default::default()
+    };
+    let client = EthClient::with_endpoints(&[&silent_url, &good], options).unwrap();
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "0.000000000000000005"
+    );
+    assert_eq!(client.endpoint_health()[0].failures, 1);
+
+    // Every endpoint failing reports the last error
+    let options = FailoverOptions {
+        timeout: Duration::from_millis(200),
+        ..Default::default()
+    };
+    let client = EthClient::with_endpoints(&[&silent_url], options).unwrap();
+    let error = client.get_balance(ADDRESS).await.unwrap_err();
+    assert!(error.to_string().contains("timed out"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_quorum_reads_need_agreeing_endpoints() {
+    let honest = balance_rpc(7).await;
+    let also_honest = balance_rpc(7).await;
+    let lying = balance_rpc(1_000).await;
+    let quorum = |quorum| FailoverOptions {
+        quorum: Some(quorum),
+        ..Default::default()
+    };
+
+    let client = EthClient::with_endpoints(&[&lying, &honest, &also_honest], quorum(2)).unwrap();
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "0.000000000000000007"
+    );
+    // Every endpoint was asked for its head and the balance
+    assert!(client.endpoint_health().iter().all(|e| e.successes == 2));
+
+    let client =
+        EthClient::with_endpoints(&[&lying, &honest, "http://127.0.0.1:9"], quorum(2)).unwrap();
+    let error = client.get_balance(ADDRESS).await.unwrap_err();
+    assert!(error.to_string().contains("No quorum"), "{}", error);
+
+    // Block numbers differ between synced nodes and are not compared
+    assert_eq!(client.get_current_block().await.unwrap(), 16);
+
+    assert!(EthClient::with_endpoints(&[&honest], quorum(2)).is_err());
+    assert!(EthClient::with_endpoints(&[&honest], quorum(0)).is_err());
+}
+
+#[tokio::test]
+async fn test_quorum_reads_of_the_latest_block_use_a_common_head() {
+    // Two honest nodes a block apart; the balance changed in block 0x11
+    let at_head = |head: u64| {
+        common::spawn_rpc(move |method, params| match method {
+            "eth_blockNumber" => json!(format!("0x{:x}", head)),
+            "eth_getBalance" => {
+                let block = match params[1].as_str() {
+                    Some("latest") | None => head,
+                    Some(number) => u64::from_str_radix(&number[2..], 16).unwrap(),
+                };
+                json!(if block >= 0x11 { "0x9" } else { "0x7" })
+            }
+            _ => Value::Null,
+        })
+    };
+    let behind = at_head(0x10).await;
+    let ahead = at_head(0x11).await;
+    let options = FailoverOptions {
+        quorum: Some(2),
+        ..Default::default()
+    };
+    let client = EthClient::with_endpoints(&[&behind, &ahead], options).unwrap();
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "0.000000000000000007"
+    );
+    // Reads at a given block are left alone
+    assert_eq!(
+        client
+            .get_balance_at(ADDRESS.parse().unwrap(), Some(0x11.into()))
+            .await
+            .unwrap(),
+        9.into()
+    );
+}
+
+#[tokio::test]
+async fn test_endpoints_on_another_chain_are_failed() {
+    let on_chain = |chain_id: u64, wei: u64| {
+        common::spawn_rpc(move |method, _| match method {
+            "eth_chainId" => json!(format!("0x{:x}", chain_id)),
+            "eth_getBalance" => json!(format!("0x{:x}", wei)),
+            "eth_blockNumber" => json!("0x10"),
+            _ => Value::Null,
+        })
+    };
+    let mainnet = on_chain(1, 7).await;
+    let sepolia = on_chain(11155111, 1_000).await;
+
+    // The misconfigured endpoint comes first, but never answers for chain 1
+    let client = EthClient::with_endpoints(&[&sepolia, &mainnet], FailoverOptions::default())
+        .unwrap()
+        .with_expected_chain_id(Some(1));
+    for _ in 0..2 {
+        assert_eq!(
+            client.get_balance(ADDRESS).await.unwrap(),
+            "0.000000000000000007"
+        );
+    }
+    let health = client.endpoint_health();
+    assert_eq!((health[0].successes, health[0].failures), (0, 1));
+    assert_eq!((health[1].successes, health[1].failures), (2, 0));
+    assert_eq!(client.verify_chain_id().await.unwrap(), 1);
+
+    // Without an expected chain id the primary endpoint's counts, so the
+    // other one cannot make up a quorum
+    let options = FailoverOptions {
+        quorum: Some(2),
+        ..Default::default()
+    };
+    let client = EthClient::with_endpoints(&[&mainnet, &sepolia], options).unwrap();
+    let error = client.get_balance(ADDRESS).await.unwrap_err();
+    assert!(error.to_string().contains("No quorum"), "{}", error);
+    // Both the head and the balance read failed on it
+    assert_eq!(client.endpoint_health()[1].failures, 2);
+}
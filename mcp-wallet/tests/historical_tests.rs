@@ -0,0 +1,267 @@
+//! Tests for reading chain state at past blocks.
+
+use common::{call, structured};
+use ethers::{
+    abi::{self, Token},
+    types::{Address, H256, U256},
+};
+use mcp_wallet::{block, eth_client::EthClient};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex as StdMutex};
+
+mod common;
+
+type Calls = Arc<StdMutex<Vec<(String, Value)>>>;
+
+const HEAD: u64 = 100;
+const GENESIS_TIME: u64 = 1_000;
+
+/// Block `n` is mined at `GENESIS_TIME + 12 * n`.
+fn block_json(number: u64) -> Value {
+    json!({
+        "number": format!("0x{:x}", number),
+        "hash": format!("{:?}", H256::from_low_u64_be(number + 1)),
+        "parentHash": format!("{:?}", H256::from_low_u64_be(number)),
+        "timestamp": format!("0x{:x}", GENESIS_TIME + 12 * number),
+        "transactions": [],
+    })
+}
+
+/// The block number a block param refers to: numbers, tags and hashes.
+fn block_number(param: &Value) -> u64 {
+    match param {
+        Value::String(tag) if tag == "latest" || tag == "pending" => HEAD,
+        Value::String(tag) if tag == "finalized" || tag == "safe" => HEAD - 64,
+        Value::String(hex) => u64::from_str_radix(hex.trim_start_matches("0x"), 16).unwrap(),
+        Value::Object(id) => {
+            let hash: H256 = serde_json::from_value(id["blockHash"].clone()).unwrap();
+            hash.to_low_u64_be() - 1
+        }
+        _ => panic!("unexpected block {}", param),
+    }
+}
+
+/// An archive node where the balance of every account is its block number
+/// in wei, which keeps no state before block 10 unless it is an archive.
+async fn spawn_node(calls: Calls) -> String {
+    common::spawn_rpc(move |method, params| {
+        calls
+            .lock()
+            .unwrap()
+            .push((method.to_string(), params.clone()));
+        let at = |index: usize| block_number(&params[index]);
+        let pruned = |number: u64| number < 10;
+        match method {
+            "eth_chainId" => json!("0x1"),
+            "eth_getBlockByNumber" => block_json(at(0).min(HEAD)),
+            "eth_getBalance" if pruned(at(1)) => {
+                common::rpc_error(-32000, "missing trie node abc (path )", None)
+            }
+            "eth_getBalance" => json!(format!("0x{:x}", at(1))),
+            // A contract deployed at block 50
+            "eth_getCode" if at(1) >= 50 => json!("0x6001"),
+            "eth_getCode" => json!("0x"),
+            "eth_getStorageAt" => {
+                let slot: U256 = serde_json::from_value(params[1].clone()).unwrap();
+                json!(format!(
+                    "{:?}",
+                    H256::from_low_u64_be(slot.as_u64() + at(2))
+                ))
+            }
+            "eth_call" => {
+                let data = params[0]["input"]
+                    .as_str()
+                    .or(params[0]["data"].as_str())
+                    .unwrap_or_default();
+                if data.starts_with("0xdeadbeef") {
+                    // Error("nope")
+                    let revert = [
+                        &[0x08, 0xc3, 0x79, 0xa0][..],
+                        &abi::encode(&[Token::String("nope".into())]),
+                    ]
+                    .concat();
+                    common::rpc_error(
+                        3,
+                        "execution reverted: nope",
+                        Some(&format!("0x{}", hex::encode(revert))),
+                    )
+                } else {
+                    let output = abi::encode(&[Token::Uint(U256::from(at(1)))]);
+                    json!(format!("0x{}", hex::encode(output)))
+                }
+            }
+            _ => Value::Null,
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_balances_at_blocks() {
+    let calls: Calls = Arc::default();
+    let url = spawn_node(calls.clone()).await;
+    let client = common::serve(EthClient::new(&url).unwrap()).await;
+    let address = format!("{:?}", Address::repeat_byte(0xaa));
+
+    let cases = [
+        (json!(null), "100"),
+        (json!(42), "42"),
+        (json!("0x2b"), "43"),
+        (json!("finalized"), "36"),
+        (json!(format!("{:?}", H256::from_low_u64_be(45))), "44"),
+    ];
+    for (block, wei) in cases {
+        let result = structured(
+            &client,
+            "eth_get_balance",
+            json!({ "address": address, "block": block }),
+        )
+        .await;
+        let expected = ethers::utils::format_ether(U256::from_dec_str(wei).unwrap());
+        assert_eq!(result["balance_eth"], expected, "block {}", block);
+        assert_eq!(result["currency"], "ETH");
+    }
+    // Numbers go out as hex, hashes as EIP-1898 objects
+    let params: Vec<Value> = calls
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(method, _)| method == "eth_getBalance")
+        .map(|(_, params)| params[1].clone())
+        .collect();
+    assert_eq!(params[1], "0x2a");
+    assert_eq!(params[3], "finalized");
+    assert!(params[4]["blockHash"].is_string());
+
+    // Pruned state points to an archive node
+    let err = call(
+        &client,
+        "eth_get_balance",
+        json!({ "address": address, "block": 3 }),
+    )
+    .await
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("archive node"), "{}", err);
+
+    let res = call(
+        &client,
+        "eth_get_balance",
+        json!({ "address": address, "block": "yesterday" }),
+    )
+    .await;
+    assert!(res.is_err());
+
+    structured(&client, "new_account", json!({ "alias": "alice" })).await;
+    let accounts = structured(
+        &client,
+        "list_accounts",
+        json!({ "with_balances": true, "block": 20 }),
+    )
+    .await;
+    assert_eq!(accounts[0]["balance"], "0.000000000000000020");
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_call_code_and_storage_at_blocks() {
+    let url = spawn_node(Arc::default()).await;
+    let client = common::serve(EthClient::new(&url).unwrap()).await;
+    let contract = format!("{:?}", Address::repeat_byte(0xc0));
+
+    let code = structured(&client, "eth_get_code", json!({ "address": contract })).await;
+    assert_eq!(code["is_contract"], true);
+    assert_eq!(code["size"], 2);
+    let code = structured(
+        &client,
+        "eth_get_code",
+        json!({ "address": contract, "block": 49 }),
+    )
+    .await;
+    assert_eq!(code["is_contract"], false);
+    assert_eq!(code["code"], "0x");
+
+    let storage = structured(
+        &client,
+        "eth_get_storage_at",
+        json!({ "address": contract, "slot": "0x3", "block": "60" }),
+    )
+    .await;
+    assert_eq!(storage["slot"], format!("{:?}", H256::from_low_u64_be(3)));
+    assert_eq!(storage["value_decimal"], "63");
+    let res = call(
+        &client,
+        "eth_get_storage_at",
+        json!({ "address": contract, "slot": "zero" }),
+    )
+    .await;
+    assert!(res.is_err());
+
+    // balanceOf(address), decoded as a well-known function
+    let data = format!("0x70a08231{:0>64}", "aa");
+    let called = structured(
+        &client,
+        "eth_call",
+        json!({ "to": contract, "data": data, "block": 77 }),
+    )
+    .await;
+    assert_eq!(called["success"], true);
+    assert_eq!(called["output"], format!("0x{:064x}", 77));
+    assert_eq!(called["decoded"]["signature"], "balanceOf(address)");
+    assert_eq!(called["block"], 77);
+
+    let reverted = structured(
+        &client,
+        "eth_call",
+        json!({ "to": contract, "data": "0xdeadbeef" }),
+    )
+    .await;
+    assert_eq!(reverted["success"], false);
+    assert_eq!(reverted["revert_reason"]["message"], "nope");
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_balance_at_time() {
+    let url = spawn_node(Arc::default()).await;
+    let eth_client = EthClient::new(&url).unwrap();
+
+    // The block whose state was current, not the closest one
+    let at = block::block_at(&eth_client, GENESIS_TIME + 12 * 30 + 11)
+        .await
+        .unwrap();
+    assert_eq!(at.number, Some(30));
+    let closest = block::closest_block(&eth_client, GENESIS_TIME + 12 * 30 + 11)
+        .await
+        .unwrap();
+    assert_eq!(closest.number, Some(31));
+    let at = block::block_at(&eth_client, GENESIS_TIME + 12 * 40)
+        .await
+        .unwrap();
+    assert_eq!(at.number, Some(40));
+    let head = block::block_at(&eth_client, u64::MAX).await.unwrap();
+    assert_eq!(head.number, Some(HEAD));
+    assert!(block::block_at(&eth_client, GENESIS_TIME - 1)
+        .await
+        .is_err());
+
+    let client = common::serve(EthClient::new(&url).unwrap()).await;
+    let result = structured(
+        &client,
+        "eth_get_balance_at_time",
+        json!({
+            "address": format!("{:?}", Address::repeat_byte(0xaa)),
+            "timestamp": GENESIS_TIME + 12 * 57 + 3,
+        }),
+    )
+    .await;
+    assert_eq!(result["block_number"], 57);
+    assert_eq!(result["block_timestamp"], GENESIS_TIME + 12 * 57);
+    assert_eq!(result["balance_wei"], "57");
+    assert_eq!(result["currency"], "ETH");
+    assert_eq!(result["network"], "Ethereum");
+
+    client.cancel().await.unwrap();
+}
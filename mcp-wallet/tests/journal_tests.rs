@@ -0,0 +1,187 @@
+//! Tests for the persistent transaction journal.
+
+use ethers::types::{Address, H256, U256};
+use mcp_wallet::{
+    eth_client::EthClient,
+    journal::{self, TxJournal, TxQuery, TxStatus},
+    models::Eip1559TransactionRequest,
+    wallet::Wallet,
+};
+use serde_json::json;
+use tempfile::tempdir;
+use tokio::sync::Mutex;
+
+mod common;
+
+const PK: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const PK2: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+/// Signs a transfer on chain 31337 and returns its raw bytes.
+async fn sign(wallet: &Wallet, from: Address, to: Address, nonce: u64, fee: u64) -> Vec<u8> {
+    let tx = Eip1559TransactionRequest {
+        chain_id: 31337,
+        to: Some(to),
+        value: U256::from(5),
+        nonce: nonce.into(),
+        max_fee_per_gas: fee.into(),
+        ..Default::default()
+    };
+    wallet
+        .sign_reserved(&tx, &format!("0x{:x}", from))
+        .await
+        .unwrap()
+        .raw_transaction
+}
+
+#[tokio::test]
+async fn test_record_decodes_and_persists() {
+    let mut wallet = Wallet::new();
+    let from = wallet.import_private_key(PK).unwrap();
+    let to = Address::repeat_byte(0xbb);
+    let raw = sign(&wallet, from, to, 3, 100).await;
+
+    let dir = tempdir().unwrap();
+    let path = journal::history_path(&dir.path().join(".mcp-wallet.json"));
+    assert_eq!(path, dir.path().join(".mcp-wallet.history.json"));
+    let mut journal = TxJournal::load_or_create(&path).unwrap();
+
+    let signed = journal.record(&raw, false, None).unwrap();
+    assert_eq!(signed.status, TxStatus::Signed);
+    assert_eq!(signed.from, from);
+    assert_eq!(signed.to, Some(to));
+    assert_eq!(signed.value, U256::from(5));
+    assert_eq!(signed.nonce, 3);
+    assert_eq!(signed.chain_id, 31337);
+    assert_eq!(signed.sent_at, None);
+
+    // Sending the same bytes later updates the existing record
+    let sent = journal.record(&raw, true, None).unwrap();
+    assert_eq!(sent.hash, signed.hash);
+    assert_eq!(sent.status, TxStatus::Pending);
+    assert!(sent.sent_at.is_some());
+    assert_eq!(journal.query(&TxQuery::default()).len(), 1);
+
+    journal.save().unwrap();
+    let stored: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        stored["transactions"][0]["from"],
+        "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+    );
+    let reloaded = TxJournal::load_or_create(&path).unwrap();
+    assert_eq!(reloaded.get(sent.hash), Some(&sent));
+}
+
+#[tokio::test]
+async fn test_query_filters() {
+    let mut wallet = Wallet::new();
+    let alice = wallet.import_private_key(PK).unwrap();
+    let bob = wallet.import_private_key(PK2).unwrap();
+    let carol = Address::repeat_byte(0xcc);
+
+    let mut journal = TxJournal::new();
+    let first = journal
+        .record(&sign(&wallet, alice, bob, 0, 100).await, true, None)
+        .unwrap();
+    let second = journal
+        .record(&sign(&wallet, alice, carol, 1, 100).await, false, None)
+        .unwrap();
+    let third = journal
+        .record(&sign(&wallet, bob, carol, 0, 100).await, true, None)
+        .unwrap();
+
+    let hashes =
+        |query: TxQuery| -> Vec<H256> { journal.query(&query).iter().map(|r| r.hash).collect() };
+    // Sender or recipient, newest first
+    let for_bob = hashes(TxQuery {
+        account: Some(bob),
+        ..Default::default()
+    });
+    assert_eq!(for_bob, vec![third.hash, first.hash]);
+    let signed_only = hashes(TxQuery {
+        status: Some(TxStatus::Signed),
+        ..Default::default()
+    });
+    assert_eq!(signed_only, vec![second.hash]);
+    let latest = hashes(TxQuery {
+        limit: Some(1),
+        ..Default::default()
+    });
+    assert_eq!(latest, vec![third.hash]);
+
+    let in_range = hashes(TxQuery {
+        since: Some(first.created_at),
+        until: Some(third.created_at),
+        ..Default::default()
+    });
+    assert_eq!(in_range.len(), 3);
+    let future = hashes(TxQuery {
+        since: Some(third.created_at + 3600),
+        ..Default::default()
+    });
+    assert!(future.is_empty());
+}
+
+#[tokio::test]
+async fn test_refresh_updates_statuses() {
+    let mut wallet = Wallet::new();
+    let from = wallet.import_private_key(PK).unwrap();
+    let to = Address::repeat_byte(0xbb);
+
+    let mut journal = TxJournal::new();
+    let mined = journal
+        .record(&sign(&wallet, from, to, 0, 100).await, true, None)
+        .unwrap();
+    let slow = journal
+        .record(&sign(&wallet, from, to, 1, 100).await, true, None)
+        .unwrap();
+    let faster = journal
+        .record(
+            &sign(&wallet, from, to, 1, 200).await,
+            true,
+            Some(slow.hash),
+        )
+        .unwrap();
+    let dropped = journal
+        .record(&sign(&wallet, from, to, 2, 100).await, true, None)
+        .unwrap();
+    let unsent = journal
+        .record(&sign(&wallet, from, to, 3, 100).await, false, None)
+        .unwrap();
+
+    // Nonces 0 and 1 are used; the replacement of nonce 1 reverted
+    let (mined_hash, faster_hash) = (format!("{:?}", mined.hash), format!("{:?}", faster.hash));
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_chainId" => json!("0x7a69"),
+        "eth_getTransactionCount" => json!("0x2"),
+        "eth_getTransactionReceipt" if params[0] == json!(mined_hash) => {
+            common::receipt_json(&mined_hash, 10)
+        }
+        "eth_getTransactionReceipt" if params[0] == json!(faster_hash) => {
+            let mut receipt = common::receipt_json(&faster_hash, 11);
+            receipt["status"] = json!("0x0");
+            receipt
+        }
+        _ => serde_json::Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let journal = Mutex::new(journal);
+    let changed = journal::refresh(&journal, &client, None).await.unwrap();
+    assert_eq!(changed, 4);
+
+    let journal = journal.into_inner();
+    let status = |hash| journal.get(hash).unwrap().status;
+    assert_eq!(status(mined.hash), TxStatus::Mined);
+    assert_eq!(journal.get(mined.hash).unwrap().block_number, Some(10));
+    assert_eq!(status(faster.hash), TxStatus::Failed);
+    assert_eq!(status(slow.hash), TxStatus::Replaced);
+    assert_eq!(
+        journal.get(slow.hash).unwrap().replaced_by,
+        Some(faster.hash)
+    );
+    assert_eq!(journal.get(faster.hash).unwrap().replaces, Some(slow.hash));
+    assert_eq!(status(dropped.hash), TxStatus::Dropped);
+    assert_eq!(status(unsent.hash), TxStatus::Signed);
+}
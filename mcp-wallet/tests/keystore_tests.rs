@@ -0,0 +1,94 @@
+//! Tests for V3 keystore import and export.
+
+use ethers::signers::{LocalWallet, Signer};
+use mcp_wallet::{error::WalletError, keystore, wallet::Wallet};
+use std::path::Path;
+use tempfile::tempdir;
+
+const PK: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const PK2: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+#[test]
+fn test_export_and_import_keystore_roundtrip() {
+    let dir = tempdir().unwrap();
+    let mut source = Wallet::new();
+    let address = source.import_private_key(PK).unwrap();
+    source.add_alias(address, "deployer".to_string()).unwrap();
+
+    let path = source
+        .export_keystore("deployer", dir.path(), "pw", None)
+        .unwrap();
+    assert_eq!(path, dir.path().join("deployer"));
+
+    // The file is a standard keystore that ethers (and cast/geth) can decrypt
+    let decrypted = LocalWallet::decrypt_keystore(&path, "pw").unwrap();
+    assert_eq!(decrypted.address(), address);
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json["version"], 3);
+    assert_eq!(json["address"], hex::encode(address));
+
+    // Existing files are not overwritten
+    let err = source
+        .export_keystore("deployer", dir.path(), "pw", None)
+        .unwrap_err();
+    assert!(matches!(err, WalletError::KeystoreError(_)));
+
+    let mut target = Wallet::new();
+    let report = target.import_keystore(&path, "pw").unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.imported[0].alias.as_deref(), Some("deployer"));
+    let (account, resolved) = target.get_account("deployer").unwrap();
+    assert_eq!(resolved, address);
+    assert!(account.private_key.is_some());
+
+    // A wrong password fails for a single file
+    let err = Wallet::new().import_keystore(&path, "nope").unwrap_err();
+    assert!(matches!(err, WalletError::KeystoreError(_)));
+}
+
+#[test]
+fn test_import_keystore_directory() {
+    let dir = tempdir().unwrap();
+    let mut source = Wallet::new();
+    let first = source.import_private_key(PK).unwrap();
+    let second = source.import_private_key(PK2).unwrap();
+    source
+        .export_keystore(
+            &format!("0x{:x}", first),
+            dir.path(),
+            "pw",
+            Some("hot-key.json"),
+        )
+        .unwrap();
+    source
+        .export_keystore(
+            &format!("0x{:x}", second),
+            dir.path(),
+            "other",
+            Some("UTC--2024-01-01T00-00-00.000000000Z--abc"),
+        )
+        .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not a keystore").unwrap();
+
+    // The alias from the first file is already taken by another account
+    let mut target = Wallet::new();
+    target.create_account("hot_key").unwrap();
+    let report = target.import_keystore(dir.path(), "pw").unwrap();
+
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.imported[0].alias, None);
+    assert_eq!(report.alias_conflicts, vec!["hot_key".to_string()]);
+    assert_eq!(report.skipped.len(), 2);
+    assert!(target.get_account(&format!("0x{:x}", first)).is_some());
+    assert!(target.get_account(&format!("0x{:x}", second)).is_none());
+}
+
+#[test]
+fn test_alias_from_file_name() {
+    let alias = |name: &str| keystore::alias_from_file_name(Path::new(name));
+    assert_eq!(alias("/keys/deployer"), Some("deployer".to_string()));
+    assert_eq!(alias("my-key.json"), Some("my_key".to_string()));
+    assert_eq!(alias("UTC--2024-01-01T00-00-00Z--f39fd6e5"), None);
+    assert_eq!(alias("a name with spaces"), None);
+}
@@ -0,0 +1,199 @@
+//! Tests for chunked, paginated and decoded log queries.
+
+use ethers::{
+    abi::{self, Token},
+    types::{Address, Log, H256, U256},
+    utils::keccak256,
+};
+use mcp_wallet::{
+    decode::{decode_log_with, parse_signatures},
+    eth_client::EthClient,
+    logs::{self, Cursor, LogFilter},
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+const TOKEN: u8 = 0x70;
+const PROVIDER_RANGE_LIMIT: u64 = 2_000;
+
+fn hex_number(value: &Value) -> u64 {
+    u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+}
+
+/// Serves one `Transfer` log every 2500 blocks, three in block 5000, and
+/// rejects requests spanning more than 2000 blocks.
+async fn spawn_log_node(requests: Arc<Mutex<Vec<(u64, u64)>>>) -> String {
+    let transfer = format!(
+        "{:?}",
+        H256::from(keccak256("Transfer(address,address,uint256)"))
+    );
+    common::spawn_rpc(move |method, params| {
+        if method != "eth_getLogs" {
+            return Value::Null;
+        }
+        let filter = &params[0];
+        let (from, to) = (
+            hex_number(&filter["fromBlock"]),
+            hex_number(&filter["toBlock"]),
+        );
+        requests.lock().unwrap().push((from, to));
+        assert_eq!(
+            filter["address"],
+            json!([format!("{:?}", Address::repeat_byte(TOKEN))])
+        );
+        assert_eq!(filter["topics"][0], json!(transfer));
+        if to - from + 1 > PROVIDER_RANGE_LIMIT {
+            return common::rpc_error(-32005, "query exceeds max block range 2000", None);
+        }
+        let logs: Vec<Value> = (from..=to)
+            .filter(|block| block % 2_500 == 0)
+            .flat_map(|block| {
+                let count = if block == 5_000 { 3 } else { 1 };
+                (0..count).map(move |index| (block, index))
+            })
+            .map(|(block, index)| {
+                json!({
+                    "address": format!("{:?}", Address::repeat_byte(TOKEN)),
+                    "topics": [transfer, topic(0xaa), topic(0xbb)],
+                    "data": format!("0x{}", hex::encode(amount(block + index))),
+                    "blockNumber": format!("0x{:x}", block),
+                    "blockHash": format!("{:?}", H256::from_low_u64_be(block)),
+                    "transactionHash": format!("{:?}", H256::from_low_u64_be(block * 10 + index)),
+                    "transactionIndex": "0x0",
+                    "logIndex": format!("0x{:x}", index),
+                    "removed": false,
+                })
+            })
+            .collect();
+        json!(logs)
+    })
+    .await
+}
+
+fn topic(byte: u8) -> String {
+    format!("{:?}", H256::from(Address::repeat_byte(byte)))
+}
+
+fn amount(value: u64) -> Vec<u8> {
+    abi::encode(&[Token::Uint(U256::from(value))])
+}
+
+fn transfer_filter(from_block: u64, to_block: u64) -> LogFilter {
+    let mut filter = LogFilter {
+        addresses: vec![Address::repeat_byte(TOKEN)],
+        from_block,
+        to_block,
+        ..Default::default()
+    };
+    filter.topics[0] = Some(vec![H256::from(keccak256(
+        "Transfer(address,address,uint256)",
+    ))]);
+    filter
+}
+
+#[test]
+fn test_custom_event_decoding_and_cursors() {
+    let abi =
+        parse_signatures(&["Swap(address indexed sender, uint256 amountIn, uint256 amountOut)"])
+            .unwrap();
+    let swap = abi.event("Swap").unwrap();
+    let log = Log {
+        address: Address::repeat_byte(1),
+        topics: vec![swap.signature(), H256::from(Address::repeat_byte(0xaa))],
+        data: abi::encode(&[Token::Uint(U256::from(10)), Token::Uint(U256::from(20))]).into(),
+        ..Default::default()
+    };
+    let decoded = decode_log_with(&log, &abi);
+    assert_eq!(decoded.event.as_deref(), Some("Swap"));
+    assert_eq!(decoded.args.unwrap()["amountOut"], "20");
+
+    // Keywords are kept, so whole human-readable ABIs parse as well
+    let abi = parse_signatures(&[
+        "function swap(uint256 amount)",
+        "event Sync(uint112 reserve0)",
+    ])
+    .unwrap();
+    assert!(abi.function("swap").is_ok());
+    assert!(parse_signatures(&["Broken(uint"]).is_err());
+
+    let cursor: Cursor = "5000:2".parse().unwrap();
+    assert_eq!(
+        cursor,
+        Cursor {
+            block: 5_000,
+            log_index: 2
+        }
+    );
+    assert_eq!(cursor.to_string(), "5000:2");
+    assert!("5000".parse::<Cursor>().is_err());
+}
+
+#[tokio::test]
+async fn test_get_logs_splits_ranges_over_the_provider_limit() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let url = spawn_log_node(requests.clone()).await;
+    let client = EthClient::new(&url).unwrap();
+
+    let page = logs::fetch_page(&client, &transfer_filter(0, 20_000), None, None, 1_000)
+        .await
+        .unwrap();
+    assert_eq!(page.next_cursor, None);
+    let positions: Vec<(Option<u64>, Option<u64>)> = page
+        .logs
+        .iter()
+        .map(|l| (l.block_number, l.log.log_index))
+        .collect();
+    assert_eq!(positions.len(), 11);
+    assert_eq!(
+        positions[2..5],
+        [
+            (Some(5_000), Some(0)),
+            (Some(5_000), Some(1)),
+            (Some(5_000), Some(2))
+        ]
+    );
+    assert_eq!(page.logs[0].log.event.as_deref(), Some("Transfer"));
+
+    // The accepted requests cover the range exactly once
+    let requests = requests.lock().unwrap();
+    let accepted: Vec<&(u64, u64)> = requests
+        .iter()
+        .filter(|(from, to)| to - from < PROVIDER_RANGE_LIMIT)
+        .collect();
+    assert_eq!(accepted.first().unwrap().0, 0);
+    assert_eq!(accepted.last().unwrap().1, 20_000);
+    assert!(accepted.windows(2).all(|w| w[1].0 == w[0].1 + 1));
+}
+
+#[tokio::test]
+async fn test_get_logs_pages_resume_inside_a_block() {
+    let url = spawn_log_node(Arc::default()).await;
+    let client = EthClient::new(&url).unwrap();
+    let filter = transfer_filter(2_000, 8_000);
+
+    let mut cursor = None;
+    let mut pages = Vec::new();
+    let mut seen = Vec::new();
+    loop {
+        let page = logs::fetch_page(&client, &filter, None, cursor, 2)
+            .await
+            .unwrap();
+        seen.extend(page.logs.iter().map(|l| l.transaction_hash.unwrap()));
+        pages.push(page.next_cursor.map(|c| c.to_string()));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    // Blocks 2500, 5000 (three logs) and 7500
+    assert_eq!(
+        pages,
+        [Some("5000:1".to_string()), Some("7500:0".to_string()), None]
+    );
+    let mut unique = seen.clone();
+    unique.dedup();
+    assert_eq!(unique.len(), 5);
+    assert_eq!(seen.len(), 5);
+}
@@ -14,7 +14,12 @@ async fn test_mcp_client_workflow() {
 
     // Create a new wallet and handler
     let wallet = Arc::new(Mutex::new(Wallet::new()));
-    let eth_client = Arc::new(EthClient::new("http://127.0.0.1:8545").unwrap());
+    // No node is running, so the chain to sign for is configured
+    let eth_client = Arc::new(
+        EthClient::new("http://127.0.0.1:8545")
+            .unwrap()
+            .with_expected_chain_id(Some(1)),
+    );
 
     // Spawn the server to run in the background
     let server_wallet = wallet.clone();
@@ -73,7 +78,7 @@ async fn test_mcp_client_workflow() {
     let accounts: Vec<Value> = serde_json::from_value(accounts_value).unwrap();
     assert_eq!(accounts[0]["address"], address);
     assert_eq!(accounts[0]["aliases"][0], "testaccount");
-    assert_eq!(accounts[0]["nonce"], 0);
+    assert_eq!(accounts[0]["nonces"], json!({}));
 
     // 5. Create a transaction
     let mut args = Map::new();
@@ -99,6 +104,8 @@ async fn test_mcp_client_workflow() {
     let mut args = Map::new();
     args.insert("from".to_string(), json!("testaccount"));
     args.insert("tx_json".to_string(), tx_json);
+    // No node is running, so skip the pre-signing simulation
+    args.insert("simulate".to_string(), json!(false));
     let sign_tx_result = client
         .call_tool(CallToolRequestParam {
             name: "sign_tx".into(),
@@ -122,7 +129,7 @@ async fn test_mcp_client_workflow() {
 
     let accounts_json_after = list_accounts_result_after.structured_content.unwrap();
     let accounts_after: Vec<Value> = serde_json::from_value(accounts_json_after).unwrap();
-    assert_eq!(accounts_after[0]["nonce"], 1);
+    assert_eq!(accounts_after[0]["nonces"], json!({ "1": 1 }));
 
     // 8. Shutdown
     client.cancel().await.unwrap();
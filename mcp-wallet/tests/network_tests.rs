@@ -0,0 +1,228 @@
+//! Tests for switching networks at runtime.
+
+use common::{call, structured};
+use ethers::types::{Address, H256};
+use mcp_wallet::{
+    chain::{ChainInfo, ChainRegistry},
+    eth_client::EthClient,
+    WalletError,
+};
+use rmcp::{service::RunningService, RoleClient};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+/// A node that reports `chain_id` and knows one mined transaction.
+async fn spawn_node(chain_id: u64) -> String {
+    common::spawn_rpc(move |method, params| match method {
+        "eth_chainId" => json!(format!("0x{:x}", chain_id)),
+        "eth_getTransactionReceipt" => common::receipt_json(params[0].as_str().unwrap(), 3),
+        _ => Value::Null,
+    })
+    .await
+}
+
+/// Serves a wallet connected to `url` that knows the chains in `networks`.
+async fn serve(url: &str, networks: Vec<ChainInfo>) -> RunningService<RoleClient, ()> {
+    let mut chains = ChainRegistry::builtin();
+    chains.extend(networks);
+    common::serve_with(EthClient::new(url).unwrap(), move |handler| {
+        handler.with_chains(chains)
+    })
+    .await
+}
+
+fn with_rpc(chain_id: u64, url: &str) -> ChainInfo {
+    let chain = ChainRegistry::builtin()
+        .get(chain_id)
+        .cloned()
+        .unwrap_or_else(|| ChainInfo::unknown(chain_id));
+    ChainInfo {
+        rpc_urls: vec![url.to_string()],
+        ..chain
+    }
+}
+
+#[tokio::test]
+async fn test_list_and_switch_networks() {
+    let mainnet = spawn_node(1).await;
+    let sepolia = spawn_node(11155111).await;
+    let client = serve(
+        &mainnet,
+        vec![with_rpc(1, &mainnet), with_rpc(11155111, &sepolia)],
+    )
+    .await;
+
+    let listed = structured(&client, "list_networks", json!({})).await;
+    assert_eq!(listed["active"], 1);
+    let names: Vec<&str> = listed["networks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["name"].as_str().unwrap())
+        .collect();
+    // Anvil comes with its default local endpoint
+    assert_eq!(names, ["Ethereum", "Anvil", "Sepolia"]);
+
+    let switched = structured(&client, "switch_network", json!({ "network": "sepolia" })).await;
+    assert_eq!(switched["chain_id"], 11155111);
+    assert_eq!(switched["rpc_url"], sepolia);
+
+    let current = structured(&client, "current_network", json!({})).await;
+    assert_eq!(current["name"], "Sepolia");
+    assert_eq!(current["rpc_url"], sepolia);
+    assert_eq!(current["endpoints"][0]["url"], sepolia);
+
+    // By chain id, as a number
+    structured(&client, "switch_network", json!({ "chain_id": 1 })).await;
+    let current = structured(&client, "current_network", json!({})).await;
+    assert_eq!(current["name"], "Ethereum");
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_switch_network_checks_chain_id() {
+    let mainnet = spawn_node(1).await;
+    // Configured as Base, but the node serves mainnet
+    let client = serve(&mainnet, vec![with_rpc(8453, &mainnet)]).await;
+
+    let err = call(&client, "switch_network", json!({ "network": "base" }))
+        .await
+        .unwrap_err()
+        .to_string();
+    let expected = WalletError::ChainIdMismatch {
+        expected: 8453,
+        actual: 1,
+    };
+    assert!(err.contains(&expected.to_string()), "{}", err);
+
+    // Unknown networks and chains without endpoints are refused
+    for network in ["goerli", "optimism", "42161"] {
+        let res = call(&client, "switch_network", json!({ "network": network })).await;
+        assert!(res.is_err(), "{}", network);
+    }
+
+    let current = structured(&client, "current_network", json!({})).await;
+    assert_eq!(current["chain_id"], 1);
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_switch_network_forgets_replacements() {
+    let from: Arc<Mutex<String>> = Arc::default();
+    let sender = from.clone();
+    // A node on mainnet that knows a pending transaction of alice
+    let mainnet = common::spawn_rpc(move |method, params| match method {
+        "eth_chainId" => json!("0x1"),
+        "eth_getTransactionByHash" => json!({
+            "hash": params[0],
+            "nonce": "0x0",
+            "from": *sender.lock().unwrap(),
+            "to": format!("{:?}", Address::repeat_byte(2)),
+            "value": "0x1",
+            "gas": "0x5208",
+            "maxFeePerGas": "0x64",
+            "maxPriorityFeePerGas": "0x1",
+            "type": "0x2",
+            "chainId": "0x1",
+            "input": "0x",
+            "v": "0x0",
+            "r": "0x1",
+            "s": "0x1",
+        }),
+        "eth_sendRawTransaction" => json!(format!("{:?}", H256::from_low_u64_be(8))),
+        "eth_getTransactionCount" => json!("0x0"),
+        _ => Value::Null,
+    })
+    .await;
+    let sepolia = spawn_node(11155111).await;
+    let client = serve(
+        &mainnet,
+        vec![with_rpc(1, &mainnet), with_rpc(11155111, &sepolia)],
+    )
+    .await;
+    let alice = structured(&client, "new_account", json!({ "alias": "alice" })).await;
+    *from.lock().unwrap() = alice["address"].as_str().unwrap().to_string();
+
+    let original = format!("{:?}", H256::from_low_u64_be(7));
+    structured(
+        &client,
+        "speed_up_tx",
+        json!({ "transaction_hash": original }),
+    )
+    .await;
+    let status = json!({ "transaction_hash": original });
+    let pending = structured(&client, "get_replacement_status", status.clone()).await;
+    assert_eq!(pending["status"], "pending");
+
+    // The group belongs to mainnet and is not looked up on Sepolia
+    structured(&client, "switch_network", json!({ "network": "sepolia" })).await;
+    let err = call(&client, "get_replacement_status", status)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("No speed-up or cancel recorded"), "{}", err);
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_transaction_results_name_the_network() {
+    let mainnet = spawn_node(1).await;
+    let sepolia = spawn_node(11155111).await;
+    let client = serve(&mainnet, vec![with_rpc(11155111, &sepolia)]).await;
+    let hash = format!("{:?}", H256::from_low_u64_be(9));
+
+    let receipt = structured(
+        &client,
+        "eth_get_transaction_receipt",
+        json!({ "transaction_hash": hash }),
+    )
+    .await;
+    assert_eq!(receipt["network"], "Ethereum");
+
+    structured(&client, "switch_network", json!({ "network": "Sepolia" })).await;
+    let receipt = structured(
+        &client,
+        "eth_get_transaction_receipt",
+        json!({ "transaction_hash": hash }),
+    )
+    .await;
+    assert_eq!(receipt["network"], "Sepolia");
+
+    // create_tx names the transaction's chain, and sign_tx takes its output as
+    // is once told the chain is not the active one
+    structured(&client, "new_account", json!({ "alias": "alice" })).await;
+    let tx = structured(
+        &client,
+        "create_tx",
+        json!({
+            "from": "alice",
+            "to": format!("{:?}", Address::repeat_byte(2)),
+            "value": "1",
+            "chain_id": 8453,
+        }),
+    )
+    .await;
+    assert_eq!(tx["network"], "Base");
+    let signed = structured(
+        &client,
+        "sign_tx",
+        json!({
+            "from": "alice",
+            "tx_json": tx,
+            "simulate": false,
+            "allow_chain_mismatch": true,
+        }),
+    )
+    .await;
+    assert_eq!(signed["network"], "Base");
+
+    let listed = structured(&client, "list_transactions", json!({})).await;
+    assert_eq!(listed["transactions"][0]["network"], "Base");
+
+    client.cancel().await.unwrap();
+}
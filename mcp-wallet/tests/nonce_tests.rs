@@ -0,0 +1,198 @@
+//! Tests for nonce reconciliation and reservation.
+
+use common::Flake;
+use ethers::{types::Address, utils::keccak256};
+use mcp_wallet::{
+    eth_client::EthClient,
+    nonce::{is_rejected_send, reconcile, NonceManager, NonceStatus, OnchainNonces},
+    wallet::Wallet,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+mod common;
+
+const PK: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Serves chain id 1 and fixed latest/pending transaction counts.
+async fn spawn_counts(latest: u64, pending: u64) -> String {
+    common::spawn_rpc(move |method, params| {
+        let count = match (method, params[1].as_str()) {
+            ("eth_chainId", _) => 1,
+            (_, Some("pending")) => pending,
+            _ => latest,
+        };
+        json!(format!("0x{:x}", count))
+    })
+    .await
+}
+
+#[test]
+fn test_reconcile_statuses() {
+    let address = Address::zero();
+    let chain = OnchainNonces {
+        latest: 5,
+        pending: 7,
+    };
+
+    let drifted = reconcile(address, 1, 3, chain, false);
+    assert_eq!(drifted.status, NonceStatus::Drifted);
+    assert_eq!(drifted.next, 7);
+
+    let pending = reconcile(address, 1, 7, chain, false);
+    assert_eq!(pending.status, NonceStatus::Pending);
+    assert_eq!(pending.next, 7);
+
+    let gap = reconcile(address, 1, 10, chain, false);
+    assert_eq!(gap.status, NonceStatus::Gap);
+    assert_eq!(gap.gap.map(|g| (g.from, g.to)), Some((7, 10)));
+    assert_eq!(gap.next, 10);
+    assert_eq!(reconcile(address, 1, 10, chain, true).next, 7);
+
+    let synced = OnchainNonces {
+        latest: 7,
+        pending: 7,
+    };
+    assert_eq!(
+        reconcile(address, 1, 7, synced, false).status,
+        NonceStatus::InSync
+    );
+}
+
+#[tokio::test]
+async fn test_concurrent_reservations_are_distinct() {
+    let mut wallet = Wallet::new();
+    let address = wallet.import_private_key(PK).unwrap();
+    let wallet = Arc::new(Mutex::new(wallet));
+    let nonces = NonceManager::new();
+
+    let mut tasks = Vec::new();
+    for _ in 0..16 {
+        let wallet = wallet.clone();
+        let nonces = nonces.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut wallet = wallet.lock().await;
+            nonces.reserve(&mut wallet, 1, address).await.unwrap()
+        }));
+    }
+    let mut reserved = Vec::new();
+    for task in tasks {
+        reserved.push(task.await.unwrap());
+    }
+    reserved.sort();
+    assert_eq!(reserved, (0..16).collect::<Vec<_>>());
+
+    // A released nonce in the middle is handed out again first; releasing the
+    // top reservations moves the next nonce back
+    let mut guard = wallet.lock().await;
+    nonces.release(&mut guard, 1, address, 4).await.unwrap();
+    assert_eq!(nonces.reserve(&mut guard, 1, address).await.unwrap(), 4);
+    nonces.release(&mut guard, 1, address, 14).await.unwrap();
+    nonces.release(&mut guard, 1, address, 15).await.unwrap();
+    assert_eq!(guard.list_accounts()[0].1.nonce(1), 14);
+}
+
+#[tokio::test]
+async fn test_sync_adopts_chain_nonce() {
+    let url = spawn_counts(3, 5).await;
+    let client = EthClient::new(&url).unwrap();
+
+    let mut wallet = Wallet::new();
+    let address = wallet.import_private_key(PK).unwrap();
+    wallet.create_account("watch_me").unwrap();
+    let wallet = Mutex::new(wallet);
+    let nonces = NonceManager::new();
+
+    let results = nonces.sync_all(&wallet, &client, false).await.unwrap();
+    assert_eq!(results.len(), 2);
+    let sync = nonces
+        .sync(&wallet, &client, 1, address, false)
+        .await
+        .unwrap();
+    assert_eq!(sync.chain.latest, 3);
+    assert_eq!(sync.status, NonceStatus::Pending);
+
+    // Another tool sent transactions; the next reservation continues after them
+    let mut guard = wallet.lock().await;
+    assert_eq!(nonces.reserve(&mut guard, 1, address).await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn test_only_rejected_sends_give_their_nonce_back() {
+    let raw = "0x02c0";
+    for (message, rejected) in [
+        ("insufficient funds for gas * price + value", true),
+        ("nonce too low", false),
+        ("replacement transaction underpriced", false),
+    ] {
+        let url = common::spawn_rpc(move |_, _| common::rpc_error(-32000, message, None)).await;
+        let client = EthClient::new(&url).unwrap();
+        let err = client.send_signed_transaction(raw).await.unwrap_err();
+        assert_eq!(is_rejected_send(&err), rejected, "{}", message);
+    }
+
+    // A server error may come after the node took the transaction
+    let (url, _) = common::spawn_flaky_rpc(vec![Flake::Status(502)], |_, _| {
+        common::rpc_error(-32000, "insufficient funds", None)
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+    let err = client.send_signed_transaction(raw).await.unwrap_err();
+    assert!(!is_rejected_send(&err));
+
+    // A node that already has the transaction is as good as one that takes it
+    let url = common::spawn_rpc(|_, _| common::rpc_error(-32000, "already known", None)).await;
+    let client = EthClient::new(&url).unwrap();
+    assert_eq!(
+        client.send_signed_transaction(raw).await.unwrap(),
+        keccak256([0x02, 0xc0]).into()
+    );
+}
+
+fn legacy_wallet() -> Wallet {
+    let wallet_json = json!({
+        "accounts": {
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266": {
+                "private_key": PK.trim_start_matches("0x"),
+                "nonce": 4,
+                "aliases": ["old"]
+            }
+        },
+        "aliases": { "old": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266" }
+    });
+    serde_json::from_value(wallet_json).unwrap()
+}
+
+#[test]
+fn test_legacy_nonce_migrates_to_first_chain() {
+    let mut wallet = legacy_wallet();
+
+    let (account, _) = wallet.get_account("old").unwrap();
+    assert_eq!(account.legacy_nonce, Some(4));
+    assert_eq!(account.nonce(31337), 4);
+
+    // The first chain used takes over the old nonce; other chains start fresh
+    wallet.set_nonce("old", 31337, 5).unwrap();
+    let (account, _) = wallet.get_account("old").unwrap();
+    assert_eq!(account.legacy_nonce, None);
+    assert_eq!(account.nonce(11155111), 0);
+
+    let saved = serde_json::to_value(&wallet).unwrap();
+    let stored = &saved["accounts"]["0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"];
+    assert_eq!(stored["chains"]["31337"]["nonce"], 5);
+    assert!(stored.get("nonce").is_none());
+}
+
+#[tokio::test]
+async fn test_list_accounts_shows_the_legacy_nonce() {
+    let client = common::serve_wallet(
+        legacy_wallet(),
+        EthClient::new("http://127.0.0.1:1").unwrap(),
+    )
+    .await;
+    let accounts = common::structured(&client, "list_accounts", json!({})).await;
+    assert_eq!(accounts[0]["nonces"], json!({}));
+    assert_eq!(accounts[0]["legacy_nonce"], 4);
+    client.cancel().await.unwrap();
+}
@@ -0,0 +1,210 @@
+//! Tests for named wallet profiles and runtime profile switching.
+
+use mcp_wallet::{
+    eth_client::EthClient,
+    profile::{ProfileRegistry, WalletProfile, DEFAULT_PROFILE},
+    service::WalletHandler,
+    wallet::Wallet,
+    WalletError,
+};
+use rmcp::{model::CallToolRequestParam, serve_client, service::ServiceExt};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tokio::{io::duplex, sync::Mutex};
+
+#[test]
+fn test_load_profiles_and_select() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("profiles.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "default": "dev",
+            "profiles": {
+                "dev": { "rpc_url": "http://127.0.0.1:8545", "chain_id": 31337 },
+                "sepolia-testing": {
+                    "wallet_file": "/tmp/sepolia.json",
+                    "rpc_url": "https://rpc.sepolia.org"
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let registry = ProfileRegistry::load(&path).unwrap();
+    let (name, profile) = registry.select(None).unwrap();
+    assert_eq!(name, "dev");
+    assert_eq!(profile.chain_id, Some(31337));
+
+    let (name, profile) = registry.select(Some("sepolia-testing")).unwrap();
+    assert_eq!(name, "sepolia-testing");
+    assert_eq!(
+        profile.wallet_path(&name).unwrap(),
+        std::path::PathBuf::from("/tmp/sepolia.json")
+    );
+
+    assert!(matches!(
+        registry.select(Some("demo")),
+        Err(WalletError::ProfileNotFound(_))
+    ));
+}
+
+#[test]
+fn test_missing_profiles_file_and_invalid_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut registry = ProfileRegistry::load(dir.path().join("absent.json")).unwrap();
+    assert_eq!(registry, ProfileRegistry::new());
+
+    registry
+        .insert_if_missing(DEFAULT_PROFILE, WalletProfile::new("http://127.0.0.1:8545"))
+        .unwrap();
+    let (name, _) = registry.select(None).unwrap();
+    assert_eq!(name, DEFAULT_PROFILE);
+
+    let err = registry
+        .insert("bad name!", WalletProfile::new("http://127.0.0.1:8545"))
+        .unwrap_err();
+    assert!(matches!(err, WalletError::InvalidProfileName(_)));
+}
+
+#[tokio::test]
+async fn test_switch_profile_via_mcp() {
+    let dir = tempfile::tempdir().unwrap();
+    let dev_file = dir.path().join("dev.json");
+    let demo_file = dir.path().join("demo.json");
+
+    let mut registry = ProfileRegistry::new();
+    registry
+        .insert(
+            "dev",
+            WalletProfile {
+                wallet_file: Some(dev_file.clone()),
+                rpc_url: "http://127.0.0.1:8545".to_string(),
+                chain_id: Some(31337),
+                ..WalletProfile::new("")
+            },
+        )
+        .unwrap();
+    registry
+        .insert(
+            "demo",
+            WalletProfile {
+                wallet_file: Some(demo_file.clone()),
+                rpc_url: "http://127.0.0.1:9545".to_string(),
+                chain_id: None,
+                ..WalletProfile::new("")
+            },
+        )
+        .unwrap();
+
+    let wallet = Arc::new(Mutex::new(Wallet::load_or_create(&dev_file).unwrap()));
+    let eth_client = Arc::new(EthClient::new("http://127.0.0.1:8545").unwrap());
+
+    let (client_stream, server_stream) = duplex(4096);
+    let server_wallet = wallet.clone();
+    tokio::spawn(async move {
+        let server = WalletHandler::new(server_wallet, eth_client)
+            .with_profiles(registry, "dev")
+            .serve(server_stream)
+            .await
+            .unwrap();
+        server.waiting().await.unwrap();
+    });
+    let client = serve_client((), client_stream).await.unwrap();
+
+    // Create an account in the dev profile
+    let mut args = Map::new();
+    args.insert("alias".to_string(), json!("devacc"));
+    client
+        .call_tool(CallToolRequestParam {
+            name: "new_account".into(),
+            arguments: Some(args),
+        })
+        .await
+        .expect("new_account should succeed");
+
+    let listed = client
+        .call_tool(CallToolRequestParam {
+            name: "list_profiles".into(),
+            arguments: None,
+        })
+        .await
+        .expect("list_profiles should succeed")
+        .structured_content
+        .unwrap();
+    assert_eq!(listed["active"], "dev");
+    assert_eq!(listed["profiles"].as_array().unwrap().len(), 2);
+
+    // Switch to demo: the dev wallet is saved and the demo wallet is empty
+    let mut args = Map::new();
+    args.insert("name".to_string(), json!("demo"));
+    client
+        .call_tool(CallToolRequestParam {
+            name: "switch_profile".into(),
+            arguments: Some(args),
+        })
+        .await
+        .expect("switch_profile should succeed");
+    assert!(dev_file.exists(), "dev wallet should be saved on switch");
+
+    let accounts = client
+        .call_tool(CallToolRequestParam {
+            name: "list_accounts".into(),
+            arguments: None,
+        })
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    assert!(accounts.as_array().unwrap().is_empty());
+
+    let current = client
+        .call_tool(CallToolRequestParam {
+            name: "current_profile".into(),
+            arguments: None,
+        })
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    assert_eq!(current["name"], "demo");
+    assert_eq!(current["rpc_url"], "http://127.0.0.1:9545");
+
+    // Switch back: the dev account is loaded from disk again
+    let mut args = Map::new();
+    args.insert("name".to_string(), json!("dev"));
+    client
+        .call_tool(CallToolRequestParam {
+            name: "switch_profile".into(),
+            arguments: Some(args),
+        })
+        .await
+        .expect("switch_profile back should succeed");
+    let accounts: Vec<Value> = serde_json::from_value(
+        client
+            .call_tool(CallToolRequestParam {
+                name: "list_accounts".into(),
+                arguments: None,
+            })
+            .await
+            .unwrap()
+            .structured_content
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0]["aliases"][0], "devacc");
+
+    // Unknown profiles are rejected
+    let mut args = Map::new();
+    args.insert("name".to_string(), json!("nope"));
+    let res = client
+        .call_tool(CallToolRequestParam {
+            name: "switch_profile".into(),
+            arguments: Some(args),
+        })
+        .await;
+    assert!(res.is_err());
+
+    client.cancel().await.unwrap();
+}
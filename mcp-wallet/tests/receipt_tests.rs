@@ -0,0 +1,195 @@
+//! Tests for revert and log decoding and decoded receipts.
+
+use ethers::{
+    abi::{self, Token},
+    types::{Address, Bytes, Log, H256, U256},
+    utils::keccak256,
+};
+use mcp_wallet::{
+    decode::{decode_log, decode_revert, RevertReason},
+    eth_client::EthClient,
+    receipt::{self, ReceiptStatus},
+};
+use serde_json::{json, Value};
+
+mod common;
+
+fn with_selector(signature: &str, tokens: &[Token]) -> Vec<u8> {
+    let mut data = keccak256(signature)[..4].to_vec();
+    data.extend(abi::encode(tokens));
+    data
+}
+
+fn address_topic(address: Address) -> H256 {
+    H256::from(address)
+}
+
+#[test]
+fn test_decode_revert_reasons() {
+    let error = with_selector("Error(string)", &[Token::String("not enough".into())]);
+    assert_eq!(
+        decode_revert(&error),
+        RevertReason::Error {
+            message: "not enough".into()
+        }
+    );
+
+    let panic = with_selector("Panic(uint256)", &[Token::Uint(U256::from(0x11))]);
+    let reason = decode_revert(&panic);
+    assert_eq!(
+        reason.to_string(),
+        "panic 0x11: arithmetic overflow or underflow"
+    );
+
+    let owner = Address::repeat_byte(0xaa);
+    let custom = with_selector(
+        "ERC20InsufficientBalance(address,uint256,uint256)",
+        &[
+            Token::Address(owner),
+            Token::Uint(U256::from(5)),
+            Token::Uint(U256::from(7)),
+        ],
+    );
+    match decode_revert(&custom) {
+        RevertReason::Custom {
+            selector,
+            signature,
+            args,
+            ..
+        } => {
+            assert_eq!(selector, "0xe450d38c");
+            assert_eq!(
+                signature.as_deref(),
+                Some("ERC20InsufficientBalance(address,uint256,uint256)")
+            );
+            let args = args.unwrap();
+            assert_eq!(args["balance"], "5");
+            assert_eq!(args["needed"], "7");
+        }
+        other => panic!("unexpected reason {:?}", other),
+    }
+
+    let unknown = with_selector("Unauthorized()", &[]);
+    assert!(matches!(
+        decode_revert(&unknown),
+        RevertReason::Custom {
+            signature: None,
+            ..
+        }
+    ));
+    assert_eq!(decode_revert(&[]), RevertReason::Empty);
+}
+
+#[test]
+fn test_decode_known_logs() {
+    let token = Address::repeat_byte(0x70);
+    let (from, to) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+    let transfer = H256::from(keccak256("Transfer(address,address,uint256)"));
+
+    // ERC-20: the amount is in the data
+    let erc20 = Log {
+        address: token,
+        topics: vec![transfer, address_topic(from), address_topic(to)],
+        data: Bytes::from(abi::encode(&[Token::Uint(U256::from(1000))])),
+        ..Default::default()
+    };
+    let decoded = decode_log(&erc20);
+    assert_eq!(decoded.event.as_deref(), Some("Transfer"));
+    assert_eq!(
+        decoded.signature.as_deref(),
+        Some("Transfer(address,address,uint256)")
+    );
+    let args = decoded.args.unwrap();
+    assert_eq!(args["from"], ethers::utils::to_checksum(&from, None));
+    assert_eq!(args["value"], "1000");
+
+    // ERC-721: the token id is indexed
+    let erc721 = Log {
+        address: token,
+        topics: vec![
+            transfer,
+            address_topic(from),
+            address_topic(to),
+            H256::from_low_u64_be(42),
+        ],
+        ..Default::default()
+    };
+    assert_eq!(decode_log(&erc721).args.unwrap()["tokenId"], "42");
+
+    let unknown = Log {
+        address: token,
+        topics: vec![H256::repeat_byte(1)],
+        data: Bytes::from(vec![1, 2, 3]),
+        ..Default::default()
+    };
+    let decoded = decode_log(&unknown);
+    assert_eq!(decoded.event, None);
+    assert_eq!(decoded.topics, Some(vec![H256::repeat_byte(1)]));
+}
+
+#[tokio::test]
+async fn test_describe_failed_receipt_with_revert_reason() {
+    let hash = format!("{:?}", H256::repeat_byte(1));
+    let revert = format!(
+        "0x{}",
+        hex::encode(with_selector(
+            "Error(string)",
+            &[Token::String("sold out".into())]
+        ))
+    );
+    let tx_hash = hash.clone();
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_getTransactionReceipt" => {
+            let mut receipt = common::receipt_json(&tx_hash, 12);
+            receipt["status"] = json!("0x0");
+            receipt["gasUsed"] = json!("0x7530");
+            receipt["effectiveGasPrice"] = json!("0x3b9aca00");
+            receipt
+        }
+        "eth_getTransactionByHash" => json!({
+            "hash": tx_hash,
+            "nonce": "0x0",
+            "blockNumber": "0xc",
+            "from": format!("0x{}", "aa".repeat(20)),
+            "to": format!("0x{}", "bb".repeat(20)),
+            "value": "0x0",
+            "gas": "0x186a0",
+            "gasPrice": "0x3b9aca00",
+            "input": "0x1234",
+            "v": "0x1b",
+            "r": "0x1",
+            "s": "0x1",
+        }),
+        // The replay runs on the parent block
+        "eth_call" if params[1] == json!("0xb") => {
+            common::rpc_error(3, "execution reverted: sold out", Some(&revert))
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let report = receipt::describe(&client, hash.parse().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(report.status, ReceiptStatus::Failed);
+    assert_eq!(report.gas_used.as_deref(), Some("30000"));
+    assert_eq!(report.fee_wei.as_deref(), Some("30000000000000"));
+    assert_eq!(report.fee_eth.as_deref(), Some("0.000030000000000000"));
+    assert_eq!(
+        report.revert_reason,
+        Some(RevertReason::Error {
+            message: "sold out".into()
+        })
+    );
+
+    // Receipts from before Byzantium have no status; they are not failures
+    let legacy = ethers::types::TransactionReceipt {
+        status: None,
+        ..Default::default()
+    };
+    let report = receipt::ReceiptReport::new(&legacy, None);
+    assert_eq!(report.status, ReceiptStatus::Unknown);
+    assert_eq!(report.revert_reason, None);
+}
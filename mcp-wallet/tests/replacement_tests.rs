@@ -0,0 +1,117 @@
+//! Tests for speeding up and cancelling pending transactions.
+
+use ethers::types::{Address, Bytes, Transaction, H256, U256, U64};
+use mcp_wallet::{
+    eth_client::EthClient,
+    replacement::{
+        bump_fee, replacement_request, ReplacementKind, ReplacementStatus, ReplacementTracker,
+    },
+};
+use serde_json::json;
+
+mod common;
+
+fn pending_tx() -> Transaction {
+    Transaction {
+        hash: H256::repeat_byte(1),
+        nonce: U256::from(7),
+        from: Address::repeat_byte(0xaa),
+        to: Some(Address::repeat_byte(0xbb)),
+        value: U256::from(1_000),
+        gas: U256::from(50_000),
+        input: Bytes::from(vec![0xde, 0xad]),
+        max_fee_per_gas: Some(U256::from(100)),
+        max_priority_fee_per_gas: Some(U256::from(10)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_bump_fee_rounds_up() {
+    assert_eq!(bump_fee(U256::from(100), 10), U256::from(110));
+    assert_eq!(bump_fee(U256::from(15), 10), U256::from(17));
+    // Zero or tiny fees still rise by at least one wei
+    assert_eq!(bump_fee(U256::zero(), 10), U256::from(1));
+    assert_eq!(bump_fee(U256::from(1), 10), U256::from(2));
+    // Huge fees or percentages saturate instead of overflowing
+    assert_eq!(bump_fee(U256::MAX / 2, 1000), U256::MAX);
+    assert_eq!(bump_fee(U256::from(100), u64::MAX), U256::from(u64::MAX));
+    assert_eq!(bump_fee(U256::MAX, 10), U256::MAX);
+}
+
+#[test]
+fn test_replacement_request_speed_up_and_cancel() {
+    let original = pending_tx();
+
+    let speed_up = replacement_request(&original, 1, ReplacementKind::SpeedUp, 5, None).unwrap();
+    assert_eq!(speed_up.nonce, U256::from(7));
+    assert_eq!(speed_up.to, original.to);
+    assert_eq!(speed_up.value, original.value);
+    assert_eq!(speed_up.data, Some(vec![0xde, 0xad]));
+    // Bumps below the node minimum are raised to 10%
+    assert_eq!(speed_up.max_fee_per_gas, U256::from(110));
+    assert_eq!(speed_up.max_priority_fee_per_gas, U256::from(11));
+
+    // The network asks for more than the bump gives
+    let network = Some((U256::from(500), U256::from(40)));
+    let cancel = replacement_request(&original, 1, ReplacementKind::Cancel, 20, network).unwrap();
+    assert_eq!(cancel.nonce, U256::from(7));
+    assert_eq!(cancel.to, Some(original.from));
+    assert_eq!(cancel.value, U256::zero());
+    assert_eq!(cancel.data, None);
+    assert_eq!(cancel.gas, U256::from(21_000));
+    assert_eq!(cancel.max_fee_per_gas, U256::from(500));
+    assert_eq!(cancel.max_priority_fee_per_gas, U256::from(40));
+
+    // Legacy transactions pay gas_price as both fees
+    let legacy = Transaction {
+        max_fee_per_gas: None,
+        max_priority_fee_per_gas: None,
+        gas_price: Some(U256::from(200)),
+        ..pending_tx()
+    };
+    let bumped = replacement_request(&legacy, 1, ReplacementKind::SpeedUp, 10, None).unwrap();
+    assert_eq!(bumped.max_fee_per_gas, U256::from(220));
+    assert_eq!(bumped.max_priority_fee_per_gas, U256::from(220));
+
+    let mined = Transaction {
+        block_number: Some(U64::from(12)),
+        ..pending_tx()
+    };
+    assert!(replacement_request(&mined, 1, ReplacementKind::Cancel, 10, None).is_err());
+}
+
+#[tokio::test]
+async fn test_tracker_reports_winner() {
+    let tracker = ReplacementTracker::new();
+    let from = Address::repeat_byte(0xaa);
+    let original = H256::repeat_byte(1);
+    let first = H256::repeat_byte(2);
+    let second = H256::repeat_byte(3);
+
+    tracker.record(from, 1, 7, original, first).await;
+    let group = tracker.record(from, 1, 7, first, second).await;
+    assert_eq!(group.candidates, vec![original, first, second]);
+    assert_eq!(tracker.find(original).await, Some(group.clone()));
+    assert_eq!(tracker.find(H256::repeat_byte(9)).await, None);
+
+    // Only the first replacement has a receipt
+    let mined = format!("{:?}", first);
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_getTransactionCount" => json!("0x8"),
+        "eth_getTransactionReceipt" if params[0] == json!(mined) => {
+            common::receipt_json(&mined, 12)
+        }
+        _ => serde_json::Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+    let status = ReplacementTracker::status(&client, &group).await.unwrap();
+    assert_eq!(
+        status,
+        ReplacementStatus::Mined {
+            winner: first,
+            block_number: Some(12),
+        }
+    );
+}
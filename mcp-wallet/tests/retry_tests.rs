@@ -0,0 +1,140 @@
+//! Tests for RPC retries, backoff and rate limiting against a flaky endpoint.
+
+use common::Flake;
+use mcp_wallet::{eth_client::EthClient, failover::FailoverOptions};
+use serde_json::{json, Value};
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+mod common;
+
+const ADDRESS: &str = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+
+fn balance(method: &str, _: &Value) -> Value {
+    match method {
+        "eth_getBalance" => json!("0x2a"),
+        _ => Value::Null,
+    }
+}
+
+fn fast_retries(max_retries: u32) -> FailoverOptions {
+    FailoverOptions {
+        max_retries,
+        backoff: Duration::from_millis(10),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limits_and_server_errors_are_retried() {
+    let (url, requests) = common::spawn_flaky_rpc(
+        vec![Flake::Status(429), Flake::Status(503), Flake::Status(502)],
+        balance,
+    )
+    .await;
+    let client = EthClient::with_endpoints(&[&url], fast_retries(3)).unwrap();
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "0.000000000000000042"
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+    // Retries that end in an answer do not count against the endpoint
+    let health = &client.endpoint_health()[0];
+    assert_eq!((health.successes, health.failures), (1, 0));
+
+    // Once the retries are used up, the next endpoint is tried
+    let (flaky, flaky_requests) =
+        common::spawn_flaky_rpc(vec![Flake::Status(503); 3], balance).await;
+    let (backup, _) = common::spawn_flaky_rpc(Vec::new(), balance).await;
+    let client = EthClient::with_endpoints(&[&flaky, &backup], fast_retries(2)).unwrap();
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "0.000000000000000042"
+    );
+    assert_eq!(flaky_requests.load(Ordering::SeqCst), 3);
+    assert_eq!(client.endpoint_health()[0].failures, 1);
+
+    // A rate limit the node reports as a JSON-RPC error is retried as well
+    let (limited, limited_requests) = common::spawn_flaky_rpc(Vec::new(), |_, _| {
+        common::rpc_error(
+            -32005,
+            "daily request count exceeded, request rate limited",
+            None,
+        )
+    })
+    .await;
+    let client = EthClient::with_endpoints(&[&limited], fast_retries(1)).unwrap();
+    assert!(client.get_balance(ADDRESS).await.is_err());
+    assert_eq!(limited_requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_reset_connections_are_retried_but_reverts_are_not() {
+    let (url, requests) = common::spawn_flaky_rpc(vec![Flake::Reset], balance).await;
+    let client = EthClient::with_endpoints(&[&url], fast_retries(3)).unwrap();
+    assert_eq!(
+        client.get_balance(ADDRESS).await.unwrap(),
+        "0.000000000000000042"
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    let (reverting, requests) = common::spawn_flaky_rpc(Vec::new(), |_, _| {
+        common::rpc_error(3, "execution reverted", Some("0x"))
+    })
+    .await;
+    let client = EthClient::with_endpoints(&[&reverting], fast_retries(3)).unwrap();
+    assert!(client.get_balance(ADDRESS).await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // Backoff doubles up to the cap, with up to half of it taken off as jitter
+    let options = FailoverOptions {
+        backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(300),
+        ..Default::default()
+    };
+    for (retry, full) in [(0, 100), (1, 200), (2, 300), (10, 300)] {
+        let delay = options.retry_delay(retry);
+        let full = Duration::from_millis(full);
+        assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_spaces_out_requests() {
+    let (url, requests) = common::spawn_flaky_rpc(Vec::new(), balance).await;
+    let options = FailoverOptions {
+        rate_limit: Some(20),
+        ..Default::default()
+    };
+    let client = EthClient::with_endpoints(&[&url], options).unwrap();
+
+    let started = Instant::now();
+    let balances = futures::future::join_all((0..5).map(|_| client.get_balance(ADDRESS))).await;
+    assert!(balances.iter().all(|b| b.is_ok()));
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+    // Five requests at 20 per second need four 50 ms gaps
+    assert!(started.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn test_sends_are_neither_retried_nor_failed_over() {
+    let limited = |method: &str, _: &Value| match method {
+        "eth_chainId" => json!("0x1"),
+        _ => common::rpc_error(429, "too many requests", None),
+    };
+    let (limited, limited_requests) = common::spawn_flaky_rpc(Vec::new(), limited).await;
+    let (backup, backup_requests) = common::spawn_flaky_rpc(Vec::new(), |method, _| match method {
+        "eth_chainId" => json!("0x1"),
+        _ => json!(format!("0x{}", "07".repeat(32))),
+    })
+    .await;
+    let client = EthClient::with_endpoints(&[&limited, &backup], fast_retries(3)).unwrap();
+
+    // The node may have taken the transaction before failing, so it is not
+    // repeated; the other request is the endpoint's chain id check
+    assert!(client.send_signed_transaction("0x02c0").await.is_err());
+    assert_eq!(limited_requests.load(Ordering::SeqCst), 2);
+    assert_eq!(backup_requests.load(Ordering::SeqCst), 0);
+}
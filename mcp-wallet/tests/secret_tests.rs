@@ -0,0 +1,45 @@
+//! Tests for in-memory handling of private key material.
+
+use mcp_wallet::{error::WalletError, secret::PrivateKey, wallet::Wallet};
+
+const PK: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+#[test]
+fn test_private_key_is_redacted_from_debug_output() {
+    let mut wallet = Wallet::new();
+    let address = wallet.import_private_key(PK).unwrap();
+    let (account, _) = wallet.get_account(&format!("0x{:x}", address)).unwrap();
+
+    let account_debug = format!("{:?}", account);
+    let wallet_debug = format!("{:?}", wallet);
+    assert!(account_debug.contains("<redacted>"));
+    assert!(!account_debug.contains(PK));
+    assert!(!wallet_debug.contains(PK));
+}
+
+#[test]
+fn test_wallet_file_format_is_unchanged() {
+    let mut wallet = Wallet::new();
+    let address = wallet.import_private_key(&format!("0x{}", PK)).unwrap();
+
+    // Keys are still stored as raw lowercase hex
+    let json = serde_json::to_value(&wallet).unwrap();
+    let stored = &json["accounts"][format!("0x{:x}", address)]["private_key"];
+    assert_eq!(stored, PK);
+
+    let restored: Wallet = serde_json::from_value(json).unwrap();
+    let (account, _) = restored.get_account(&format!("0x{:x}", address)).unwrap();
+    assert_eq!(account.private_key, Some(PrivateKey::from_hex(PK).unwrap()));
+}
+
+#[test]
+fn test_private_key_parsing() {
+    assert!(PrivateKey::from_hex(&format!("  0x{}\n", PK)).is_ok());
+    for bad in ["0x1234", &"0".repeat(64), &"zz".repeat(32)] {
+        assert!(matches!(
+            PrivateKey::from_hex(bad),
+            Err(WalletError::InvalidPrivateKey(_))
+        ));
+    }
+    assert!(PrivateKey::from_slice(&[1u8; 31]).is_err());
+}
@@ -0,0 +1,78 @@
+//! Tests for Shamir secret sharing of private keys.
+
+use mcp_wallet::{
+    error::WalletError,
+    shamir::{combine_shares, split_secret, KeyShare},
+    wallet::Wallet,
+};
+
+const PK: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+#[test]
+fn test_split_and_combine_any_threshold_subset() {
+    let secret: Vec<u8> = (0u8..32).collect();
+    let shares = split_secret(&secret, 3, 5).unwrap();
+    assert_eq!(shares.len(), 5);
+
+    for subset in [[0, 1, 2], [0, 2, 4], [1, 3, 4], [4, 3, 0]] {
+        let picked: Vec<_> = subset.iter().map(|&i| shares[i].clone()).collect();
+        assert_eq!(*combine_shares(&picked).unwrap(), secret);
+    }
+
+    // Fewer than the threshold yields a different value
+    assert_ne!(*combine_shares(&shares[..2]).unwrap(), secret);
+
+    // Invalid parameters are rejected
+    assert!(split_secret(&secret, 1, 3).is_err());
+    assert!(split_secret(&secret, 4, 3).is_err());
+}
+
+#[test]
+fn test_wallet_split_and_import_key_shares() {
+    let mut source = Wallet::new();
+    let address = source.import_private_key(PK).unwrap();
+    source.add_alias(address, "treasury".to_string()).unwrap();
+
+    let shares = source.split_private_key("treasury", 2, 3).unwrap();
+    let text: Vec<String> = shares.iter().map(ToString::to_string).collect();
+    assert!(text.iter().all(|s| s.starts_with("mcpw-sss1:2:")));
+
+    // Rebuild from shares 3 and 1 in a fresh wallet, via their text form
+    let parsed: Vec<KeyShare> = [&text[2], &text[0]]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    let mut target = Wallet::new();
+    let restored = target.import_key_shares(&parsed).unwrap();
+    assert_eq!(restored, address);
+    let (account, _) = target.get_account(&format!("0x{:x}", address)).unwrap();
+    assert!(account.private_key.is_some());
+
+    // Not enough shares
+    let err = target.import_key_shares(&parsed[..1]).unwrap_err();
+    assert!(matches!(err, WalletError::InvalidKeyShares(_)));
+}
+
+#[test]
+fn test_key_share_text_detects_typos() {
+    let mut wallet = Wallet::new();
+    wallet.import_private_key(PK).unwrap();
+    let address = wallet.list_accounts()[0].0;
+    let share = wallet
+        .split_private_key(&format!("0x{:x}", address), 2, 2)
+        .unwrap()
+        .remove(0)
+        .to_string();
+
+    // Flip one hex digit in the share data
+    let mut corrupted = share.clone().into_bytes();
+    let pos = share.rfind(':').unwrap() - 1;
+    corrupted[pos] = if corrupted[pos] == b'0' { b'1' } else { b'0' };
+    let corrupted = String::from_utf8(corrupted).unwrap();
+
+    assert!(share.parse::<KeyShare>().is_ok());
+    assert!(matches!(
+        corrupted.parse::<KeyShare>(),
+        Err(WalletError::InvalidKeyShares(_))
+    ));
+}
@@ -0,0 +1,469 @@
+//! Tests for simulating transactions before they are signed or sent, and for
+//! previewing their effects in the embedded EVM or with the node's tracers.
+
+use ethers::{
+    abi::{self, Token},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        H256, U256,
+    },
+    utils::{keccak256, to_checksum},
+};
+use mcp_wallet::{
+    decode::RevertReason,
+    eth_client::EthClient,
+    simulation::{self, Engine, Simulation},
+    WalletError,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+fn transfer(gas: Option<u64>) -> TypedTransaction {
+    let mut tx = Eip1559TransactionRequest::new()
+        .from(Address::repeat_byte(0xaa))
+        .to(Address::repeat_byte(0xbb))
+        .value(1000)
+        .nonce(7);
+    if let Some(gas) = gas {
+        tx = tx.gas(gas);
+    }
+    TypedTransaction::Eip1559(tx)
+}
+
+#[tokio::test]
+async fn test_simulate_reports_decoded_revert() {
+    let mut panic = keccak256("Panic(uint256)")[..4].to_vec();
+    panic.extend(abi::encode(&[Token::Uint(U256::from(0x12))]));
+    let revert = format!("0x{}", hex::encode(panic));
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_call" if params[1] == json!("pending") => {
+            common::rpc_error(3, "execution reverted", Some(&revert))
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    match simulation::simulate(&client, &transfer(None)).await {
+        Err(WalletError::WouldRevert(reason)) => assert_eq!(
+            reason,
+            RevertReason::Panic {
+                code: 0x12,
+                description: "division or modulo by zero".into()
+            }
+        ),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_simulate_rejects_gas_limit_below_estimate() {
+    let estimated: Arc<Mutex<Vec<Value>>> = Arc::default();
+    let seen = estimated.clone();
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_call" => {
+            // Queued transactions may be ahead of the pending nonce
+            assert!(params[0].get("nonce").is_none());
+            json!("0x")
+        }
+        "eth_estimateGas" => {
+            seen.lock().unwrap().push(params[0].clone());
+            json!("0xc350")
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    match simulation::simulate(&client, &transfer(Some(21_000))).await {
+        Err(WalletError::GasLimitTooLow { needed, limit }) => {
+            assert_eq!(needed, U256::from(50_000));
+            assert_eq!(limit, U256::from(21_000));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    // The estimate is not capped by the transaction's own limit
+    {
+        let estimated = estimated.lock().unwrap();
+        assert_eq!(estimated.len(), 1);
+        assert!(estimated[0].get("gas").is_none());
+    }
+
+    let simulation = simulation::simulate(&client, &transfer(Some(60_000)))
+        .await
+        .unwrap();
+    assert_eq!(simulation.gas_estimate, U256::from(50_000));
+}
+
+#[tokio::test]
+async fn test_simulate_signed_raw_transaction() {
+    let signer: LocalWallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        .parse::<LocalWallet>()
+        .unwrap()
+        .with_chain_id(1u64);
+    let mut tx = transfer(Some(21_000));
+    tx.set_chain_id(1u64);
+    let signature = signer.sign_transaction(&tx).await.unwrap();
+    let raw = tx.rlp_signed(&signature);
+
+    let sender = format!("{:?}", signer.address());
+    let url = common::spawn_rpc(move |method, params| match method {
+        "eth_call" | "eth_estimateGas" => {
+            // The sender is recovered from the signature
+            assert_eq!(params[0]["from"], json!(sender));
+            if method == "eth_call" {
+                json!("0x01")
+            } else {
+                json!("0x5208")
+            }
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let call = simulation::call_from_raw(&raw).unwrap();
+    assert_eq!(call.from(), Some(&signer.address()));
+    assert_eq!(
+        simulation::simulate(&client, &call).await.unwrap(),
+        Simulation {
+            gas_estimate: U256::from(21_000),
+            output: vec![1].into(),
+        }
+    );
+
+    assert!(simulation::call_from_raw(&[0xde, 0xad]).is_err());
+}
+
+fn topic(address: Address) -> String {
+    format!("{:?}", H256::from(address))
+}
+
+#[tokio::test]
+async fn test_preview_reports_balance_token_and_storage_changes() {
+    let (sender, token, pool) = (
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0x70),
+        Address::repeat_byte(0xcc),
+    );
+    let transfer_topic = format!(
+        "{:?}",
+        H256::from(keccak256("Transfer(address,address,uint256)"))
+    );
+    let amount = format!(
+        "0x{}",
+        hex::encode(abi::encode(&[Token::Uint(U256::from(250))]))
+    );
+    let slot = format!("{:?}", H256::from_low_u64_be(3));
+    let call_trace = json!({
+        "type": "CALL",
+        "from": format!("{:?}", sender),
+        "gasUsed": "0xb411",
+        "input": "0x",
+        "calls": [{
+            "type": "CALL",
+            "from": format!("{:?}", pool),
+            "to": format!("{:?}", token),
+            "gasUsed": "0x100",
+            "input": "0x",
+            "logs": [{
+                "address": format!("{:?}", token),
+                "topics": [transfer_topic, topic(pool), topic(sender)],
+                "data": amount,
+                "position": "0x0",
+            }],
+        }],
+        // Emitted after the subcall returned
+        "logs": [{
+            "address": format!("{:?}", pool),
+            "topics": [format!("{:?}", H256::repeat_byte(9))],
+            "data": "0x",
+            "position": "0x1",
+        }],
+    });
+    let diff = json!({
+        "pre": {
+            format!("{:?}", sender): { "balance": "0x3e8", "nonce": 1 },
+            format!("{:?}", token): { "balance": "0x0", "storage": { slot.clone(): format!("{:?}", H256::from_low_u64_be(1)) } },
+        },
+        "post": {
+            format!("{:?}", sender): { "balance": "0x64", "nonce": 2 },
+            format!("{:?}", pool): { "balance": "0x384" },
+            format!("{:?}", token): { "storage": { slot: format!("{:?}", H256::from_low_u64_be(2)) } },
+        },
+    });
+    let url = common::spawn_rpc(move |method, params| match method {
+        "debug_traceCall" if params[2]["tracer"] == "callTracer" => {
+            assert_eq!(params[2]["tracerConfig"]["withLog"], true);
+            call_trace.clone()
+        }
+        "debug_traceCall" => {
+            assert_eq!(params[2]["tracerConfig"]["diffMode"], true);
+            diff.clone()
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = Arc::new(EthClient::new(&url).unwrap());
+
+    let report = simulation::preview(&client, &transfer(None), BlockNumber::Latest.into(), true)
+        .await
+        .unwrap();
+    assert_eq!(report.engine, Engine::Node);
+    assert!(report.success);
+    assert_eq!(report.gas_used, "46097");
+
+    let sender_change = &report.balance_changes[0];
+    assert_eq!(
+        (sender_change.before.as_str(), sender_change.after.as_str()),
+        ("1000", "100")
+    );
+    assert_eq!(sender_change.delta, "-900");
+    // The pool did not exist before
+    assert_eq!(report.balance_changes[1].delta, "900");
+
+    let deltas: Vec<&str> = report
+        .token_changes
+        .iter()
+        .map(|c| c.delta.as_str())
+        .collect();
+    assert_eq!(deltas, ["250", "-250"]);
+    assert_eq!(report.token_changes[0].address, to_checksum(&sender, None));
+
+    // Logs come out in execution order, subcalls first here
+    assert_eq!(report.events.len(), 2);
+    assert_eq!(report.events[0].event.as_deref(), Some("Transfer"));
+    assert_eq!(report.events[1].event, None);
+    assert_eq!(report.events[1].log_index, Some(1));
+
+    assert_eq!(report.storage_changes.len(), 1);
+    assert_eq!(report.storage_changes[0].before, H256::from_low_u64_be(1));
+    assert_eq!(report.storage_changes[0].after, H256::from_low_u64_be(2));
+}
+
+#[tokio::test]
+async fn test_preview_reports_revert_from_node() {
+    let mut revert = keccak256("Error(string)")[..4].to_vec();
+    revert.extend(abi::encode(&[Token::String("paused".into())]));
+    let output = format!("0x{}", hex::encode(revert));
+    let url = common::spawn_rpc(move |_, params| match params[2]["tracer"].as_str() {
+        Some("callTracer") => json!({
+            "type": "CALL",
+            "from": format!("0x{}", "aa".repeat(20)),
+            "gasUsed": "0x5208",
+            "input": "0x",
+            "output": output,
+            "error": "execution reverted",
+        }),
+        _ => json!({ "pre": {}, "post": {} }),
+    })
+    .await;
+    let client = Arc::new(EthClient::new(&url).unwrap());
+
+    let report = simulation::preview(&client, &transfer(None), BlockNumber::Latest.into(), true)
+        .await
+        .unwrap();
+    assert!(!report.success);
+    assert_eq!(
+        report.revert_reason,
+        Some(RevertReason::Error {
+            message: "paused".into()
+        })
+    );
+    assert_eq!(report.error, None);
+    assert!(report.balance_changes.is_empty());
+}
+
+/// Block 100, produced by `0x1111…` with a base fee of 10 wei.
+fn block_json() -> Value {
+    json!({
+        "hash": format!("{:?}", H256::from_low_u64_be(101)),
+        "parentHash": format!("{:?}", H256::from_low_u64_be(100)),
+        "number": "0x64",
+        "timestamp": "0x3e8",
+        "miner": format!("0x{}", "11".repeat(20)),
+        "mixHash": format!("{:?}", H256::repeat_byte(7)),
+        "gasUsed": "0x0",
+        "gasLimit": "0x1c9c380",
+        "baseFeePerGas": "0xa",
+        "transactions": [],
+    })
+}
+
+/// An account's address, balance, code and storage slots.
+type AccountFixture = (Address, u64, String, Vec<(u64, u64)>);
+
+/// A chain without the debug namespace holding `accounts`; every state read
+/// must be for block 100.
+async fn spawn_state_rpc(accounts: Vec<AccountFixture>) -> String {
+    let find = move |params: &Value| {
+        let address: Address = params[0].as_str().unwrap().parse().unwrap();
+        accounts.iter().find(|a| a.0 == address).cloned()
+    };
+    common::spawn_rpc(move |method, params| match method {
+        "debug_traceCall" => {
+            common::rpc_error(-32601, "the method debug_traceCall does not exist", None)
+        }
+        "eth_chainId" => json!("0x1"),
+        "eth_getBlockByNumber" => block_json(),
+        "eth_getBalance" | "eth_getTransactionCount" | "eth_getCode" | "eth_getStorageAt" => {
+            let block = params.as_array().unwrap().last().unwrap();
+            assert_eq!(block, &json!("0x64"));
+            let account = find(params);
+            match method {
+                "eth_getBalance" => json!(format!("0x{:x}", account.map_or(0, |a| a.1))),
+                "eth_getTransactionCount" => json!(if account.is_some() { "0x1" } else { "0x0" }),
+                "eth_getCode" => json!(account.map_or("0x".into(), |a| a.2)),
+                _ => {
+                    let slot = U256::from_str_radix(params[1].as_str().unwrap(), 16).unwrap();
+                    let value = account
+                        .and_then(|a| a.3.iter().find(|s| U256::from(s.0) == slot).map(|s| s.1))
+                        .unwrap_or_default();
+                    json!(format!("{:?}", H256::from_low_u64_be(value)))
+                }
+            }
+        }
+        _ => Value::Null,
+    })
+    .await
+}
+
+fn priced_call(to: Address) -> TypedTransaction {
+    TypedTransaction::Eip1559(
+        Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(0xaa))
+            .to(to)
+            .value(1000)
+            .gas(100_000)
+            .max_fee_per_gas(30)
+            .max_priority_fee_per_gas(2),
+    )
+}
+
+#[tokio::test]
+async fn test_preview_runs_in_local_evm_without_debug_namespace() {
+    let (sender, token, pool, producer) = (
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0x70),
+        Address::repeat_byte(0xcc),
+        Address::repeat_byte(0x11),
+    );
+    // Emits Transfer(pool, sender, 250) and stores 42 in slot 3
+    let code = format!(
+        "0x60fa6000527f{}7f{}7f{}60206000a3602a60035500",
+        hex::encode(H256::from(sender)),
+        hex::encode(H256::from(pool)),
+        hex::encode(keccak256("Transfer(address,address,uint256)")),
+    );
+    let url = spawn_state_rpc(vec![
+        (sender, 1_000_000_000, "0x".into(), vec![]),
+        (token, 0, code, vec![(3, 1)]),
+    ])
+    .await;
+    let client = Arc::new(EthClient::new(&url).unwrap());
+
+    let report = simulation::preview(
+        &client,
+        &priced_call(token),
+        BlockNumber::Latest.into(),
+        true,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.engine, Engine::Local);
+    assert!(report.success);
+    assert_eq!(report.revert_reason, None);
+
+    // The sender pays the value and base fee plus tip, the producer gets the tip
+    let gas: i64 = report.gas_used.parse().unwrap();
+    assert!(gas > 21_000);
+    let deltas: Vec<(String, String)> = report
+        .balance_changes
+        .iter()
+        .map(|c| (c.address.clone(), c.delta.clone()))
+        .collect();
+    assert_eq!(
+        deltas,
+        [
+            (to_checksum(&producer, None), (gas * 2).to_string()),
+            (to_checksum(&token, None), "1000".to_string()),
+            (to_checksum(&sender, None), (-1000 - gas * 12).to_string()),
+        ]
+    );
+    assert_eq!(report.balance_changes[2].before, "1000000000");
+
+    assert_eq!(report.events.len(), 1);
+    assert_eq!(report.events[0].event.as_deref(), Some("Transfer"));
+    let deltas: Vec<&str> = report
+        .token_changes
+        .iter()
+        .map(|c| c.delta.as_str())
+        .collect();
+    assert_eq!(deltas, ["250", "-250"]);
+    assert_eq!(report.token_changes[0].address, to_checksum(&sender, None));
+
+    assert_eq!(report.storage_changes.len(), 1);
+    assert_eq!(report.storage_changes[0].address, to_checksum(&token, None));
+    assert_eq!(report.storage_changes[0].slot, H256::from_low_u64_be(3));
+    assert_eq!(report.storage_changes[0].before, H256::from_low_u64_be(1));
+    assert_eq!(report.storage_changes[0].after, H256::from_low_u64_be(42));
+}
+
+#[tokio::test]
+async fn test_preview_reports_local_revert_and_halt() {
+    let (sender, reverter, looper) = (
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0x70),
+        Address::repeat_byte(0x71),
+    );
+    let url = spawn_state_rpc(vec![
+        (sender, 1_000_000_000, "0x".into(), vec![]),
+        // revert(0, 0)
+        (reverter, 0, "0x60006000fd".into(), vec![]),
+        // An endless loop
+        (looper, 0, "0x5b600056".into(), vec![]),
+    ])
+    .await;
+    let client = Arc::new(EthClient::new(&url).unwrap());
+
+    let report = simulation::preview(
+        &client,
+        &priced_call(reverter),
+        BlockNumber::Latest.into(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.engine, Engine::Local);
+    assert!(!report.success);
+    assert_eq!(report.revert_reason, Some(RevertReason::Empty));
+    assert_eq!(report.error, None);
+    // Only the fees are paid
+    let gas: i64 = report.gas_used.parse().unwrap();
+    assert_eq!(report.balance_changes[1].delta, (-gas * 12).to_string());
+    assert!(report.events.is_empty());
+
+    let report = simulation::preview(
+        &client,
+        &priced_call(looper),
+        BlockNumber::Latest.into(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(!report.success);
+    assert_eq!(report.revert_reason, None);
+    assert_eq!(report.error.as_deref(), Some("OutOfGas(Basic)"));
+    assert_eq!(report.gas_used, "100000");
+
+    // A sender that cannot pay is refused
+    let mut poor = priced_call(reverter);
+    poor.set_from(Address::repeat_byte(0xdd));
+    let error = simulation::preview(&client, &poor, BlockNumber::Latest.into(), false)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Invalid transaction"));
+}
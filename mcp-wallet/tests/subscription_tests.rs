@@ -0,0 +1,235 @@
+//! Tests for WebSocket and IPC endpoints and chain event subscriptions.
+
+use ethers::types::{Address, H256};
+use mcp_wallet::{
+    eth_client::EthClient,
+    logs::LogFilter,
+    subscription::{Subscription, SubscriptionEvent, SubscriptionKind, Subscriptions},
+    transport::Transport,
+};
+use serde_json::{json, Value};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::mpsc, time::timeout};
+
+mod common;
+
+fn notification(id: &str, result: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "eth_subscription",
+        "params": { "subscription": id, "result": result },
+    })
+}
+
+fn header(number: u64) -> Value {
+    json!({
+        "number": format!("0x{:x}", number),
+        "hash": format!("{:?}", H256::from_low_u64_be(number)),
+        "parentHash": format!("{:?}", H256::from_low_u64_be(number - 1)),
+        "timestamp": "0x64",
+        "gasUsed": "0x5208",
+        "gasLimit": "0x1c9c380",
+        "baseFeePerGas": "0x7",
+    })
+}
+
+async fn next_event(
+    received: &mut mpsc::UnboundedReceiver<SubscriptionEvent>,
+) -> SubscriptionEvent {
+    timeout(Duration::from_secs(5), received.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn test_transport_is_picked_from_the_url() {
+    assert!(!Transport::new("http://localhost:8545")
+        .unwrap()
+        .supports_subscriptions());
+    assert!(Transport::new("WSS://mainnet.example/v3/key")
+        .unwrap()
+        .supports_subscriptions());
+    assert!(Transport::new("ftp://localhost").is_err());
+    assert!(Transport::new("").is_err());
+    // Anything without a scheme is an IPC socket path
+    assert!(Transport::new("/tmp/anvil.ipc")
+        .unwrap()
+        .supports_subscriptions());
+    assert!(Transport::new("geth.ipc").is_ok());
+    // But a forgotten scheme is caught early
+    for url in ["localhost:8545", "127.0.0.1:8546", "node.example:443"] {
+        let err = Transport::new(url).unwrap_err();
+        assert!(err.contains("no scheme"), "{}", err);
+    }
+
+    // WebSockets connect lazily, so no node is needed to create the client
+    let client = EthClient::new("ws://127.0.0.1:9").unwrap();
+    assert!(client.supports_subscriptions());
+    assert_eq!(client.url(), "ws://127.0.0.1:9");
+}
+
+#[tokio::test]
+async fn test_subscribing_over_http_is_rejected() {
+    let client = Arc::new(EthClient::new("http://127.0.0.1:9").unwrap());
+    let subscriptions = Subscriptions::new();
+    let (events, _received) = mpsc::unbounded_channel();
+
+    let error = subscriptions
+        .subscribe(client, Subscription::NewHeads, events)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("ws://"));
+    assert!(subscriptions.list().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_ws_subscriptions_deliver_decoded_events() {
+    let token = Address::repeat_byte(0x70);
+    let calls: Arc<Mutex<Vec<(String, Value)>>> = Arc::default();
+    let seen = calls.clone();
+    let (url, push) = common::spawn_ws_rpc(move |method, params| {
+        seen.lock()
+            .unwrap()
+            .push((method.to_string(), params.clone()));
+        match (method, params[0].as_str()) {
+            ("eth_blockNumber", _) => json!("0x10"),
+            ("eth_subscribe", Some("newHeads")) => json!("0x1"),
+            ("eth_subscribe", _) => json!("0x2"),
+            ("eth_unsubscribe", _) => json!(true),
+            _ => Value::Null,
+        }
+    })
+    .await;
+    let client = Arc::new(EthClient::new(&url).unwrap());
+    // Plain requests go over the same socket
+    assert_eq!(client.get_current_block().await.unwrap(), 16);
+
+    let subscriptions = Subscriptions::new();
+    let (events, mut received) = mpsc::unbounded_channel();
+    let heads = subscriptions
+        .subscribe(client.clone(), Subscription::NewHeads, events.clone())
+        .await
+        .unwrap();
+    let filter = LogFilter {
+        addresses: vec![token],
+        ..Default::default()
+    };
+    let logs = subscriptions
+        .subscribe(client, Subscription::Logs { filter, abi: None }, events)
+        .await
+        .unwrap();
+    let listed: Vec<SubscriptionKind> = subscriptions.list().await.iter().map(|s| s.kind).collect();
+    assert_eq!(listed, [SubscriptionKind::NewHeads, SubscriptionKind::Logs]);
+
+    push.send(notification("0x1", header(17))).unwrap();
+    let transfer = ethers::utils::keccak256("Transfer(address,address,uint256)");
+    push.send(notification(
+        "0x2",
+        json!({
+            "address": format!("{:?}", token),
+            "topics": [
+                format!("{:?}", H256::from(transfer)),
+                format!("{:?}", H256::from(Address::repeat_byte(0xaa))),
+                format!("{:?}", H256::from(Address::repeat_byte(0xbb))),
+            ],
+            "data": format!("0x{:064x}", 5),
+            "blockNumber": "0x11",
+            "transactionHash": format!("{:?}", H256::repeat_byte(1)),
+            "logIndex": "0x0",
+        }),
+    ))
+    .unwrap();
+
+    let block = next_event(&mut received).await;
+    assert_eq!(
+        (block.subscription, block.kind),
+        (heads, SubscriptionKind::NewHeads)
+    );
+    assert_eq!(block.data["number"], 17);
+    let log = next_event(&mut received).await;
+    assert_eq!(log.subscription, logs);
+    assert_eq!(log.data["event"], "Transfer");
+    assert_eq!(log.data["args"]["value"], "5");
+
+    // The log filter is sent without a block range
+    let subscribe = calls.lock().unwrap()[2].clone();
+    assert_eq!(subscribe.1[0], "logs");
+    assert!(subscribe.1[1].get("fromBlock").is_none());
+
+    assert!(subscriptions.unsubscribe(heads).await);
+    assert!(!subscriptions.unsubscribe(heads).await);
+    timeout(Duration::from_secs(5), async {
+        while !calls
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(method, _)| method == "eth_unsubscribe")
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("eth_unsubscribe was not sent");
+    assert_eq!(subscriptions.clear().await, 1);
+}
+
+#[tokio::test]
+async fn test_ipc_socket_serves_requests_and_subscriptions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("anvil.ipc");
+    let push = common::spawn_ipc_rpc(&path, |method, _| match method {
+        "eth_chainId" => json!("0x7a69"),
+        "eth_subscribe" => json!("0x9"),
+        _ => Value::Null,
+    })
+    .await;
+    let client = Arc::new(EthClient::new(path.to_str().unwrap()).unwrap());
+    assert_eq!(client.get_chain_id().await.unwrap(), 31337);
+
+    let subscriptions = Subscriptions::new();
+    let (events, mut received) = mpsc::unbounded_channel();
+    let id = subscriptions
+        .subscribe(client, Subscription::PendingTransactions, events)
+        .await
+        .unwrap();
+    let hash = H256::repeat_byte(7);
+    push.send(notification("0x9", json!(format!("{:?}", hash))))
+        .unwrap();
+    let event = next_event(&mut received).await;
+    assert_eq!(
+        (event.subscription, event.kind),
+        (id, SubscriptionKind::PendingTransactions)
+    );
+    assert_eq!(event.data["hash"], json!(format!("{:?}", hash)));
+
+    // A missing socket fails on use, not when the client is created
+    let missing = EthClient::new(dir.path().join("gone.ipc").to_str().unwrap()).unwrap();
+    assert!(missing.get_current_block().await.is_err());
+}
+
+#[tokio::test]
+async fn test_ipc_reconnects_after_the_node_restarts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("anvil.ipc");
+    let node = |block: u64| {
+        move |method: &str, _: &Value| match method {
+            "eth_blockNumber" => json!(format!("0x{:x}", block)),
+            _ => Value::Null,
+        }
+    };
+    let push = common::spawn_ipc_rpc(&path, node(1)).await;
+    let client = EthClient::new(path.to_str().unwrap()).unwrap();
+    assert_eq!(client.get_current_block().await.unwrap(), 1);
+
+    // The node goes away: requests fail until it is back
+    drop(push);
+    std::fs::remove_file(&path).unwrap();
+    assert!(client.get_current_block().await.is_err());
+
+    let _push = common::spawn_ipc_rpc(&path, node(2)).await;
+    assert_eq!(client.get_current_block().await.unwrap(), 2);
+}
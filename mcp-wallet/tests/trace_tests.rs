@@ -0,0 +1,181 @@
+//! Tests for decoded call traces from the node's tracers.
+
+use ethers::{
+    abi::{self, Token},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        H256, U256,
+    },
+    utils::{keccak256, to_checksum},
+};
+use mcp_wallet::{
+    decode::decode_call,
+    eth_client::EthClient,
+    trace::{self, Tracer},
+};
+use serde_json::{json, Value};
+
+mod common;
+
+fn call_data(signature: &str, tokens: &[Token]) -> String {
+    let mut data = keccak256(signature)[..4].to_vec();
+    data.extend(abi::encode(tokens));
+    format!("0x{}", hex::encode(data))
+}
+
+fn hex_bytes(tokens: &[Token]) -> String {
+    format!("0x{}", hex::encode(abi::encode(tokens)))
+}
+
+#[test]
+fn test_decode_known_calls() {
+    let to = Address::repeat_byte(0xbb);
+    let input = call_data(
+        "transfer(address,uint256)",
+        &[Token::Address(to), Token::Uint(U256::from(5))],
+    );
+    let input = hex::decode(&input[2..]).unwrap();
+    let output = abi::encode(&[Token::Bool(true)]);
+
+    let call = decode_call(&input, Some(&output)).unwrap();
+    assert_eq!(call.signature, "transfer(address,uint256)");
+    assert_eq!(call.args["to"], to_checksum(&to, None));
+    assert_eq!(call.args["value"], "5");
+    // Unnamed return values are keyed by position
+    assert_eq!(call.returns.unwrap()["0"], true);
+
+    let call = decode_call(&keccak256("deposit()")[..4], None).unwrap();
+    assert_eq!(call.args, json!({}));
+    assert_eq!(call.returns, None);
+
+    assert!(decode_call(&keccak256("swap(uint256)")[..4], None).is_none());
+    assert!(decode_call(&[0x12], None).is_none());
+}
+
+#[tokio::test]
+async fn test_trace_transaction_returns_decoded_call_tree() {
+    let (user, router, token) = (
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0xcc),
+        Address::repeat_byte(0x70),
+    );
+    let insufficient = call_data(
+        "ERC20InsufficientBalance(address,uint256,uint256)",
+        &[
+            Token::Address(router),
+            Token::Uint(U256::from(1)),
+            Token::Uint(U256::from(9)),
+        ],
+    );
+    let trace = json!({
+        "type": "CALL",
+        "from": format!("{:?}", user),
+        "to": format!("{:?}", router),
+        "value": "0xde0b6b3a7640000",
+        "gas": "0x30000",
+        "gasUsed": "0x1d4c0",
+        "input": "0x38ed173900000000",
+        "calls": [
+            {
+                "type": "STATICCALL",
+                "from": format!("{:?}", router),
+                "to": format!("{:?}", token),
+                "gasUsed": "0xa28",
+                "input": call_data("balanceOf(address)", &[Token::Address(router)]),
+                "output": hex_bytes(&[Token::Uint(U256::from(1))]),
+            },
+            {
+                "type": "CALL",
+                "from": format!("{:?}", router),
+                "to": format!("{:?}", token),
+                "value": "0x0",
+                "gasUsed": "0x1f4",
+                "input": call_data(
+                    "transfer(address,uint256)",
+                    &[Token::Address(user), Token::Uint(U256::from(9))],
+                ),
+                "output": insufficient,
+                "error": "execution reverted",
+            },
+        ],
+        "logs": [{
+            "address": format!("{:?}", router),
+            "topics": [format!("{:?}", H256::from(keccak256("OwnershipTransferred(address,address)"))), format!("{:?}", H256::from(user)), format!("{:?}", H256::from(router))],
+            "data": "0x",
+            "position": "0x2",
+        }],
+    });
+    let hash = H256::repeat_byte(1);
+    let url = common::spawn_rpc(move |method, params| match method {
+        "debug_traceTransaction" => {
+            assert_eq!(params[0], json!(format!("{:?}", hash)));
+            assert_eq!(params[1]["tracer"], "callTracer");
+            assert_eq!(params[1]["tracerConfig"]["withLog"], true);
+            trace.clone()
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let tree = trace::trace_transaction(&client, hash, Tracer::Call, false)
+        .await
+        .unwrap();
+    assert_eq!(tree["value"], "1000000000000000000");
+    assert_eq!(tree["gas_used"], "120000");
+    assert_eq!(tree["selector"], "0x38ed1739");
+    assert!(tree.get("signature").is_none());
+    assert_eq!(tree["events"][0]["event"], "OwnershipTransferred");
+
+    let balance = &tree["calls"][0];
+    assert_eq!(balance["type"], "STATICCALL");
+    assert_eq!(balance["signature"], "balanceOf(address)");
+    assert_eq!(balance["returns"]["0"], "1");
+
+    let transfer = &tree["calls"][1];
+    assert!(transfer.get("value").is_none());
+    assert_eq!(transfer["args"]["value"], "9");
+    // Revert data is not decoded as return values
+    assert!(transfer.get("returns").is_none());
+    assert_eq!(transfer["error"], "execution reverted");
+    assert_eq!(transfer["revert_reason"]["kind"], "custom");
+    assert_eq!(transfer["revert_reason"]["args"]["needed"], "9");
+}
+
+#[tokio::test]
+async fn test_trace_call_prestate_passes_node_result_through() {
+    let diff = json!({
+        "pre": { format!("0x{}", "aa".repeat(20)): { "balance": "0x64" } },
+        "post": { format!("0x{}", "aa".repeat(20)): { "balance": "0x0" } },
+    });
+    let result = diff.clone();
+    let url = common::spawn_rpc(move |method, params| match method {
+        "debug_traceCall" => {
+            assert_eq!(params[0]["from"], json!(format!("0x{}", "aa".repeat(20))));
+            assert_eq!(params[1], json!("0x10"));
+            assert_eq!(params[2]["tracer"], "prestateTracer");
+            assert_eq!(params[2]["tracerConfig"]["diffMode"], true);
+            result.clone()
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthClient::new(&url).unwrap();
+
+    let tx = TypedTransaction::Eip1559(
+        Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(0xaa))
+            .to(Address::repeat_byte(0xbb))
+            .value(100),
+    );
+    let trace = trace::trace_call(
+        &client,
+        &tx,
+        BlockNumber::Number(16.into()).into(),
+        Tracer::Prestate,
+        true,
+    )
+    .await
+    .unwrap();
+    assert_eq!(trace, diff);
+}
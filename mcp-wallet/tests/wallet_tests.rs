@@ -40,7 +40,7 @@ fn test_import_upgrades_watch_only_to_signing() {
     // Verify watch-only state before import
     let (acc_before, _) = wallet.get_account(&alias).unwrap();
     assert!(acc_before.private_key.is_none());
-    let nonce_before = acc_before.nonce;
+    let nonce_before = acc_before.nonce(1);
 
     // Import the matching private key; should upgrade
     let returned_addr = wallet.import_private_key(pk).unwrap();
@@ -50,7 +50,7 @@ fn test_import_upgrades_watch_only_to_signing() {
     let (acc_after, resolved_addr) = wallet.get_account(&alias).unwrap();
     assert_eq!(resolved_addr, addr);
     assert!(acc_after.private_key.is_some());
-    assert_eq!(acc_after.nonce, nonce_before);
+    assert_eq!(acc_after.nonce(1), nonce_before);
 }
 
 #[test]
@@ -181,7 +181,7 @@ fn test_alias_uniqueness() {
 async fn test_sign_transaction() {
     let mut wallet = create_test_wallet();
     let (account, address) = wallet.get_account("testaccount").unwrap();
-    let initial_nonce = account.nonce;
+    let initial_nonce = account.nonce(1);
 
     let tx_request = TransactionBuilder::new()
         .chain_id(1)
@@ -204,14 +204,15 @@ async fn test_sign_transaction() {
 
     // Check if the nonce was incremented
     let (account_after, _) = wallet.get_account("testaccount").unwrap();
-    assert_eq!(account_after.nonce, initial_nonce + 1);
+    assert_eq!(account_after.nonce(1), initial_nonce + 1);
+    assert_eq!(account_after.nonce(5), 0);
 }
 
 #[tokio::test]
 async fn test_sign_transaction_with_nonce_mismatch() {
     let mut wallet = create_test_wallet();
     let (account, _) = wallet.get_account("testaccount").unwrap();
-    let incorrect_nonce = account.nonce + 1;
+    let incorrect_nonce = account.nonce(1) + 1;
 
     let tx_request = TransactionBuilder::new()
         .chain_id(1)
@@ -230,10 +231,11 @@ fn test_set_nonce() {
     let mut wallet = create_test_wallet();
     let new_nonce = 10;
 
-    wallet.set_nonce("testaccount", new_nonce).unwrap();
+    wallet.set_nonce("testaccount", 1, new_nonce).unwrap();
 
     let (account, _) = wallet.get_account("testaccount").unwrap();
-    assert_eq!(account.nonce, new_nonce);
+    assert_eq!(account.nonce(1), new_nonce);
+    assert_eq!(account.nonce(11155111), 0);
 }
 
 #[test]
@@ -257,5 +259,5 @@ fn test_save_and_load_wallet() {
     let (account, address) = loaded_wallet.get_account("saved_account").unwrap();
     assert_eq!(address, original_address);
     assert_eq!(account.aliases, vec!["saved_account"]);
-    assert_eq!(account.nonce, 0);
+    assert!(account.chains.is_empty());
 }
@@ -1,7 +1,9 @@
 //! Configuration management for the REPL application.
 
 use anyhow::{Context, Result};
+use mcp_wallet::{chain::ChainInfo, profile::WalletProfile};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -47,6 +49,12 @@ fn apply_env_defaults(cfg: &mut Config) {
         }
     }
 
+    if let Ok(v) = env::var("WALLET_PROFILE") {
+        if cfg.wallet_server.profile.is_none() {
+            cfg.wallet_server.profile = Some(v);
+        }
+    }
+
     if let Ok(v) = env::var("GAS_LIMIT") {
         if cfg.wallet_server.gas_limit.is_none() {
             if let Ok(parsed) = v.parse::<u64>() {
@@ -102,10 +110,28 @@ pub struct WalletServerConfig {
     pub enable: bool,
     /// The URL of the Ethereum RPC endpoint.
     pub rpc_url: String,
+    /// Further endpoints of the same network, used when `rpc_url` fails.
+    pub fallback_rpc_urls: Vec<String>,
+    /// Optional number of endpoints that must agree on a read.
+    pub quorum: Option<usize>,
+    /// Optional seconds an RPC endpoint may take to answer a request.
+    pub rpc_timeout_secs: Option<u64>,
+    /// Optional retries of rate-limited, failed or reset RPC requests.
+    pub rpc_max_retries: Option<u32>,
+    /// Optional requests per second sent to each RPC endpoint.
+    pub rpc_rate_limit: Option<u32>,
     /// Optional chain ID override to use with the RPC endpoint.
     pub chain_id: Option<u64>,
     /// Optional path to the wallet file managed by mcp-wallet.
     pub wallet_file: Option<PathBuf>,
+    /// Optional name of the wallet profile to start with.
+    pub profile: Option<String>,
+    /// Named wallet profiles that can be switched at runtime. The `default`
+    /// profile is derived from `rpc_url` and `wallet_file` unless defined here.
+    pub profiles: BTreeMap<String, WalletProfile>,
+    /// Chains added to the built-in chain registry, or replacing built-in
+    /// entries with the same chain id.
+    pub chains: Vec<ChainInfo>,
     /// Optional gas limit to use for transactions.
     pub gas_limit: Option<u64>,
     /// Optional gas price (in wei) to use for transactions.
@@ -120,8 +146,16 @@ impl Default for WalletServerConfig {
         Self {
             enable: true,
             rpc_url: "http://127.0.0.1:8545".to_string(),
+            fallback_rpc_urls: Vec::new(),
+            quorum: None,
+            rpc_timeout_secs: None,
+            rpc_max_retries: None,
+            rpc_rate_limit: None,
             chain_id: None,
             wallet_file: None,
+            profile: None,
+            profiles: BTreeMap::new(),
+            chains: Vec::new(),
             gas_limit: None,
             gas_price: None,
             listen_address: "127.0.0.1:8546".to_string(),
@@ -211,6 +245,7 @@ mod tests {
             "ETH_RPC_URL",
             "CHAIN_ID",
             "WALLET_FILE",
+            "WALLET_PROFILE",
             "GAS_LIMIT",
             "GAS_PRICE",
         ]);
@@ -265,6 +300,7 @@ mod tests {
                     gas_limit: None,
                     gas_price: None,
                     listen_address: "127.0.0.1:5678".to_string(),
+                    ..Default::default()
                 },
             }
         );
@@ -276,6 +312,7 @@ mod tests {
             "ETH_RPC_URL",
             "CHAIN_ID",
             "WALLET_FILE",
+            "WALLET_PROFILE",
             "GAS_LIMIT",
             "GAS_PRICE",
         ]);
@@ -318,6 +355,7 @@ mod tests {
             "ETH_RPC_URL",
             "CHAIN_ID",
             "WALLET_FILE",
+            "WALLET_PROFILE",
             "GAS_LIMIT",
             "GAS_PRICE",
         ]);
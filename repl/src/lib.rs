@@ -7,7 +7,9 @@ pub mod tools;
 
 use crate::agent::ReplAgent;
 use crate::config::GenerationConfig;
-use crate::tools::mcp_wallet::{start_mcp_wallet_server, McpWalletTool, ServerShutdown};
+use crate::tools::mcp_wallet::{
+    start_mcp_wallet_server, McpWalletTool, ServerShutdown, WalletNotifications,
+};
 use crate::tools::web_search::WebSearchTool;
 use anyhow::{Context, Result};
 use rig::client::{CompletionClient, ProviderClient};
@@ -15,7 +17,7 @@ use rig::completion::CompletionModel;
 use rig::providers::gemini;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
-use rustyline::Editor;
+use rustyline::{Editor, ExternalPrinter};
 use serde_json::json;
 use tracing::{info, warn};
 use tracing_subscriber::{fmt, EnvFilter};
@@ -70,14 +72,17 @@ pub async fn run_repl() -> Result<()> {
 
     // --- Optional: Start embedded mcp-wallet MCP server ---
     let mut wallet_shutdown: Option<ServerShutdown> = None;
-    let mut mcp_wallet_tool: Option<McpWalletTool> = None;
+    let mut mcp_wallet_tool: Option<McpWalletTool<WalletNotifications>> = None;
+    let mut notifications = None;
     if config.wallet_server.enable {
         match start_mcp_wallet_server(&config).await {
             Ok(handle) => {
                 // Build RMCP client from the client stream and construct the pass-through tool
                 let (client_stream, shutdown) = handle.into_client_stream();
                 wallet_shutdown = Some(shutdown);
-                match rmcp::serve_client((), client_stream).await {
+                let (handler, lines) = WalletNotifications::new();
+                notifications = Some(lines);
+                match rmcp::serve_client(handler, client_stream).await {
                     Ok(client) => {
                         mcp_wallet_tool = Some(McpWalletTool::new(client));
                         info!("mcp-wallet server started (in-process) and client initialized");
@@ -167,6 +172,21 @@ pub async fn run_repl() -> Result<()> {
 
     // --- REPL Loop ---
     let mut rl = Editor::<(), DefaultHistory>::new().context("Failed to create REPL editor")?;
+
+    // Print wallet notifications (e.g. subscription events) above the prompt
+    if let Some(mut lines) = notifications {
+        let mut printer = rl.create_external_printer().ok();
+        tokio::spawn(async move {
+            while let Some(line) = lines.recv().await {
+                match printer.as_mut() {
+                    Some(printer) => {
+                        let _ = printer.print(line);
+                    }
+                    None => println!("{}", line),
+                }
+            }
+        });
+    }
     //if rl.load_history("history.txt").is_err() {
     //    println!("No previous history.");
     //}
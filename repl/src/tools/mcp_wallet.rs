@@ -6,19 +6,26 @@
 //! an MCP client/agent in a later step.
 
 use anyhow::{Context, Result};
-use mcp_wallet::{eth_client::EthClient, service::WalletHandler, wallet::Wallet, WalletError};
+use mcp_wallet::{
+    chain::ChainRegistry,
+    eth_client::EthClient,
+    journal::{self, TxJournal},
+    profile::{ProfileRegistry, WalletProfile, DEFAULT_PROFILE},
+    service::WalletHandler,
+    wallet::Wallet,
+};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
-use rmcp::model::CallToolRequestParam;
-use rmcp::service::{RoleClient, RunningService, ServiceExt};
+use rmcp::model::{CallToolRequestParam, LoggingMessageNotificationParam};
+use rmcp::service::{NotificationContext, RoleClient, RunningService, ServiceExt};
+use rmcp::ClientHandler;
 use serde::Deserialize;
 use serde_json::{json, Map, Value};
-use std::path::PathBuf;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::io::{duplex, DuplexStream};
 use tokio::io::{split, ReadHalf, WriteHalf};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 use tokio::task::JoinHandle;
 
 use crate::config::Config;
@@ -88,46 +95,46 @@ impl ServerShutdown {
 /// stdio-like transport. The agent-side adapter can use this stream to talk to
 /// the server (Task 2.2).
 pub async fn start_mcp_wallet_server(cfg: &Config) -> Result<ServerHandle> {
-    // Resolve wallet file path
-    let wallet_path: PathBuf = if let Some(path) = &cfg.wallet_server.wallet_file {
-        path.clone()
-    } else {
-        dirs::home_dir()
-            .map(|mut p| {
-                p.push(".mcp-wallet.json");
-                p
-            })
-            .ok_or_else(|| WalletError::WalletError("Could not determine home directory".into()))?
-    };
+    // Build the profile set; the `default` profile falls back to the flat
+    // `rpc_url`/`wallet_file`/`chain_id` settings.
+    let mut profiles = ProfileRegistry::new();
+    for (name, profile) in &cfg.wallet_server.profiles {
+        profiles.insert(name, profile.clone())?;
+    }
+    profiles.insert_if_missing(
+        DEFAULT_PROFILE,
+        WalletProfile {
+            wallet_file: cfg.wallet_server.wallet_file.clone(),
+            rpc_url: cfg.wallet_server.rpc_url.clone(),
+            fallback_rpc_urls: cfg.wallet_server.fallback_rpc_urls.clone(),
+            quorum: cfg.wallet_server.quorum,
+            timeout_secs: cfg.wallet_server.rpc_timeout_secs,
+            max_retries: cfg.wallet_server.rpc_max_retries,
+            rate_limit: cfg.wallet_server.rpc_rate_limit,
+            chain_id: cfg.wallet_server.chain_id,
+        },
+    )?;
+    let (profile_name, profile) = profiles.select(cfg.wallet_server.profile.as_deref())?;
+    tracing::info!(profile = %profile_name, "Using wallet profile");
 
-    // Load or create wallet
-    let mut wallet = match std::fs::read_to_string(&wallet_path) {
-        Ok(contents) => {
-            tracing::info!(path = %wallet_path.display(), "Loading wallet file");
-            serde_json::from_str(&contents).unwrap_or_else(|e| {
-                tracing::warn!(error = %e, "Failed to parse wallet file, creating new");
-                Wallet::new()
-            })
-        }
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            tracing::info!(path = %wallet_path.display(), "Creating new wallet file");
-            Wallet::new()
-        }
-        Err(e) => {
-            return Err(WalletError::FileError(e).into());
-        }
-    };
-    wallet.set_file_path(&wallet_path);
+    // Load or create wallet and its transaction history
+    let wallet_path = profile.wallet_path(&profile_name)?;
+    let wallet = Wallet::load_or_create(&wallet_path)?;
+    let journal = TxJournal::load_or_create(journal::history_path(&wallet_path))?;
 
     let wallet = Arc::new(Mutex::new(wallet));
-    let eth_client = Arc::new(EthClient::new(&cfg.wallet_server.rpc_url).with_context(|| {
-        format!(
-            "Failed to create ETH RPC client for {}",
-            cfg.wallet_server.rpc_url
-        )
-    })?);
+    let eth_client = Arc::new(
+        EthClient::from_profile(profile)
+            .with_context(|| format!("Failed to create ETH RPC client for {}", profile.rpc_url))?,
+    );
 
-    let handler = WalletHandler::new(wallet.clone(), eth_client.clone());
+    let mut chains = ChainRegistry::builtin();
+    chains.extend(cfg.wallet_server.chains.iter().cloned());
+
+    let handler = WalletHandler::new(wallet.clone(), eth_client.clone())
+        .with_profiles(profiles.clone(), profile_name)
+        .with_chains(chains)
+        .with_journal(journal);
 
     // Create in-memory stdio transport using a duplex stream
     let (server_end, client_end) = duplex(64 * 1024);
@@ -146,17 +153,11 @@ pub async fn start_mcp_wallet_server(cfg: &Config) -> Result<ServerHandle> {
         }
 
         // After the server shuts down, save the wallet if it has changed.
-        let wallet = wallet.lock().await;
-        if wallet.is_dirty() {
-            if let Some(path) = wallet.file_path() {
-                tracing::info!(path = %path.display(), "Saving wallet file");
-                let contents = serde_json::to_string_pretty(&*wallet)
-                    .context("failed to serialize wallet for saving")?;
-                std::fs::write(path, contents).with_context(|| {
-                    format!("failed to write wallet file to {}", path.display())
-                })?;
-            }
-        }
+        wallet
+            .lock()
+            .await
+            .save()
+            .context("failed to save wallet file")?;
 
         Ok(())
     });
@@ -167,6 +168,75 @@ pub async fn start_mcp_wallet_server(cfg: &Config) -> Result<ServerHandle> {
     })
 }
 
+// =============================
+// Client handler: notifications
+// =============================
+
+/// RMCP client handler that turns the server's logging notifications, such as
+/// the events of chain subscriptions, into printable lines.
+///
+/// The lines are sent to a channel instead of being printed directly so the
+/// REPL can print them above the prompt without garbling the input line.
+#[derive(Clone)]
+pub struct WalletNotifications {
+    lines: mpsc::UnboundedSender<String>,
+}
+
+impl WalletNotifications {
+    /// Create a handler and the receiver of its formatted lines.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<String>) {
+        let (lines, receiver) = mpsc::unbounded_channel();
+        (Self { lines }, receiver)
+    }
+}
+
+impl ClientHandler for WalletNotifications {
+    fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) -> impl std::future::Future<Output = ()> + Send + '_ {
+        let _ = self.lines.send(format_notification(&params));
+        std::future::ready(())
+    }
+}
+
+/// Formats a logging notification from mcp-wallet as one line.
+///
+/// Subscription events are summarized by kind; other messages are shown with
+/// their level and logger.
+pub fn format_notification(params: &LoggingMessageNotificationParam) -> String {
+    let data = &params.data;
+    if params.logger.as_deref() != Some("subscription") {
+        let logger = params.logger.as_deref().unwrap_or("mcp-wallet");
+        return format!("[{:?}] {}: {}", params.level, logger, data);
+    }
+
+    let id = &data["subscription"];
+    let event = &data["data"];
+    let text = match data["kind"].as_str() {
+        Some("new_heads") => format!(
+            "new block {} ({} txs)",
+            event["number"], event["transaction_count"]
+        ),
+        Some("logs") => format!(
+            "{} from {} in block {}{}",
+            event["event"].as_str().unwrap_or("log"),
+            event["address"].as_str().unwrap_or("?"),
+            event["block_number"],
+            event
+                .get("args")
+                .map(|a| format!(" {}", a))
+                .unwrap_or_default()
+        ),
+        Some("pending_transactions") => {
+            format!("pending tx {}", event["hash"].as_str().unwrap_or("?"))
+        }
+        _ => event.to_string(),
+    };
+    format!("[subscription {}] {}", id, text)
+}
+
 // =============================
 // Agent Tool: McpWalletTool
 // =============================
@@ -184,13 +254,13 @@ pub struct McpWalletArgs {
 }
 
 #[derive(Clone)]
-pub struct McpWalletTool {
-    client: Arc<RunningService<RoleClient, ()>>, // returned by rmcp::serve_client
+pub struct McpWalletTool<H: ClientHandler> {
+    client: Arc<RunningService<RoleClient, H>>, // returned by rmcp::serve_client
 }
 
-impl McpWalletTool {
+impl<H: ClientHandler> McpWalletTool<H> {
     /// Create a new tool from the RMCP client returned by `serve_client`.
-    pub fn new(client: RunningService<RoleClient, ()>) -> Self {
+    pub fn new(client: RunningService<RoleClient, H>) -> Self {
         Self {
             client: Arc::new(client),
         }
@@ -205,7 +275,7 @@ pub enum McpWalletToolError {
     Service(#[from] rmcp::ServiceError),
 }
 
-impl Tool for McpWalletTool {
+impl<H: ClientHandler> Tool for McpWalletTool<H> {
     const NAME: &'static str = "mcp_wallet";
 
     type Error = McpWalletToolError;
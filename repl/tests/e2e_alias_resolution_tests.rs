@@ -31,6 +31,7 @@ async fn setup_wallet_server_with_anvil(
             gas_limit: None,
             gas_price: None,
             listen_address: "127.0.0.1:0".to_string(),
+            ..Default::default()
         },
         ..Default::default()
     };
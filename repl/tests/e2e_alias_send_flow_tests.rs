@@ -29,6 +29,7 @@ async fn e2e_alias_send_flow() -> Result<()> {
             gas_limit: None,
             gas_price: None,
             listen_address: "127.0.0.1:0".to_string(),
+            ..Default::default()
         },
         ..Default::default()
     };
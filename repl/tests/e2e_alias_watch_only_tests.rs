@@ -26,6 +26,7 @@ async fn e2e_alias_unknown_address_creates_watch_only() -> Result<()> {
             gas_limit: None,
             gas_price: None,
             listen_address: "127.0.0.1:0".to_string(),
+            ..Default::default()
         },
         ..Default::default()
     };
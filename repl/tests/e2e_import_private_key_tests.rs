@@ -26,6 +26,7 @@ async fn e2e_import_private_key_creates_signing_account() -> Result<()> {
             gas_limit: None,
             gas_price: None,
             listen_address: "127.0.0.1:0".to_string(),
+            ..Default::default()
         },
         ..Default::default()
     };
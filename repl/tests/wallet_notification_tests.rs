@@ -0,0 +1,48 @@
+//! Tests for how mcp-wallet notifications are shown in the REPL.
+
+use repl::tools::mcp_wallet::format_notification;
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use serde_json::{json, Value};
+
+fn notification(logger: &str, data: Value) -> LoggingMessageNotificationParam {
+    LoggingMessageNotificationParam {
+        level: LoggingLevel::Info,
+        logger: Some(logger.to_string()),
+        data,
+    }
+}
+
+#[test]
+fn test_format_subscription_notifications() {
+    let block = notification(
+        "subscription",
+        json!({"subscription": 1, "kind": "new_heads", "data": {"number": 17, "transaction_count": 3}}),
+    );
+    assert_eq!(
+        format_notification(&block),
+        "[subscription 1] new block 17 (3 txs)"
+    );
+
+    let log = notification(
+        "subscription",
+        json!({"subscription": 2, "kind": "logs", "data": {
+            "block_number": 18, "address": "0xToken", "event": "Transfer", "args": {"value": "5"}
+        }}),
+    );
+    assert_eq!(
+        format_notification(&log),
+        r#"[subscription 2] Transfer from 0xToken in block 18 {"value":"5"}"#
+    );
+
+    let pending = notification(
+        "subscription",
+        json!({"subscription": 3, "kind": "pending_transactions", "data": {"hash": "0xabc"}}),
+    );
+    assert_eq!(
+        format_notification(&pending),
+        "[subscription 3] pending tx 0xabc"
+    );
+
+    let other = notification("wallet", json!("saved"));
+    assert_eq!(format_notification(&other), r#"[Info] wallet: "saved""#);
+}